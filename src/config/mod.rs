@@ -0,0 +1,526 @@
+use toml::{self, Value};
+use toml::value::Table;
+
+use irc::{Address, Proxy, ProxyKind, Transport, DEFAULT_PORT, DEFAULT_TLS_PORT, DEFAULT_SOCKS5_PORT, DEFAULT_HTTP_PORT};
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(PathBuf, io::Error),
+    Parse(PathBuf, toml::de::Error),
+    Invalid(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ConfigError::Io(ref path, ref e) => write!(f, "{}: {}", path.display(), e),
+            ConfigError::Parse(ref path, ref e) => write!(f, "{}: {}", path.display(), e),
+            ConfigError::Invalid(ref msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Identity {
+    pub nicks: Vec<String>,
+    pub username: Option<String>,
+    pub realname: String,
+    pub mode: u8,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sasl {
+    pub username: String,
+    pub password: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum IdentifyMethod {
+    NickServ { account: Option<String>, password: String },
+    Commands(Vec<String>),
+}
+
+/// Identification run once the network has accepted registration.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Identify {
+    pub method: IdentifyMethod,
+    /// The services nick to message, and whose notices confirm identification.
+    pub service: String,
+    /// Text of the services notice sent once identified.
+    pub pattern: String,
+    /// Texts of services notices that mean identification failed.
+    pub failure_patterns: Vec<String>,
+    /// Holds back autojoin until identification is confirmed.
+    pub wait: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AutoJoin {
+    pub channel: String,
+    pub key: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct NetworkConfig {
+    pub name: String,
+    pub address: Address,
+    pub transport: Transport,
+    pub password: Option<String>,
+    pub sasl: Option<Sasl>,
+    pub identify: Option<Identify>,
+    pub nicks: Vec<String>,
+    pub username: Option<String>,
+    pub realname: Option<String>,
+    pub autojoin: Vec<AutoJoin>,
+    pub commands: Vec<String>,
+    pub autoconnect: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct UiConfig {
+    pub nick_width: i32,
+    pub scroll_lines: i32,
+    /// A strftime-style format for the timestamp column, empty to hide it.
+    pub timestamp_format: String,
+    /// How many messages to fetch at a time from servers with chathistory, 0 to disable it.
+    pub history_lines: u32,
+    /// Whether to show the message of the day on connecting. It is kept for `/motd` either way.
+    pub show_motd: bool,
+    /// Whether channels show their users down the right-hand side; Alt+N toggles it.
+    pub nick_list: bool,
+}
+
+/// Settings given on the command line, applied on top of the file every time it is loaded.
+#[derive(Clone, Debug, Default)]
+pub struct Overrides {
+    pub nick: Option<String>,
+    pub realname: Option<String>,
+    pub server: Option<String>,
+    pub port: Option<u16>,
+    pub tls: bool,
+    pub password: Option<String>,
+    /// Extra channels for the `server` network, from an `irc://` URL.
+    pub channels: Vec<AutoJoin>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Config {
+    pub identity: Identity,
+    pub networks: Vec<NetworkConfig>,
+    pub ui: UiConfig,
+    /// Command aliases by name, from the `[aliases]` table.
+    pub aliases: BTreeMap<String, String>,
+}
+
+impl Default for Identity {
+    /// Named after the login user, keeping only the characters a nick may use.
+    fn default() -> Identity {
+        let user = env::var("USER").or(env::var("LOGNAME")).unwrap_or("".to_string());
+        let nick: String = user.chars()
+            .filter(|c| c.is_ascii_alphanumeric() || "-_[]\\`^{}|".contains(*c))
+            .collect();
+        let nick = if nick.is_empty() || nick.starts_with(|c: char| c.is_digit(10) || c == '-') {
+            "guest".to_string()
+        } else {
+            nick
+        };
+        Identity {
+            nicks: vec![nick.clone()],
+            username: None,
+            realname: if user.is_empty() { nick } else { user },
+            mode: 8,
+        }
+    }
+}
+
+impl Default for UiConfig {
+    fn default() -> UiConfig {
+        UiConfig {
+            nick_width: 14,
+            scroll_lines: 5,
+            timestamp_format: "%H:%M".to_string(),
+            history_lines: 50,
+            show_motd: true,
+            nick_list: true,
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            identity: Identity::default(),
+            networks: Vec::new(),
+            ui: UiConfig::default(),
+            aliases: BTreeMap::new(),
+        }
+    }
+}
+
+impl NetworkConfig {
+    /// A network that was not named in the configuration file, using the default identity.
+    pub fn from_address(address: Address) -> NetworkConfig {
+        NetworkConfig {
+            name: address.host.clone(),
+            address: address,
+            transport: Transport::Direct,
+            password: None,
+            sasl: None,
+            identify: None,
+            nicks: Vec::new(),
+            username: None,
+            realname: None,
+            autojoin: Vec::new(),
+            commands: Vec::new(),
+            autoconnect: true,
+        }
+    }
+
+    /// Adds a channel to join on connect, replacing any entry for the same channel so that
+    /// joining it again doesn't list it twice.
+    pub fn add_autojoin(&mut self, channel: String, key: Option<String>) {
+        let lower = channel.to_lowercase();
+        self.autojoin.retain(|x| x.channel.to_lowercase() != lower);
+        self.autojoin.push(AutoJoin { channel: channel, key: key });
+    }
+
+    pub fn nicks<'a>(&'a self, identity: &'a Identity) -> &'a [String] {
+        if self.nicks.len() > 0 { &self.nicks } else { &identity.nicks }
+    }
+
+    pub fn username<'a>(&'a self, identity: &'a Identity) -> &'a str {
+        self.username.as_ref()
+            .or(identity.username.as_ref())
+            .or(self.nicks(identity).first())
+            .map(|x| &**x)
+            .unwrap_or("")
+    }
+
+    pub fn realname<'a>(&'a self, identity: &'a Identity) -> &'a str {
+        self.realname.as_ref().unwrap_or(&identity.realname)
+    }
+}
+
+impl Config {
+    /// `$XDG_CONFIG_HOME/mass-irc/config.toml`, falling back to `~/.config` when unset.
+    pub fn default_path() -> Option<PathBuf> {
+        let base = match env::var_os("XDG_CONFIG_HOME") {
+            Some(dir) => Some(PathBuf::from(dir)),
+            None => env::var_os("HOME").map(|home| Path::new(&home).join(".config")),
+        };
+        base.map(|dir| dir.join("mass-irc").join("config.toml"))
+    }
+
+    /// Loads the file at `path`. A missing file is not an error and gives the defaults.
+    pub fn load(path: &Path) -> Result<Config, ConfigError> {
+        let mut contents = String::new();
+        match File::open(path) {
+            Ok(mut file) => {
+                try!(file.read_to_string(&mut contents)
+                     .map_err(|e| ConfigError::Io(path.to_path_buf(), e)));
+            },
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(Config::default()),
+            Err(e) => return Err(ConfigError::Io(path.to_path_buf(), e)),
+        }
+
+        Config::parse(&contents).map_err(|e| match e {
+            ConfigError::Parse(_, e) => ConfigError::Parse(path.to_path_buf(), e),
+            e => e,
+        })
+    }
+
+    pub fn parse(contents: &str) -> Result<Config, ConfigError> {
+        let root: Table = try!(toml::from_str(contents)
+                               .map_err(|e| ConfigError::Parse(PathBuf::new(), e)));
+        let mut config = Config::default();
+
+        if let Some(identity) = try!(get_table(&root, "identity")) {
+            if let Some(nicks) = try!(get_strings(identity, "nicks")) {
+                if nicks.len() == 0 {
+                    return Err(ConfigError::Invalid("`identity.nicks` must not be empty".to_string()));
+                }
+                config.identity.nicks = nicks;
+            }
+            config.identity.username = try!(get_string(identity, "username"));
+            if let Some(realname) = try!(get_string(identity, "realname")) {
+                config.identity.realname = realname;
+            }
+            match try!(get_integer(identity, "mode")) {
+                Some(mode) if mode >= 0 && mode <= 0xff => config.identity.mode = mode as u8,
+                Some(mode) => return Err(ConfigError::Invalid(format!("Invalid `identity.mode` {}", mode))),
+                None => {},
+            }
+        }
+
+        if let Some(ui) = try!(get_table(&root, "ui")) {
+            match try!(get_integer(ui, "nick_width")) {
+                Some(width) if width > 0 && width <= i32::max_value() as i64 => config.ui.nick_width = width as i32,
+                Some(width) => return Err(ConfigError::Invalid(format!("Invalid `ui.nick_width` {}", width))),
+                None => {},
+            }
+            match try!(get_integer(ui, "scroll_lines")) {
+                Some(lines) if lines > 0 && lines <= i32::max_value() as i64 => config.ui.scroll_lines = lines as i32,
+                Some(lines) => return Err(ConfigError::Invalid(format!("Invalid `ui.scroll_lines` {}", lines))),
+                None => {},
+            }
+            if let Some(format) = try!(get_string(ui, "timestamp_format")) {
+                config.ui.timestamp_format = format;
+            }
+            match try!(get_integer(ui, "history_lines")) {
+                Some(lines) if lines >= 0 && lines <= u32::max_value() as i64 => config.ui.history_lines = lines as u32,
+                Some(lines) => return Err(ConfigError::Invalid(format!("Invalid `ui.history_lines` {}", lines))),
+                None => {},
+            }
+            if let Some(show) = try!(get_bool(ui, "show_motd")) {
+                config.ui.show_motd = show;
+            }
+            if let Some(show) = try!(get_bool(ui, "nick_list")) {
+                config.ui.nick_list = show;
+            }
+        }
+
+        if let Some(aliases) = try!(get_table(&root, "aliases")) {
+            for name in aliases.keys() {
+                let expansion = try!(get_string(aliases, name)).unwrap_or("".to_string());
+                config.aliases.insert(name.to_lowercase(), expansion);
+            }
+        }
+
+        if let Some(networks) = root.get("network") {
+            let networks = match networks.as_array() {
+                Some(n) => n,
+                None => return Err(ConfigError::Invalid("`network` must be an array of tables, use [[network]]".to_string())),
+            };
+            for network in networks {
+                match network.as_table() {
+                    Some(network) => config.networks.push(try!(Config::network(network))),
+                    None => return Err(ConfigError::Invalid("`network` entries must be tables".to_string())),
+                }
+            }
+        }
+
+        Ok(config)
+    }
+
+    fn network(table: &Table) -> Result<NetworkConfig, ConfigError> {
+        let host = match try!(get_string(table, "host")) {
+            Some(host) => host,
+            None => return Err(ConfigError::Invalid("Every [[network]] needs a `host`".to_string())),
+        };
+        let tls = try!(get_bool(table, "tls")).unwrap_or(false);
+        let port = match try!(get_integer(table, "port")) {
+            Some(port) if port > 0 && port <= 0xffff => port as u16,
+            Some(port) => return Err(ConfigError::Invalid(format!("Invalid port {} for {}", port, host))),
+            None => if tls { DEFAULT_TLS_PORT } else { DEFAULT_PORT },
+        };
+
+        let sasl = match try!(get_table(table, "sasl")) {
+            Some(sasl) => {
+                match (try!(get_string(sasl, "username")), try!(get_string(sasl, "password"))) {
+                    (Some(username), Some(password)) => Some(Sasl { username: username, password: password }),
+                    _ => return Err(ConfigError::Invalid(format!("`sasl` for {} needs a username and password", host))),
+                }
+            },
+            None => None,
+        };
+
+        let mut transports = Vec::new();
+        if let Some(command) = try!(get_string(table, "proxy_command")) {
+            transports.push(Transport::Command(command));
+        }
+        if let Some(path) = try!(get_string(table, "socket")) {
+            transports.push(Transport::Unix(PathBuf::from(path)));
+        }
+        if let Some(proxy) = try!(get_table(table, "proxy")) {
+            let kind = match try!(get_string(proxy, "type")).as_ref().map(|x| &**x) {
+                Some("socks5") | None => ProxyKind::Socks5,
+                Some("http") => ProxyKind::Http,
+                Some(other) => return Err(ConfigError::Invalid(format!("Unknown proxy type `{}` for {}, expected socks5 or http", other, host))),
+            };
+            let proxy_host = match try!(get_string(proxy, "host")) {
+                Some(h) => h,
+                None => return Err(ConfigError::Invalid(format!("`proxy` for {} needs a `host`", host))),
+            };
+            let port = match try!(get_integer(proxy, "port")) {
+                Some(port) if port > 0 && port <= 0xffff => port as u16,
+                Some(port) => return Err(ConfigError::Invalid(format!("Invalid proxy port {} for {}", port, host))),
+                None => if kind == ProxyKind::Socks5 { DEFAULT_SOCKS5_PORT } else { DEFAULT_HTTP_PORT },
+            };
+            let mut p = Proxy::new(kind, proxy_host, port);
+            p.username = try!(get_string(proxy, "username"));
+            p.password = try!(get_string(proxy, "password"));
+            p.remote_dns = try!(get_bool(proxy, "remote_dns")).unwrap_or(true);
+            transports.push(Transport::Proxy(p));
+        }
+        if transports.len() > 1 {
+            return Err(ConfigError::Invalid(format!("Only one of `proxy`, `proxy_command` and `socket` may be set for {}", host)));
+        }
+
+        let identify = match try!(get_table(table, "identify")) {
+            Some(identify) => {
+                let method = match (try!(get_string(identify, "password")), try!(get_strings(identify, "commands"))) {
+                    (Some(password), None) => IdentifyMethod::NickServ {
+                        account: try!(get_string(identify, "account")),
+                        password: password,
+                    },
+                    (None, Some(commands)) => IdentifyMethod::Commands(commands),
+                    _ => return Err(ConfigError::Invalid(format!("`identify` for {} needs either a `password` or `commands`", host))),
+                };
+                Some(Identify {
+                    method: method,
+                    service: try!(get_string(identify, "service")).unwrap_or("NickServ".to_string()),
+                    pattern: try!(get_string(identify, "pattern")).unwrap_or("You are now identified".to_string()),
+                    failure_patterns: try!(get_strings(identify, "failure_patterns")).unwrap_or(
+                        vec!["Invalid password".to_string(), "Password incorrect".to_string(),
+                             "is not a registered nickname".to_string(), "isn't registered".to_string()]),
+                    wait: try!(get_bool(identify, "wait")).unwrap_or(true),
+                })
+            },
+            None => None,
+        };
+
+        let mut autojoin = Vec::new();
+        if let Some(channels) = table.get("autojoin") {
+            for channel in channels.as_array().map(|x| &x[..]).unwrap_or(&[]) {
+                match *channel {
+                    Value::String(ref name) => autojoin.push(AutoJoin { channel: name.clone(), key: None }),
+                    Value::Table(ref t) => {
+                        match try!(get_string(t, "channel")) {
+                            Some(name) => autojoin.push(AutoJoin { channel: name, key: try!(get_string(t, "key")) }),
+                            None => return Err(ConfigError::Invalid(format!("`autojoin` entry for {} needs a `channel`", host))),
+                        }
+                    },
+                    _ => return Err(ConfigError::Invalid(format!("Invalid `autojoin` entry for {}", host))),
+                }
+            }
+        }
+
+        Ok(NetworkConfig {
+            name: try!(get_string(table, "name")).unwrap_or(host.clone()),
+            address: Address::new(host, port, tls),
+            transport: transports.pop().unwrap_or(Transport::Direct),
+            password: try!(get_string(table, "password")),
+            sasl: sasl,
+            identify: identify,
+            nicks: try!(get_strings(table, "nicks")).unwrap_or(Vec::new()),
+            username: try!(get_string(table, "username")),
+            realname: try!(get_string(table, "realname")),
+            autojoin: autojoin,
+            commands: try!(get_strings(table, "commands")).unwrap_or(Vec::new()),
+            autoconnect: try!(get_bool(table, "autoconnect")).unwrap_or(true),
+        })
+    }
+
+    pub fn find_network(&self, name: &str) -> Option<&NetworkConfig> {
+        self.networks.iter().find(|x| x.name == name)
+    }
+
+    /// Applies command line overrides. A `--server` naming a configured network connects to
+    /// that network only, any other value replaces the configured networks with a new one.
+    pub fn apply(&mut self, overrides: &Overrides) {
+        if let Some(ref nick) = overrides.nick {
+            self.identity.nicks = vec![nick.clone()];
+            for network in self.networks.iter_mut() {
+                network.nicks.clear();
+            }
+        }
+        if let Some(ref realname) = overrides.realname {
+            self.identity.realname = realname.clone();
+            for network in self.networks.iter_mut() {
+                network.realname = None;
+            }
+        }
+
+        if let Some(ref server) = overrides.server {
+            let mut network = match self.networks.iter().find(|x| &x.name == server || &x.address.host == server) {
+                Some(n) => n.clone(),
+                None => NetworkConfig::from_address(Address::new(server.clone(), DEFAULT_PORT, false)),
+            };
+            if overrides.tls {
+                network.address.tls = true;
+                if overrides.port.is_none() && network.address.port == DEFAULT_PORT {
+                    network.address.port = DEFAULT_TLS_PORT;
+                }
+            }
+            if let Some(port) = overrides.port {
+                network.address.port = port;
+            }
+            for join in overrides.channels.iter() {
+                if !network.autojoin.iter().any(|x| x.channel == join.channel) {
+                    network.autojoin.push(join.clone());
+                }
+            }
+            network.autoconnect = true;
+            for other in self.networks.iter_mut() {
+                other.autoconnect = false;
+            }
+            self.networks.retain(|x| x.name != network.name);
+            self.networks.insert(0, network);
+        }
+
+        if self.networks.len() == 0 {
+            let port = overrides.port.unwrap_or(if overrides.tls { DEFAULT_TLS_PORT } else { DEFAULT_PORT });
+            let address = Address::new("127.0.0.1".to_string(), port, overrides.tls);
+            self.networks.push(NetworkConfig::from_address(address));
+        }
+
+        if let Some(ref password) = overrides.password {
+            for network in self.networks.iter_mut().filter(|x| x.autoconnect) {
+                network.password = Some(password.clone());
+            }
+        }
+    }
+}
+
+fn invalid_type(key: &str, expected: &str) -> ConfigError {
+    ConfigError::Invalid(format!("`{}` must be {}", key, expected))
+}
+
+fn get_table<'a>(table: &'a Table, key: &str) -> Result<Option<&'a Table>, ConfigError> {
+    match table.get(key) {
+        Some(v) => v.as_table().map(Some).ok_or(invalid_type(key, "a table")),
+        None => Ok(None),
+    }
+}
+
+fn get_string(table: &Table, key: &str) -> Result<Option<String>, ConfigError> {
+    match table.get(key) {
+        Some(v) => v.as_str().map(|x| Some(x.to_string())).ok_or(invalid_type(key, "a string")),
+        None => Ok(None),
+    }
+}
+
+fn get_integer(table: &Table, key: &str) -> Result<Option<i64>, ConfigError> {
+    match table.get(key) {
+        Some(v) => v.as_integer().map(Some).ok_or(invalid_type(key, "an integer")),
+        None => Ok(None),
+    }
+}
+
+fn get_bool(table: &Table, key: &str) -> Result<Option<bool>, ConfigError> {
+    match table.get(key) {
+        Some(v) => v.as_bool().map(Some).ok_or(invalid_type(key, "true or false")),
+        None => Ok(None),
+    }
+}
+
+fn get_strings(table: &Table, key: &str) -> Result<Option<Vec<String>>, ConfigError> {
+    match table.get(key) {
+        Some(v) => {
+            let values = try!(v.as_array().ok_or(invalid_type(key, "an array of strings")));
+            let mut strings = Vec::new();
+            for value in values {
+                strings.push(try!(value.as_str().ok_or(invalid_type(key, "an array of strings"))).to_string());
+            }
+            Ok(Some(strings))
+        },
+        None => Ok(None),
+    }
+}
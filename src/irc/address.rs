@@ -0,0 +1,63 @@
+use std::fmt;
+
+pub const DEFAULT_PORT: u16 = 6667;
+pub const DEFAULT_TLS_PORT: u16 = 6697;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Address {
+    pub host: String,
+    pub port: u16,
+    pub tls: bool,
+}
+
+impl Address {
+    pub fn new(host: String, port: u16, tls: bool) -> Address {
+        Address {
+            host: host,
+            port: port,
+            tls: tls,
+        }
+    }
+
+    /// Parses `host`, `host:port` or `[v6-host]:port`. The port defaults to the
+    /// standard plain or TLS port.
+    pub fn parse(addr: &str, tls: bool) -> Option<Address> {
+        let addr = addr.trim();
+        let (host, port) = if addr.starts_with('[') {
+            match addr.find(']') {
+                Some(end) => (&addr[1..end], addr[end + 1..].trim_left_matches(':')),
+                None => return None,
+            }
+        } else {
+            let mut parts = addr.splitn(2, ':');
+            (parts.next().unwrap_or(""), parts.next().unwrap_or(""))
+        };
+
+        if host.len() == 0 { return None; }
+
+        let port = if port.len() == 0 {
+            if tls { DEFAULT_TLS_PORT } else { DEFAULT_PORT }
+        } else {
+            match port.parse() {
+                Ok(port) => port,
+                Err(_) => return None,
+            }
+        };
+
+        Some(Address::new(host.to_string(), port, tls))
+    }
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.host.contains(':') {
+            try!(write!(f, "[{}]:{}", self.host, self.port));
+        } else {
+            try!(write!(f, "{}:{}", self.host, self.port));
+        }
+        if self.tls {
+            try!(write!(f, " (TLS)"));
+        }
+        Ok(())
+    }
+}
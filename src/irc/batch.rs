@@ -0,0 +1,71 @@
+use std::collections::HashMap;
+
+use irc::{ChannelModes, ClientEvent, Command, CommandType};
+
+struct OpenBatch {
+    kind: String,
+    params: Vec<String>,
+    /// The batch this one is nested in.
+    parent: Option<String>,
+    messages: Vec<ClientEvent>,
+}
+
+/// Groups the commands tagged with an open IRCv3 batch, so each batch arrives as a single
+/// `ClientEvent::Batch`. A batch opened inside another is delivered as one of its messages.
+pub struct Batches {
+    open: HashMap<String, OpenBatch>,
+}
+
+impl Batches {
+    pub fn new() -> Batches {
+        Batches {
+            open: HashMap::new(),
+        }
+    }
+
+    /// Feeds a command received from the server, read with the server's `modes`. Returns
+    /// `None` when it is not part of a batch, otherwise the events to send now, if any.
+    pub fn handle(&mut self, cmd: &Command, modes: &ChannelModes) -> Option<Vec<ClientEvent>> {
+        let parent = cmd.get_tag("batch")
+            .and_then(|x| if self.open.contains_key(x) { Some(x.to_string()) } else { None });
+
+        if cmd.command == CommandType::Batch {
+            let reference = cmd.get_param(0).unwrap_or("");
+            if reference.starts_with('+') && reference.len() > 1 {
+                self.open.insert(reference[1..].to_string(), OpenBatch {
+                    kind: cmd.get_param(1).unwrap_or("").to_string(),
+                    params: cmd.params.data.iter().skip(2).cloned().collect(),
+                    parent: parent,
+                    messages: Vec::new(),
+                });
+                return Some(Vec::new());
+            }
+            if reference.starts_with('-') {
+                return self.open.remove(&reference[1..]).map(|batch| self.close(batch));
+            }
+        }
+
+        match parent {
+            Some(parent) => {
+                self.open.get_mut(&parent).unwrap().messages.push(ClientEvent::parse(cmd.clone(), modes));
+                Some(Vec::new())
+            },
+            None => None,
+        }
+    }
+
+    fn close(&mut self, batch: OpenBatch) -> Vec<ClientEvent> {
+        let event = ClientEvent::Batch {
+            kind: batch.kind,
+            params: batch.params,
+            messages: batch.messages,
+        };
+        match batch.parent.and_then(|x| self.open.get_mut(&x)) {
+            Some(parent) => {
+                parent.messages.push(event);
+                Vec::new()
+            },
+            None => vec![event],
+        }
+    }
+}
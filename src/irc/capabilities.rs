@@ -0,0 +1,189 @@
+use irc::{Command, CommandBuilder, CommandType};
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum CapState {
+    Idle,
+    Listing,
+    Requesting,
+    Authenticating,
+    Done,
+}
+
+/// Negotiates IRCv3 capabilities, and SASL PLAIN authentication when credentials are given,
+/// while the connection registers.
+pub struct Capabilities {
+    requested: Vec<String>,
+    sasl: Option<(String, String)>,
+    available: Vec<String>,
+    enabled: Vec<String>,
+    state: CapState,
+}
+
+impl Capabilities {
+    pub fn new() -> Capabilities {
+        Capabilities {
+            requested: Vec::new(),
+            sasl: None,
+            available: Vec::new(),
+            enabled: Vec::new(),
+            state: CapState::Idle,
+        }
+    }
+
+    pub fn request(mut self, cap: &str) -> Self {
+        self.requested.push(cap.to_string());
+
+        self
+    }
+
+    pub fn sasl(mut self, username: String, password: String) -> Self {
+        self.sasl = Some((username, password));
+
+        self
+    }
+
+    pub fn enabled(&self) -> &[String] {
+        &self.enabled
+    }
+
+    pub fn is_enabled(&self, cap: &str) -> bool {
+        self.enabled.iter().any(|x| x == cap)
+    }
+
+    /// Commands to send as soon as the connection opens, ahead of NICK and USER.
+    pub fn start(&mut self) -> Vec<Command> {
+        if self.requested.len() == 0 && self.sasl.is_none() {
+            self.state = CapState::Done;
+            return Vec::new();
+        }
+        self.state = CapState::Listing;
+        vec![Self::cap(&["LS", "302"])]
+    }
+
+    /// Feeds a command received from the server, returning the replies to send.
+    pub fn handle(&mut self, cmd: &Command) -> Vec<Command> {
+        match cmd.command {
+            CommandType::Cap => self.handle_cap(cmd),
+            CommandType::Authenticate if self.state == CapState::Authenticating => {
+                if cmd.get_param(0) == Some("+") { self.authenticate() } else { Vec::new() }
+            },
+            CommandType::Rpl_SaslSuccess |
+            CommandType::Err_SaslFail |
+            CommandType::Err_SaslTooLong |
+            CommandType::Err_SaslAborted |
+            CommandType::Err_SaslAlready if self.state == CapState::Authenticating => self.end(),
+            CommandType::Err_UnknownCommand if cmd.get_param(1) == Some("CAP") => {
+                self.state = CapState::Done;
+                Vec::new()
+            },
+            CommandType::Rpl_Welcome => {
+                self.state = CapState::Done;
+                Vec::new()
+            },
+            _ => Vec::new(),
+        }
+    }
+
+    fn handle_cap(&mut self, cmd: &Command) -> Vec<Command> {
+        let list: Vec<String> = cmd.params.data.last()
+            .map(|x| x.split_whitespace().map(|x| x.to_string()).collect())
+            .unwrap_or(Vec::new());
+
+        match cmd.get_param(1).unwrap_or("") {
+            "LS" => {
+                for cap in list {
+                    self.available.push(cap.split('=').next().unwrap_or("").to_string());
+                }
+                let more = cmd.params.data.len() > 3 && cmd.get_param(2) == Some("*");
+                if more || self.state != CapState::Listing { return Vec::new(); }
+
+                let mut wanted: Vec<String> = self.requested.iter()
+                    .filter(|x| self.available.contains(x))
+                    .cloned()
+                    .collect();
+                if self.sasl.is_some() && self.available.iter().any(|x| x == "sasl") {
+                    wanted.push("sasl".to_string());
+                }
+                if wanted.len() == 0 { return self.end(); }
+
+                self.state = CapState::Requesting;
+                vec![Self::cap(&["REQ", &*wanted.join(" ")])]
+            },
+            "ACK" => {
+                for cap in list {
+                    if cap.starts_with('-') {
+                        self.enabled.retain(|x| x != &cap[1..]);
+                    } else if !self.is_enabled(&cap) {
+                        self.enabled.push(cap);
+                    }
+                }
+                if self.state != CapState::Requesting { return Vec::new(); }
+
+                if self.sasl.is_some() && self.is_enabled("sasl") {
+                    self.state = CapState::Authenticating;
+                    vec![Self::command(CommandType::Authenticate, &["PLAIN"])]
+                } else {
+                    self.end()
+                }
+            },
+            "NAK" if self.state == CapState::Requesting => self.end(),
+            "DEL" => {
+                for cap in list {
+                    self.enabled.retain(|x| x != &cap);
+                }
+                Vec::new()
+            },
+            _ => Vec::new(),
+        }
+    }
+
+    fn authenticate(&self) -> Vec<Command> {
+        let (ref username, ref password) = match self.sasl {
+            Some(ref sasl) => sasl.clone(),
+            None => return Vec::new(),
+        };
+        let encoded = base64(format!("{}\0{}\0{}", username, username, password).as_bytes());
+
+        // Payloads are sent in 400 byte chunks, a full final chunk is followed by `+`.
+        let mut commands: Vec<Command> = encoded.as_bytes().chunks(400)
+            .map(|x| Self::command(CommandType::Authenticate, &[&*String::from_utf8_lossy(x)]))
+            .collect();
+        if encoded.len() % 400 == 0 {
+            commands.push(Self::command(CommandType::Authenticate, &["+"]));
+        }
+        commands
+    }
+
+    fn end(&mut self) -> Vec<Command> {
+        self.state = CapState::Done;
+        vec![Self::cap(&["END"])]
+    }
+
+    fn cap(params: &[&str]) -> Command {
+        Self::command(CommandType::Cap, params)
+    }
+
+    fn command(command: CommandType, params: &[&str]) -> Command {
+        CommandBuilder::new()
+            .command(command)
+            .add_params(params.iter().map(|x| x.to_string()).collect())
+            .build().unwrap()
+    }
+}
+
+pub fn base64(input: &[u8]) -> String {
+    const TABLE: &'static [u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::new();
+    for chunk in input.chunks(3) {
+        let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = ((b[0] as u32) << 16) | ((b[1] as u32) << 8) | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(TABLE[((n >> (18 - i * 6)) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
@@ -1,22 +1,31 @@
-extern crate tokio_core;
-extern crate futures;
-
-use self::tokio_core::net::TcpStream;
-use self::tokio_core::io::{Codec, EasyBuf, Io};
-use self::tokio_core::reactor::{Core, Interval};
-use self::futures::{Stream, Future, Sink};
-use self::futures::sync::mpsc::{
+use tokio_core::net::TcpStream;
+use tokio_core::io::{Codec, EasyBuf, Io};
+use tokio_core::reactor::{Core, Handle};
+use futures::{future, stream, Async, AsyncSink, Poll, StartSend, Stream, Future, Sink};
+use futures::executor::{self, Unpark};
+use futures::sync::oneshot;
+use futures::sync::mpsc::{
     unbounded as fut_unbounded,
     UnboundedSender as FutSender,
+    UnboundedReceiver as FutReceiver,
 };
-use std::io;
-use std::net::ToSocketAddrs;
-use std::sync::Arc;
-use std::sync::mpsc::{channel, Receiver};
+use std::cell::RefCell;
+use std::io::{self, Read, Write};
+use std::rc::Rc;
+use std::sync::{Arc, Condvar, Mutex};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
 
-use irc::{ClientEvent, Command, CommandBuilder, CommandType, CommandParser, UserCommand};
+use irc::batch::Batches;
+use irc::echo::LocalEcho;
+use irc::response::{Requests, Response};
+use irc::whois::Whoises;
+use irc::list::Listing;
+use irc::motd::MotdCollector;
+use irc::proxy::resolve;
+use irc::transport::{connect_unix, CommandStream, Duplex};
+use irc::{Address, Capabilities, ChannelModes, Transport, ClientEvent, Command, CommandBuilder, CommandType, CommandParser, UserCommand};
 
 struct IrcCodec {
     parser: CommandParser,
@@ -48,121 +57,398 @@ impl Codec for IrcCodec {
     }
 }
 
+struct Connection {
+    events: FutSender<ClientEvent>,
+    commands: FutSender<Command>,
+    outgoing: FutReceiver<Command>,
+    requests: FutReceiver<(Command, oneshot::Sender<Vec<Command>>)>,
+    shutdown: oneshot::Receiver<()>,
+    connected: Arc<AtomicBool>,
+    capabilities: Capabilities,
+}
+
+type Connecting = Box<Future<Item=TcpStream, Error=io::Error>>;
+
+impl Connection {
+    fn run(self, address: &Address, transport: &Transport, handle: &Handle)
+            -> Box<Future<Item=(), Error=io::Error>> {
+        let connected = self.connected.clone();
+        let c = match *transport {
+            Transport::Direct => {
+                let stream: Connecting = match resolve((&*address.host, address.port)) {
+                    Ok(addr) => Box::new(TcpStream::connect(&addr, handle)),
+                    Err(e) => Box::new(future::err(e)),
+                };
+                self.start(address, stream)
+            },
+            Transport::Proxy(ref proxy) => {
+                self.start(address, proxy.connect(address, handle))
+            },
+            Transport::Command(ref command) => {
+                self.start(address, future::result(CommandStream::spawn(command, address, handle)))
+            },
+            Transport::Unix(ref path) => {
+                self.start(address, future::result(connect_unix(path, handle)))
+            },
+        };
+
+        Box::new(c.then(move |r| {
+            connected.store(false, Ordering::SeqCst);
+            r
+        }))
+    }
+
+    fn start<S, F>(self, address: &Address, stream: F) -> Box<Future<Item=(), Error=io::Error>>
+            where S: Io + 'static, F: Future<Item=S, Error=io::Error> + 'static {
+        if address.tls {
+            self.tls(address.host.clone(), stream)
+        } else {
+            Box::new(stream.and_then(move |stream| self.serve(stream)))
+        }
+    }
+
+    #[cfg(feature = "tls")]
+    fn tls<S, F>(self, domain: String, stream: F) -> Box<Future<Item=(), Error=io::Error>>
+            where S: Io + 'static, F: Future<Item=S, Error=io::Error> + 'static {
+        use native_tls;
+        use tokio_tls::TlsConnector;
+        use irc::transport::Compat;
+
+        let c = stream
+            .and_then(move |stream| {
+                let connector = try!(native_tls::TlsConnector::new()
+                                     .map_err(|e| io::Error::new(io::ErrorKind::Other, e)));
+                Ok(TlsConnector::from(connector).connect(&domain, Compat(stream))
+                   .map_err(|e| io::Error::new(io::ErrorKind::Other, e)))
+            })
+            .flatten()
+            .and_then(move |stream| self.serve(Compat(stream)));
+
+        Box::new(c)
+    }
+
+    #[cfg(not(feature = "tls"))]
+    fn tls<S, F>(self, _domain: String, _stream: F) -> Box<Future<Item=(), Error=io::Error>>
+            where S: Io + 'static, F: Future<Item=S, Error=io::Error> + 'static {
+        Box::new(future::err(io::Error::new(io::ErrorKind::Other,
+                                            "TLS support was not compiled in")))
+    }
+
+    fn serve<S: Io + 'static>(self, stream: S) -> Box<Future<Item=(), Error=io::Error>> {
+        let Connection { events, commands, outgoing, requests, shutdown, mut capabilities, .. } = self;
+        let echo_events = events.clone();
+        let echo_commands = commands.clone();
+        let initial = capabilities.start();
+
+        let capabilities = Rc::new(RefCell::new(capabilities));
+        let echo = Rc::new(RefCell::new(LocalEcho::new()));
+        let mut modes = ChannelModes::new();
+        let mut batches = Batches::new();
+        let mut whoises = Whoises::new();
+        let mut listing = Listing::new();
+        let mut motd = MotdCollector::new();
+        let pending = Rc::new(RefCell::new(Requests::new()));
+        let (echo_caps, local_echo) = (capabilities.clone(), echo.clone());
+        let (request_caps, requested) = (capabilities.clone(), pending.clone());
+
+        let codec = IrcCodec::new();
+        let (w, r) = stream.framed(codec).split();
+        let incoming = r.for_each(move |cmd| {
+            match cmd.command {
+                CommandType::Ping => {
+                    let pong = CommandBuilder::new()
+                        .command(CommandType::Pong)
+                        .add_param(cmd.params.data[0].clone())
+                        .build().unwrap();
+                    let _ = FutSender::send(&commands, pong);
+                },
+                _ => {}
+            }
+            let enabled = capabilities.borrow().enabled().len();
+            for reply in capabilities.borrow_mut().handle(&cmd) {
+                let _ = FutSender::send(&commands, reply);
+            }
+            if cmd.command == CommandType::Cap && capabilities.borrow().enabled().len() != enabled {
+                let enabled = capabilities.borrow().enabled().to_vec();
+                let _ = FutSender::send(&events, ClientEvent::Capabilities(enabled));
+            }
+
+            let (settled, ping, hidden) = echo.borrow_mut().handle(&cmd);
+            for event in settled {
+                let _ = FutSender::send(&events, event);
+            }
+            if let Some(ping) = ping {
+                let _ = FutSender::send(&commands, ping);
+            }
+            if hidden { return Ok(()); }
+            modes.handle(&cmd);
+            if pending.borrow_mut().handle(&cmd) { return Ok(()); }
+
+            let grouped = batches.handle(&cmd, &modes)
+                .or_else(|| whoises.handle(&cmd))
+                .or_else(|| listing.handle(&cmd))
+                .or_else(|| motd.handle(&cmd));
+            match grouped {
+                Some(done) => {
+                    for event in done {
+                        let _ = FutSender::send(&events, event);
+                    }
+                },
+                None => {
+                    let _ = FutSender::send(&events, ClientEvent::parse(cmd, &modes));
+                },
+            }
+            Ok(())
+        });
+
+        let requests = requests.map(move |(cmd, done)| {
+            let labeled = request_caps.borrow().is_enabled("labeled-response");
+            requested.borrow_mut().sent(cmd, done, labeled)
+        });
+
+        let out = stream::iter(initial.into_iter().map(Ok)).chain(outgoing.select(requests)).map(move |cmd| {
+            // With echo-message the server sends our messages back, prefixed and tagged.
+            if !echo_caps.borrow().is_enabled("echo-message") {
+                if cmd.command == CommandType::PrivMsg {
+                    let (pending, ping) = local_echo.borrow_mut().sent(&cmd);
+                    let _ = FutSender::send(&echo_events, pending);
+                    if let Some(ping) = ping {
+                        let _ = FutSender::send(&echo_commands, ping);
+                    }
+                } else {
+                    local_echo.borrow_mut().written(&cmd);
+                }
+            }
+            cmd
+        }).map_err(|_| io::Error::new(io::ErrorKind::Other, "Recv Error"));
+
+        let outgoing =
+            w.send_all(out)
+            .map(|_| ());
+
+        let shutdown = shutdown.then(|_| Ok(()));
+
+        let c = incoming
+            .select(outgoing).map(|_| ()).map_err(|(e, _next)| e)
+            .select(shutdown).map(|_| ()).map_err(|(e, _next)| e);
+
+        Box::new(c)
+    }
+}
+
 pub struct Client {
     sender: FutSender<Command>,
-    receiver: Receiver<Command>,
+    requests: FutSender<(Command, oneshot::Sender<Vec<Command>>)>,
+    receiver: FutReceiver<ClientEvent>,
     connected: Arc<AtomicBool>,
-    thread: JoinHandle<io::Result<()>>,
+    shutdown: Option<oneshot::Sender<()>>,
+    thread: Option<JoinHandle<io::Result<()>>>,
 }
 impl Client {
-    pub fn connect<S: ToSocketAddrs>(addr: S) -> Client {
-        let addr = addr.to_socket_addrs().unwrap().next().unwrap();
-        let (in_tx, in_rx) = channel();
-        let (out_tx, out_rx) = fut_unbounded();
-        let core_out_tx = out_tx.clone();
-        let echo_in_tx = in_tx.clone();
-
-        let connected = Arc::new(AtomicBool::new(true));
-        let inner_connected = connected.clone();
+    /// Connects on a dedicated reactor thread, for callers without an event loop of their own.
+    pub fn connect(address: Address) -> Client {
+        let (mut client, connection) = Client::pair(Capabilities::new());
 
         let thread = ::std::thread::spawn(move || {
-            let connected = inner_connected;
-            let core_tx = core_out_tx;
-            let echo_tx = echo_in_tx;
             let mut core = Core::new().unwrap();
             let handle = core.handle();
-            let c = TcpStream::connect(&addr, &handle)
-                .and_then(|stream| {
-                    let codec = IrcCodec::new();
-                    let (w, r) = stream.framed(codec).split();
-                    let incoming = r.for_each(|cmd| {
-                        match cmd.command {
-                            CommandType::Ping => {
-                                let pong = CommandBuilder::new()
-                                    .command(CommandType::Pong)
-                                    .add_param(cmd.params.data[0].clone())
-                                    .build().unwrap();
-                                let _ = FutSender::send(&core_tx, pong);
-                            },
-                            _ => {}
-                        }
-                        let _ = in_tx.send(cmd);
-                        Ok(())
-                    });
-
-                    let out = out_rx.map(move |cmd| {
-                        match cmd.command {
-                            CommandType::PrivMsg => {
-                                let _ = echo_tx.send(cmd.clone());
-                            },
-                            _ => {},
-                        }
-                        cmd
-                    }).map_err(|_| io::Error::new(io::ErrorKind::Other, "Recv Error"));
-
-                    let outgoing =
-                        w.send_all(out)
-                        .map(|_| ());
-
-                    let watchdog = Interval::new(::std::time::Duration::from_millis(50), &handle)
-                        .unwrap()
-                        .take_while(|_| Ok(connected.load(Ordering::SeqCst)))
-                        .collect()
-                        .map(|_| ());
-
-                    incoming
-                        .select(outgoing).map(|_| ()).map_err(|(e, _next)| e)
-                        .select(watchdog).map(|_| ()).map_err(|(e, _next)| e)
-                }).map(|_|());
-
-            let r = core.run(c);
-            connected.store(false, Ordering::SeqCst);
-            r
+            let c = connection.run(&address, &Transport::Direct, &handle);
+            core.run(c)
         });
 
-        Client {
+        client.thread = Some(thread);
+        client
+    }
+
+    /// Connects on an existing reactor. The connection is driven by `handle`'s event loop,
+    /// and its events are read by polling the returned `Client` as a `Stream`. `capabilities`
+    /// are negotiated before registration completes, and the connection is made over
+    /// `transport`.
+    pub fn spawn(address: Address, transport: &Transport, capabilities: Capabilities,
+                 handle: &Handle) -> Client {
+        let (client, connection) = Client::pair(capabilities);
+
+        let c = connection.run(&address, transport, handle)
+            .map_err(move |e| error!("Connection to {} failed: {}", address, e));
+        handle.spawn(c);
+
+        client
+    }
+
+    /// Runs the protocol over an established stream, such as an in-memory one in tests. The
+    /// stream is driven by `handle`'s event loop.
+    pub fn from_stream<S: Io + 'static>(stream: S, capabilities: Capabilities, handle: &Handle)
+            -> Client {
+        let (client, connection) = Client::pair(capabilities);
+
+        let connected = connection.connected.clone();
+        let c = connection.serve(stream)
+            .then(move |r| {
+                connected.store(false, Ordering::SeqCst);
+                r
+            })
+            .map_err(|e| error!("Connection failed: {}", e));
+        handle.spawn(c);
+
+        client
+    }
+
+    /// Like `from_stream`, for a reader and writer that are separate objects. Both must be
+    /// non-blocking.
+    pub fn from_pair<R, W>(reader: R, writer: W, capabilities: Capabilities, handle: &Handle)
+            -> Client where R: Read + 'static, W: Write + 'static {
+        Client::from_stream(Duplex::new(reader, writer), capabilities, handle)
+    }
+
+    fn pair(capabilities: Capabilities) -> (Client, Connection) {
+        let (in_tx, in_rx) = fut_unbounded();
+        let (out_tx, out_rx) = fut_unbounded();
+        let (req_tx, req_rx) = fut_unbounded();
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+        let connected = Arc::new(AtomicBool::new(true));
+
+        let connection = Connection {
+            events: in_tx,
+            commands: out_tx.clone(),
+            outgoing: out_rx,
+            requests: req_rx,
+            shutdown: shutdown_rx,
+            connected: connected.clone(),
+            capabilities: capabilities,
+        };
+
+        let client = Client {
             sender: out_tx,
+            requests: req_tx,
             receiver: in_rx,
             connected: connected,
-            thread: thread,
-        }
+            shutdown: Some(shutdown_tx),
+            thread: None,
+        };
+
+        (client, connection)
     }
 
     pub fn is_connected(&self) -> bool {
         self.connected.load(Ordering::SeqCst)
     }
 
-    pub fn poll_messages(&self) -> PollMessagesIter {
+    /// Blocks until the next event arrives or `timeout` elapses. Returns `None` on timeout or
+    /// once the connection has closed and every pending event has been read.
+    pub fn recv_timeout(&mut self, timeout: Duration) -> Option<ClientEvent> {
+        let deadline = Instant::now() + timeout;
+        let unpark = Arc::new(ThreadUnpark::new());
+        let mut events = executor::spawn(&mut self.receiver);
+        loop {
+            match events.poll_stream(unpark.clone()) {
+                Ok(Async::Ready(Some(e))) => return Some(e),
+                Ok(Async::Ready(None)) | Err(_) => return None,
+                Ok(Async::NotReady) => {
+                    let now = Instant::now();
+                    if now >= deadline { return None; }
+                    unpark.park_timeout(deadline - now);
+                }
+            }
+        }
+    }
+
+    pub fn poll_messages(&mut self) -> PollMessagesIter {
         PollMessagesIter {
-            source: &self.receiver,
+            source: self,
         }
     }
 
     pub fn send_message(&self, cmd: UserCommand) {
-        let _ = FutSender::send(&self.sender, cmd.to_command().unwrap());
+        if let Ok(cmd) = cmd.to_command() {
+            let _ = FutSender::send(&self.sender, cmd);
+        }
+    }
+
+    /// Sends a command and collects the server's replies to it, which are then not delivered
+    /// as events. Replies are matched by label when `labeled-response` is enabled, and by
+    /// their numerics otherwise, which covers WHOIS, WHOWAS, WHO, NAMES, TOPIC, LIST, ISON
+    /// and USERHOST; for other commands the response is empty.
+    pub fn request(&self, cmd: UserCommand) -> Response {
+        let (done, receiver) = oneshot::channel();
+        if let Ok(cmd) = cmd.to_command() {
+            let _ = FutSender::send(&self.requests, (cmd, done));
+        }
+        Response::new(receiver)
     }
 
-    pub fn close(self) -> io::Result<()> {
-        self.connected.store(false, Ordering::SeqCst);
-        self.thread.join().map_err(|e| panic!(e)).unwrap()
+    pub fn close(mut self) -> io::Result<()> {
+        if let Some(shutdown) = self.shutdown.take() {
+            shutdown.complete(());
+        }
+        match self.thread.take() {
+            Some(thread) => thread.join().map_err(|e| panic!(e)).unwrap(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Stream for Client {
+    type Item = ClientEvent;
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<Option<ClientEvent>, ()> {
+        self.receiver.poll()
+    }
+}
+
+impl Sink for Client {
+    type SinkItem = UserCommand;
+    type SinkError = io::Error;
+
+    fn start_send(&mut self, cmd: UserCommand) -> StartSend<UserCommand, io::Error> {
+        let cmd = try!(cmd.to_command()
+                       .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e)));
+        try!(FutSender::send(&self.sender, cmd)
+             .map_err(|_| io::Error::new(io::ErrorKind::NotConnected, "Send Error")));
+        Ok(AsyncSink::Ready)
+    }
+
+    fn poll_complete(&mut self) -> Poll<(), io::Error> {
+        Ok(Async::Ready(()))
+    }
+}
+
+struct ThreadUnpark {
+    ready: Mutex<bool>,
+    cvar: Condvar,
+}
+
+impl ThreadUnpark {
+    fn new() -> ThreadUnpark {
+        ThreadUnpark {
+            ready: Mutex::new(false),
+            cvar: Condvar::new(),
+        }
+    }
+
+    fn park_timeout(&self, timeout: Duration) {
+        let mut ready = self.ready.lock().unwrap();
+        if !*ready {
+            ready = self.cvar.wait_timeout(ready, timeout).unwrap().0;
+        }
+        *ready = false;
+    }
+}
+
+impl Unpark for ThreadUnpark {
+    fn unpark(&self) {
+        *self.ready.lock().unwrap() = true;
+        self.cvar.notify_one();
     }
 }
 
 pub struct PollMessagesIter<'a> {
-    source: &'a Receiver<Command>,
+    source: &'a mut Client,
 }
 
 impl<'a> Iterator for PollMessagesIter<'a> {
     type Item = ClientEvent;
     fn next(&mut self) -> Option<Self::Item> {
-        match self.source.try_recv() {
-            Ok(e) => {
-                match ClientEvent::from_command(&e) {
-                    Some(ce) => Some(ce),
-                    None => Some(ClientEvent::Command(e)),
-                }
-            },
-            _ => None,
-        }
+        self.source.recv_timeout(Duration::from_millis(0))
     }
 }
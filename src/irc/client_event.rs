@@ -1,27 +1,80 @@
-use irc::{Sender, UserCommand, Command, CommandType};
+use irc::{Sender, Command, CommandType, Timestamp, ChannelModes, Member, Whois, ListEntry};
 
+/// When a message was sent, and the server's id for it once `message-tags` is negotiated.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Meta {
+    pub time: Timestamp,
+    pub msgid: Option<String>,
+}
+
+impl Meta {
+    pub fn from_command(command: &Command) -> Meta {
+        Meta {
+            // The server's time when server-time is negotiated, otherwise the time we received it.
+            time: command.time().unwrap_or_else(Timestamp::now),
+            msgid: command.get_tag("msgid").map(|x| x.to_string()),
+        }
+    }
+}
 
 pub enum ClientEvent {
-    ChannelMessage(String, Option<String>, String),
-    PrivateMessage(Option<String>, String),
-    JoinChannel(String, Option<String>),
+    ChannelMessage(String, Option<String>, String, Meta),
+    PrivateMessage(Option<String>, String, Meta),
+    /// A user joining a channel, with their account and realname under `extended-join`.
+    JoinChannel(String, Member),
     LeaveChannel(String, Option<String>),
-    ChannelNotice(String, Option<String>, String),
-    PrivateNotice(Option<String>, String),
+    ChannelNotice(String, Option<String>, String, Meta),
+    PrivateNotice(Option<String>, String, Meta),
     Topic(String, String),
-    Names(String, Vec<String>),
+    Names(String, Vec<Member>),
     NamesEnd(String),
+    /// A WHO reply for a channel member, carrying their away flag and realname.
+    WhoReply(String, Member),
+    /// A user going away with a message, or coming back.
+    Away(String, Option<String>),
+    /// A user logging in to an account, or out of it.
+    Account(String, Option<String>),
+    /// Mode changes on a channel: whether each is set, the mode, and its parameter.
+    ChannelMode(String, Option<String>, Vec<(bool, char, Option<String>)>, Meta),
     Command(Command),
     Connected,
+    LoggedIn(String),
+    /// A message we sent, shown before the server has accepted it: id, target, message.
+    PendingMessage(u64, String, String, Timestamp),
+    MessageConfirmed(u64, String),
+    /// A pending message the server refused: id, target, reason.
+    MessageRejected(u64, String, String),
+    /// The capabilities enabled on the connection, sent whenever they change.
+    Capabilities(Vec<String>),
+    /// The replies to a WHOIS, collected once the server has sent them all.
+    WhoisResult(Whois),
+    /// The message of the day, one entry per line, empty if the server has none.
+    Motd(Vec<String>),
+    /// The channels from a LIST reply, collected once the server has sent them all.
+    ChannelList(Vec<ListEntry>),
+    /// Commands grouped by an IRCv3 batch of type `kind`, such as `netsplit` or
+    /// `chathistory`, in the order received. Nested batches appear among the messages.
+    Batch { kind: String, params: Vec<String>, messages: Vec<ClientEvent> },
 }
 
 impl ClientEvent {
-    pub fn from_command(command: &Command) -> Option<ClientEvent> {
+    /// The event for a command, or the command itself when it has none of its own.
+    pub fn parse(command: Command, modes: &ChannelModes) -> ClientEvent {
+        match ClientEvent::from_command(&command, modes) {
+            Some(ce) => ce,
+            None => ClientEvent::Command(command),
+        }
+    }
+
+    /// The event for a command, reading prefixes and mode parameters with the server's
+    /// `modes`. `None` for commands without one of their own.
+    pub fn from_command(command: &Command, modes: &ChannelModes) -> Option<ClientEvent> {
         let sender = match command.prefix {
             Some(Sender::Server(ref name)) => Some(name.to_string()),
             Some(Sender::User(ref nick, _, _)) => Some(nick.to_string()),
             _ => None
         };
+        let meta = Meta::from_command(command);
 
         match command.command {
             CommandType::PrivMsg => {
@@ -29,14 +82,18 @@ impl ClientEvent {
                 let message = command.get_param(1).unwrap_or("ERROR").to_string();
 
                 if target.starts_with("#") {
-                    Some(ClientEvent::ChannelMessage(target, sender, message))
+                    Some(ClientEvent::ChannelMessage(target, sender, message, meta))
                 } else {
-                    Some(ClientEvent::PrivateMessage(sender, message))
+                    Some(ClientEvent::PrivateMessage(sender, message, meta))
                 }
             },
             CommandType::Join => {
                 let target = command.get_param(0).unwrap_or("ERROR").to_string();
-                Some(ClientEvent::JoinChannel(target, sender))
+                let mut member = command.prefix.as_ref().map(Member::from_sender)
+                    .unwrap_or(Member::new("".to_string()));
+                member.account = command.get_param(1).and_then(account);
+                member.realname = command.get_param(2).map(|x| x.to_string());
+                Some(ClientEvent::JoinChannel(target, member))
             },
             CommandType::Part => {
                 let target = command.get_param(0).unwrap_or("ERROR").to_string();
@@ -47,9 +104,9 @@ impl ClientEvent {
                 let message = command.get_param(1).unwrap_or("ERROR").to_string();
 
                 if target.starts_with("#") {
-                    Some(ClientEvent::ChannelNotice(target, sender, message))
+                    Some(ClientEvent::ChannelNotice(target, sender, message, meta))
                 } else {
-                    Some(ClientEvent::PrivateNotice(sender, message))
+                    Some(ClientEvent::PrivateNotice(sender, message, meta))
                 }
             },
             CommandType::Topic => {
@@ -69,9 +126,63 @@ impl ClientEvent {
             CommandType::Rpl_NamReply => {
                 let target = command.get_param(2).unwrap_or("ERROR").to_string();
                 let last = command.get_param(3).unwrap_or("ERROR");
-                let names = last.split(' ').map(|x|x.to_string()).collect();
+                let names = last.split(' ').filter(|x| !x.is_empty()).map(|x| Member::parse(x, modes)).collect();
                 Some(ClientEvent::Names(target, names))
             },
+            CommandType::Rpl_WhoReply => {
+                let target = command.get_param(1).unwrap_or("ERROR").to_string();
+                let mut member = Member::new(command.get_param(5).unwrap_or("ERROR").to_string());
+                member.user = command.get_param(2).map(|x| x.to_string());
+                member.host = command.get_param(3).map(|x| x.to_string());
+                let flags = command.get_param(6).unwrap_or("");
+                member.away = flags.starts_with('G');
+                member.add_prefixes(flags, modes);
+                // The last parameter is the hop count followed by the realname.
+                member.realname = command.get_param(7)
+                    .map(|x| x.splitn(2, ' ').nth(1).unwrap_or("").to_string());
+                Some(ClientEvent::WhoReply(target, member))
+            },
+            CommandType::Away => {
+                let nick = match sender {
+                    Some(nick) => nick,
+                    None => return None,
+                };
+                Some(ClientEvent::Away(nick, command.get_param(0).map(|x| x.to_string())))
+            },
+            CommandType::Account => {
+                let nick = match sender {
+                    Some(nick) => nick,
+                    None => return None,
+                };
+                Some(ClientEvent::Account(nick, command.get_param(0).and_then(account)))
+            },
+            CommandType::Mode => {
+                let target = command.get_param(0).unwrap_or("ERROR").to_string();
+                if !target.starts_with('#') && !target.starts_with('&') {
+                    return None;
+                }
+                let mut params = command.params.data.iter().skip(2);
+                let mut changes = Vec::new();
+                let mut add = true;
+                for c in command.get_param(1).unwrap_or("").chars() {
+                    match c {
+                        '+' => add = true,
+                        '-' => add = false,
+                        _ => {
+                            let param = if modes.has_param(c, add) { params.next().cloned() } else { None };
+                            changes.push((add, c, param));
+                        },
+                    }
+                }
+                Some(ClientEvent::ChannelMode(target, sender, changes, meta))
+            },
+            CommandType::Rpl_Welcome => {
+                Some(ClientEvent::Connected)
+            },
+            CommandType::Rpl_LoggedIn => {
+                let account = command.get_param(2).unwrap_or("ERROR").to_string();
+                Some(ClientEvent::LoggedIn(account))
+            },
             CommandType::Rpl_EndOfNames => {
                 let target = command.get_param(1).unwrap_or("ERROR").to_string();
                 Some(ClientEvent::NamesEnd(target))
@@ -80,3 +191,8 @@ impl ClientEvent {
         }
     }
 }
+
+/// Account names as sent by `extended-join` and `account-notify`, where `*` means none.
+fn account(name: &str) -> Option<String> {
+    if name == "*" || name.is_empty() { None } else { Some(name.to_string()) }
+}
@@ -1,4 +1,4 @@
-use super::{CommandType};
+use super::{CommandType, Timestamp};
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum Sender {
@@ -90,11 +90,16 @@ pub struct Command {
     pub prefix: Option<Sender>,
     pub command: CommandType,
     pub params: Params,
+    /// The command as written when `command` is `Unknown`, so it can still be sent.
+    pub unknown: Option<String>,
 }
 
 impl Command {
     pub fn to_string(&self) -> String {
-        let cmd: &str = self.command.into();
+        let cmd: &str = match self.unknown {
+            Some(ref name) if self.command == CommandType::Unknown => name,
+            _ => self.command.into(),
+        };
         format!("{}{}{}{}\r\n", self.tags.as_ref().map(|x|x.to_cmd()).unwrap_or("".to_string()),
                             self.prefix.as_ref().map(|x|x.to_cmd()).unwrap_or("".to_string()),
                             cmd,
@@ -104,4 +109,27 @@ impl Command {
     pub fn get_param(&self, index: usize) -> Option<&str> {
         self.params.data.get(index).map(|x| &**x)
     }
+
+    pub fn get_tag(&self, key: &str) -> Option<&str> {
+        self.tags.as_ref()
+            .and_then(|t| t.data.iter().find(|x| x.key == key))
+            .map(|x| &*x.value)
+    }
+
+    /// Sets a tag, replacing any with the same key.
+    pub fn add_tag(&mut self, key: String, value: String) {
+        if self.tags.is_none() {
+            self.tags = Some(Tags { data: Vec::new() });
+        }
+        if let Some(ref mut tags) = self.tags {
+            tags.data.retain(|x| x.key != key);
+            tags.data.push(Tag { key: key, value: value });
+        }
+    }
+
+    /// When the server sent the command, from the `time` tag added once `server-time` is
+    /// negotiated.
+    pub fn time(&self) -> Option<Timestamp> {
+        self.get_tag("time").and_then(Timestamp::parse)
+    }
 }
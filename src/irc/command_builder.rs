@@ -73,7 +73,8 @@ impl CommandBuilder {
             tags: tags,
             prefix: self.sender,
             command: self.command_type.unwrap(),
-            params: params
+            params: params,
+            unknown: None,
         })
     }
 }
@@ -11,6 +11,14 @@ impl CommandParser {
     }
 
     pub fn parse(&self, message: &Vec<u8>) -> Command {
+        match self.try_parse(message) {
+            Some(cmd) => cmd,
+            None => panic!("Unparsable message"),
+        }
+    }
+
+    /// Parses a line ending in CRLF, or returns `None` if it isn't a valid message.
+    pub fn try_parse(&self, message: &[u8]) -> Option<Command> {
         fn unescape_tag_value(value: &str) -> String {
             let escape_seqs =
                 vec![("\\\\", "\\"), ("\\:", ";"), ("\\s", " "), ("\\r", "\r"), ("\\n", "\n")];
@@ -115,9 +123,7 @@ impl CommandParser {
                                         }),
                                     || tri ));
 
-        named!(command<&str, CommandType >, chain! (
-                                r: alt!(digits | alpha),
-                                || r.into()));
+        named!(command<&str, &str>, alt!(digits | alpha));
 
         named!(param<&str, String >,
                chain!(
@@ -153,14 +159,29 @@ impl CommandParser {
                                         command: command ~
                                         params: params,
                                         || {
-                                            Command { tags: tags, prefix: prefix, command: command, params: params }
+                                            let command_type: CommandType = command.into();
+                                            let unknown = if command_type == CommandType::Unknown {
+                                                Some(command.to_string())
+                                            } else {
+                                                None
+                                            };
+                                            Command { tags: tags, prefix: prefix, command: command_type,
+                                                      params: params, unknown: unknown }
                                         }));
 
-        let r = command_parser(str::from_utf8(message).unwrap());
+        let message = match str::from_utf8(message) {
+            Ok(message) => message,
+            Err(e) => {
+                error!("{:?}", e);
+                return None;
+            },
+        };
+        let r = command_parser(message);
         if r.is_err() || r.is_incomplete() {
-            error!("{}", str::from_utf8(message).unwrap());
+            error!("{}", message);
             error!("{:?}", r);
+            return None;
         }
-        r.unwrap().1
+        Some(r.unwrap().1)
     }
 }
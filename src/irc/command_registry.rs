@@ -0,0 +1,370 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use irc::{ParseError, UserCommand};
+
+/// One of a command's arguments, which decides how it is read from the line and shown in
+/// the usage string.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Arg {
+    /// A word that must be given.
+    Required(String),
+    /// A word that may be left out.
+    Optional(String),
+    /// A leading channel name, taken from the active tab when the first word isn't one.
+    Channel,
+    /// A word that defaults to the active tab.
+    Target(String),
+    /// The rest of the line, which must not be empty.
+    Text(String),
+    /// The rest of the line, possibly empty.
+    OptionalText(String),
+    /// One or more words.
+    List(String),
+}
+
+impl Arg {
+    fn usage(&self) -> String {
+        match *self {
+            Arg::Required(ref name) => format!("<{}>", name),
+            Arg::Optional(ref name) | Arg::Target(ref name) => format!("[{}]", name),
+            Arg::Channel => "[channel]".to_string(),
+            Arg::Text(ref name) => format!("<{}...>", name),
+            Arg::OptionalText(ref name) => format!("[{}...]", name),
+            Arg::List(ref name) => format!("<{}>...", name),
+        }
+    }
+}
+
+/// The arguments of a command after validation, one value per `Arg` in its spec.
+pub struct Args {
+    /// Everything after the command name, as typed.
+    pub raw: String,
+    /// The channel or query of the active tab, empty in a server tab.
+    pub active: String,
+    values: Vec<String>,
+}
+
+impl Args {
+    /// The value of the argument at `index`, empty if an optional one was left out.
+    pub fn get(&self, index: usize) -> String {
+        self.values.get(index).cloned().unwrap_or("".to_string())
+    }
+
+    pub fn get_opt(&self, index: usize) -> Option<String> {
+        self.values.get(index).and_then(|x| if x.is_empty() { None } else { Some(x.clone()) })
+    }
+
+    /// The words of a `List` argument.
+    pub fn words(&self, index: usize) -> Vec<String> {
+        self.get(index).split_whitespace().map(|x| x.to_string()).collect()
+    }
+}
+
+/// A slash command: its name and aliases, its arguments, a line of help, and how to turn
+/// validated arguments into a `UserCommand`.
+pub struct CommandSpec {
+    name: String,
+    aliases: Vec<String>,
+    args: Vec<Arg>,
+    help: String,
+    handler: Box<Fn(&Args) -> Result<UserCommand, ParseError>>,
+}
+
+impl CommandSpec {
+    pub fn new<F>(name: &str, help: &str, handler: F) -> CommandSpec
+        where F: Fn(&Args) -> Result<UserCommand, ParseError> + 'static {
+        CommandSpec {
+            name: name.to_string(),
+            aliases: Vec::new(),
+            args: Vec::new(),
+            help: help.to_string(),
+            handler: Box::new(handler),
+        }
+    }
+
+    pub fn alias(mut self, alias: &str) -> Self {
+        self.aliases.push(alias.to_string());
+        self
+    }
+
+    pub fn arg(mut self, arg: Arg) -> Self {
+        self.args.push(arg);
+        self
+    }
+
+    pub fn name(&self) -> &str { &self.name }
+    pub fn aliases(&self) -> &[String] { &self.aliases }
+    pub fn help(&self) -> &str { &self.help }
+
+    /// The command as it is typed, such as `/kick [channel] <nick> [reason...]`.
+    pub fn usage(&self) -> String {
+        let mut usage = format!("/{}", self.name);
+        for arg in &self.args {
+            usage.push(' ');
+            usage.push_str(&arg.usage());
+        }
+        usage
+    }
+
+    fn matches(&self, name: &str) -> bool {
+        self.name == name || self.aliases.iter().any(|x| x == name)
+    }
+
+    fn parse_args(&self, raw: &str, active: &str) -> Result<Args, ParseError> {
+        let usage = || ParseError::Usage(self.usage());
+        let mut values = Vec::new();
+        let mut rest = raw;
+        for arg in &self.args {
+            let (word, after) = split(rest);
+            match *arg {
+                Arg::Required(_) => {
+                    if word.is_empty() { return Err(usage()); }
+                    values.push(word.to_string());
+                    rest = after;
+                },
+                Arg::Optional(_) => {
+                    values.push(word.to_string());
+                    rest = after;
+                },
+                Arg::Channel => {
+                    if word.starts_with('#') || word.starts_with('&') {
+                        values.push(word.to_string());
+                        rest = after;
+                    } else if !active.is_empty() {
+                        values.push(active.to_string());
+                    } else {
+                        return Err(usage());
+                    }
+                },
+                Arg::Target(_) => {
+                    if !word.is_empty() {
+                        values.push(word.to_string());
+                        rest = after;
+                    } else if !active.is_empty() {
+                        values.push(active.to_string());
+                    } else {
+                        return Err(usage());
+                    }
+                },
+                Arg::Text(_) | Arg::List(_) => {
+                    if rest.trim().is_empty() { return Err(usage()); }
+                    values.push(rest.trim_left().to_string());
+                    rest = "";
+                },
+                Arg::OptionalText(_) => {
+                    values.push(rest.trim_left().to_string());
+                    rest = "";
+                },
+            }
+        }
+        if !rest.trim().is_empty() {
+            return Err(usage());
+        }
+        Ok(Args { raw: raw.to_string(), active: active.to_string(), values: values })
+    }
+}
+
+/// Splits off the first word, returning it and the rest of the line.
+fn split(args: &str) -> (&str, &str) {
+    let args = args.trim_left();
+    match args.find(' ') {
+        Some(i) => (&args[..i], &args[i + 1..]),
+        None => (args, ""),
+    }
+}
+
+/// How deep aliases may expand into other aliases.
+const MAX_ALIAS_DEPTH: usize = 10;
+/// How many commands one line may expand into.
+const MAX_ALIAS_LINES: usize = 100;
+
+/// The slash commands the input line understands, and the way to parse it. Starts out with
+/// the built-in commands, and more can be registered; a command registered later replaces
+/// one with the same name. Aliases are looked up before commands.
+pub struct CommandRegistry {
+    commands: Vec<CommandSpec>,
+    aliases: BTreeMap<String, String>,
+    /// The aliases that came from the config file rather than `/alias`.
+    config_aliases: BTreeSet<String>,
+}
+
+impl CommandRegistry {
+    /// A registry without any commands.
+    pub fn empty() -> CommandRegistry {
+        CommandRegistry {
+            commands: Vec::new(),
+            aliases: BTreeMap::new(),
+            config_aliases: BTreeSet::new(),
+        }
+    }
+
+    /// A registry with the built-in commands.
+    pub fn new() -> CommandRegistry {
+        let mut registry = CommandRegistry::empty();
+        for spec in ::irc::input_parser::builtin_commands() {
+            registry.register(spec);
+        }
+        registry
+    }
+
+    pub fn register(&mut self, spec: CommandSpec) {
+        self.commands.retain(|x| x.name != spec.name);
+        self.commands.push(spec);
+    }
+
+    /// Finds a command by name or alias, with or without the leading slash.
+    pub fn find(&self, name: &str) -> Option<&CommandSpec> {
+        let name = name.trim_left_matches('/').to_lowercase();
+        // Names shadow aliases, and later registrations earlier ones.
+        self.commands.iter().rev().find(|x| x.name == name)
+            .or_else(|| self.commands.iter().rev().find(|x| x.matches(&name)))
+    }
+
+    /// The commands sorted by name.
+    pub fn commands(&self) -> Vec<&CommandSpec> {
+        let mut commands: Vec<&CommandSpec> = self.commands.iter().collect();
+        commands.sort_by(|a, b| a.name.cmp(&b.name));
+        commands
+    }
+
+    /// Defines `/name` to run `expansion`: commands separated by `;`, where `$1` is the first
+    /// argument, `$2-` the second onwards, `$nick` our nick and `$chan` the active tab.
+    pub fn set_alias(&mut self, name: &str, expansion: &str) {
+        let name = name.trim_left_matches('/').to_lowercase();
+        self.config_aliases.remove(&name);
+        self.aliases.insert(name, expansion.to_string());
+    }
+
+    /// Replaces the aliases from the config file with `aliases`, leaving those defined with
+    /// `/alias` alone, even where the config file names the same alias.
+    pub fn set_config_aliases(&mut self, aliases: &BTreeMap<String, String>) {
+        for name in &self.config_aliases {
+            self.aliases.remove(name);
+        }
+        self.config_aliases.clear();
+        for (name, expansion) in aliases {
+            let name = name.trim_left_matches('/').to_lowercase();
+            if !self.aliases.contains_key(&name) {
+                self.aliases.insert(name.clone(), expansion.to_string());
+                self.config_aliases.insert(name);
+            }
+        }
+    }
+
+    /// Returns false if there was no such alias.
+    pub fn remove_alias(&mut self, name: &str) -> bool {
+        let name = name.trim_left_matches('/').to_lowercase();
+        self.config_aliases.remove(&name);
+        self.aliases.remove(&name).is_some()
+    }
+
+    pub fn aliases(&self) -> &BTreeMap<String, String> {
+        &self.aliases
+    }
+
+    /// Expands the aliases in a line of input into the lines to parse. An alias may use
+    /// other aliases, and a command's own name inside its alias means the built-in command.
+    pub fn expand(&self, input: &str, nick: &str, active_window: Option<&str>) -> Result<Vec<String>, ParseError> {
+        let mut lines = Vec::new();
+        try!(self.expand_into(input, nick, active_window.unwrap_or(""), &mut Vec::new(), &mut lines));
+        Ok(lines)
+    }
+
+    fn expand_into(&self, input: &str, nick: &str, chan: &str, stack: &mut Vec<String>,
+                   lines: &mut Vec<String>) -> Result<(), ParseError> {
+        let (name, args) = match input.find(' ') {
+            Some(i) => (&input[..i], &input[i + 1..]),
+            None => (input, ""),
+        };
+        let name = name.trim_left_matches('/').to_lowercase();
+        let expansion = match self.aliases.get(&name) {
+            Some(expansion) if input.starts_with('/') => expansion,
+            _ => {
+                if lines.len() >= MAX_ALIAS_LINES { return Err(ParseError::AliasLoop(name)); }
+                lines.push(input.to_string());
+                return Ok(());
+            },
+        };
+        if stack.contains(&name) {
+            if self.find(&name).is_some() {
+                lines.push(input.to_string());
+                return Ok(());
+            }
+            return Err(ParseError::AliasLoop(name));
+        }
+        if stack.len() >= MAX_ALIAS_DEPTH {
+            return Err(ParseError::AliasLoop(name));
+        }
+
+        stack.push(name);
+        for part in expansion.split(';').map(|x| x.trim()).filter(|x| !x.is_empty()) {
+            let line = substitute(part, args, nick, chan);
+            let line = if line.starts_with('/') { line } else { format!("/{}", line) };
+            try!(self.expand_into(&line, nick, chan, stack, lines));
+        }
+        stack.pop();
+        Ok(())
+    }
+
+    /// Parses a line from the input box. Lines not starting with `/` are messages to the
+    /// active tab.
+    pub fn parse(&self, input: &str, active_window: Option<&str>) -> Result<UserCommand, ParseError> {
+        let active = active_window.unwrap_or("");
+        if input.len() == 0 { return Err(ParseError::InputRequired); }
+        if !input.starts_with('/') {
+            return Ok(UserCommand::PrivMsg(active.to_string(), input.to_string()));
+        }
+
+        let (name, raw) = match input.find(' ') {
+            Some(i) => (&input[..i], &input[i + 1..]),
+            None => (input, ""),
+        };
+        let spec = match self.find(name) {
+            Some(spec) => spec,
+            None => return Err(ParseError::UnknownCommand(name[1..].to_string())),
+        };
+        let args = try!(spec.parse_args(raw, active));
+        match (spec.handler)(&args) {
+            Err(ParseError::InputRequired) => Err(ParseError::Usage(spec.usage())),
+            result => result,
+        }
+    }
+}
+
+/// Fills in an alias's `$N`, `$N-`, `$nick` and `$chan` from the arguments it was given;
+/// `$$` is a dollar sign.
+fn substitute(template: &str, args: &str, nick: &str, chan: &str) -> String {
+    let words: Vec<&str> = args.split_whitespace().collect();
+    let mut out = String::new();
+    let mut rest = template;
+    while let Some(i) = rest.find('$') {
+        out.push_str(&rest[..i]);
+        rest = &rest[i + 1..];
+        let digits = rest.chars().take_while(|c| c.is_digit(10)).count();
+        if digits > 0 {
+            let n: usize = rest[..digits].parse().unwrap_or(0);
+            rest = &rest[digits..];
+            if rest.starts_with('-') {
+                rest = &rest[1..];
+                if n > 0 && n <= words.len() {
+                    out.push_str(&words[n - 1..].join(" "));
+                }
+            } else if n > 0 && n <= words.len() {
+                out.push_str(words[n - 1]);
+            }
+        } else if rest.starts_with("nick") {
+            out.push_str(nick);
+            rest = &rest[4..];
+        } else if rest.starts_with("chan") {
+            out.push_str(chan);
+            rest = &rest[4..];
+        } else if rest.starts_with('$') {
+            out.push('$');
+            rest = &rest[1..];
+        } else {
+            out.push('$');
+        }
+    }
+    out.push_str(rest);
+    out
+}
@@ -41,6 +41,13 @@ pub enum CommandType {
     WAllOps,
     UserHost,
     IsOn,
+    Cap,
+    Authenticate,
+    Batch,
+    ChatHistory,
+    Account,
+    Ack,
+    Motd,
 
     Err_NoSuchNick,
     Err_NoSuchServer,
@@ -87,6 +94,11 @@ pub enum CommandType {
     Err_UModeUnknownFlag,
     Err_UsersDontMatch,
 
+    Rpl_Welcome,
+    Rpl_YourHost,
+    Rpl_Created,
+    Rpl_MyInfo,
+    Rpl_ISupport,
     Rpl_None,
     Rpl_UserHost,
     Rpl_IsOn,
@@ -99,6 +111,8 @@ pub enum CommandType {
     Rpl_WhoIsIdle,
     Rpl_EndOfWhoIs,
     Rpl_WhoIsChannels,
+    Rpl_WhoIsAccount,
+    Rpl_WhoIsSecure,
     Rpl_WhoWasUser,
     Rpl_EndOfWhoWas,
     Rpl_ListStart,
@@ -107,6 +121,7 @@ pub enum CommandType {
     Rpl_ChannelModeIs,
     Rpl_NoTopic,
     Rpl_Topic,
+    Rpl_TopicWhoTime,
     Rpl_Inviting,
     Rpl_Summoning,
     Rpl_Version,
@@ -157,11 +172,24 @@ pub enum CommandType {
     Rpl_LUserUnkown,
     Rpl_LUserChannels,
     Rpl_LUserMe,
+    Rpl_LocalUsers,
+    Rpl_GlobalUsers,
+    Rpl_StatsConn,
     Rpl_AdminMe,
     Rpl_AdminLoc1,
     Rpl_AdminLoc2,
     Rpl_AdminEmail,
 
+    Rpl_LoggedIn,
+    Rpl_LoggedOut,
+    Err_NickLocked,
+    Rpl_SaslSuccess,
+    Err_SaslFail,
+    Err_SaslTooLong,
+    Err_SaslAborted,
+    Err_SaslAlready,
+    Rpl_SaslMechs,
+
     Unknown
 }
 
@@ -208,6 +236,13 @@ impl<'a> From<CommandType> for &'a str {
             CommandType::WAllOps => "WALLOPS",
             CommandType::UserHost => "USERHOST",
             CommandType::IsOn => "ISON",
+            CommandType::Cap => "CAP",
+            CommandType::Authenticate => "AUTHENTICATE",
+            CommandType::Batch => "BATCH",
+            CommandType::ChatHistory => "CHATHISTORY",
+            CommandType::Account => "ACCOUNT",
+            CommandType::Ack => "ACK",
+            CommandType::Motd => "MOTD",
 
             CommandType::Err_NoSuchNick => "401",
             CommandType::Err_NoSuchServer => "402",
@@ -254,6 +289,11 @@ impl<'a> From<CommandType> for &'a str {
             CommandType::Err_UModeUnknownFlag => "501",
             CommandType::Err_UsersDontMatch => "502",
  
+            CommandType::Rpl_Welcome => "001",
+            CommandType::Rpl_YourHost => "002",
+            CommandType::Rpl_Created => "003",
+            CommandType::Rpl_MyInfo => "004",
+            CommandType::Rpl_ISupport => "005",
             CommandType::Rpl_None => "300",
             CommandType::Rpl_UserHost => "302",
             CommandType::Rpl_IsOn => "303",
@@ -265,6 +305,8 @@ impl<'a> From<CommandType> for &'a str {
             CommandType::Rpl_WhoIsOperator => "313",
             CommandType::Rpl_WhoIsIdle => "317",
             CommandType::Rpl_EndOfWhoIs => "318",
+            CommandType::Rpl_WhoIsAccount => "330",
+            CommandType::Rpl_WhoIsSecure => "671",
             CommandType::Rpl_WhoIsChannels => "319",
             CommandType::Rpl_WhoWasUser => "314",
             CommandType::Rpl_EndOfWhoWas => "369",
@@ -274,6 +316,7 @@ impl<'a> From<CommandType> for &'a str {
             CommandType::Rpl_ChannelModeIs => "324",
             CommandType::Rpl_NoTopic => "331",
             CommandType::Rpl_Topic => "332",
+            CommandType::Rpl_TopicWhoTime => "333",
             CommandType::Rpl_Inviting => "341",
             CommandType::Rpl_Summoning => "342",
             CommandType::Rpl_Version => "351",
@@ -324,10 +367,22 @@ impl<'a> From<CommandType> for &'a str {
             CommandType::Rpl_LUserUnkown => "253",
             CommandType::Rpl_LUserChannels => "254",
             CommandType::Rpl_LUserMe => "255",
+            CommandType::Rpl_LocalUsers => "265",
+            CommandType::Rpl_GlobalUsers => "266",
+            CommandType::Rpl_StatsConn => "250",
             CommandType::Rpl_AdminMe => "256",
             CommandType::Rpl_AdminLoc1 => "257",
             CommandType::Rpl_AdminLoc2 => "258",
             CommandType::Rpl_AdminEmail => "259",
+            CommandType::Rpl_LoggedIn => "900",
+            CommandType::Rpl_LoggedOut => "901",
+            CommandType::Err_NickLocked => "902",
+            CommandType::Rpl_SaslSuccess => "903",
+            CommandType::Err_SaslFail => "904",
+            CommandType::Err_SaslTooLong => "905",
+            CommandType::Err_SaslAborted => "906",
+            CommandType::Err_SaslAlready => "907",
+            CommandType::Rpl_SaslMechs => "908",
             _ => "ERROR",
         }
     }
@@ -376,6 +431,13 @@ impl<'a> From<&'a str> for CommandType {
             "WALLOPS" => CommandType::WAllOps,
             "USERHOST" => CommandType::UserHost,
             "ISON" => CommandType::IsOn,
+            "CAP" => CommandType::Cap,
+            "AUTHENTICATE" => CommandType::Authenticate,
+            "BATCH" => CommandType::Batch,
+            "CHATHISTORY" => CommandType::ChatHistory,
+            "ACCOUNT" => CommandType::Account,
+            "ACK" => CommandType::Ack,
+            "MOTD" => CommandType::Motd,
 
             "401" => CommandType::Err_NoSuchNick,
             "402" => CommandType::Err_NoSuchServer,
@@ -422,6 +484,11 @@ impl<'a> From<&'a str> for CommandType {
             "501" => CommandType::Err_UModeUnknownFlag,
             "502" => CommandType::Err_UsersDontMatch,
  
+            "001" => CommandType::Rpl_Welcome,
+            "002" => CommandType::Rpl_YourHost,
+            "003" => CommandType::Rpl_Created,
+            "004" => CommandType::Rpl_MyInfo,
+            "005" => CommandType::Rpl_ISupport,
             "300" => CommandType::Rpl_None,
             "302" => CommandType::Rpl_UserHost,
             "303" => CommandType::Rpl_IsOn,
@@ -434,6 +501,8 @@ impl<'a> From<&'a str> for CommandType {
             "317" => CommandType::Rpl_WhoIsIdle,
             "318" => CommandType::Rpl_EndOfWhoIs,
             "319" => CommandType::Rpl_WhoIsChannels,
+            "330" => CommandType::Rpl_WhoIsAccount,
+            "671" => CommandType::Rpl_WhoIsSecure,
             "314" => CommandType::Rpl_WhoWasUser,
             "369" => CommandType::Rpl_EndOfWhoWas,
             "321" => CommandType::Rpl_ListStart,
@@ -442,6 +511,7 @@ impl<'a> From<&'a str> for CommandType {
             "324" => CommandType::Rpl_ChannelModeIs,
             "331" => CommandType::Rpl_NoTopic,
             "332" => CommandType::Rpl_Topic,
+            "333" => CommandType::Rpl_TopicWhoTime,
             "341" => CommandType::Rpl_Inviting,
             "342" => CommandType::Rpl_Summoning,
             "351" => CommandType::Rpl_Version,
@@ -492,10 +562,22 @@ impl<'a> From<&'a str> for CommandType {
             "253" => CommandType::Rpl_LUserUnkown,
             "254" => CommandType::Rpl_LUserChannels,
             "255" => CommandType::Rpl_LUserMe,
+            "265" => CommandType::Rpl_LocalUsers,
+            "266" => CommandType::Rpl_GlobalUsers,
+            "250" => CommandType::Rpl_StatsConn,
             "256" => CommandType::Rpl_AdminMe,
             "257" => CommandType::Rpl_AdminLoc1,
             "258" => CommandType::Rpl_AdminLoc2,
             "259" => CommandType::Rpl_AdminEmail,
+            "900" => CommandType::Rpl_LoggedIn,
+            "901" => CommandType::Rpl_LoggedOut,
+            "902" => CommandType::Err_NickLocked,
+            "903" => CommandType::Rpl_SaslSuccess,
+            "904" => CommandType::Err_SaslFail,
+            "905" => CommandType::Err_SaslTooLong,
+            "906" => CommandType::Err_SaslAborted,
+            "907" => CommandType::Err_SaslAlready,
+            "908" => CommandType::Rpl_SaslMechs,
 
             _ => CommandType::Unknown
         }
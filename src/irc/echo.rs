@@ -0,0 +1,142 @@
+use std::collections::VecDeque;
+
+use irc::{ClientEvent, Command, CommandBuilder, CommandType, Timestamp};
+
+const TOKEN_PREFIX: &'static str = "echo-";
+
+/// Echoes sent messages locally for servers without `echo-message`. Each message stays
+/// pending until a PING sent after it is answered, since the server handles commands in
+/// order and would have reported an error first. Errors are matched against what was sent
+/// in the same order, so an error naming the message's target rejects it only when no
+/// earlier command to that target is still unanswered, such as a `/whois` of the same nick.
+pub struct LocalEcho {
+    next_id: u64,
+    /// What was written to the server, oldest first, back to the last answered PING.
+    sent: VecDeque<Sent>,
+    /// The newest message covered by the PING in flight.
+    checking: Option<u64>,
+}
+
+enum Sent {
+    Message(u64, String),
+    /// Any other command, by the lowercased targets an error reply could name.
+    Other(Vec<String>),
+    Ping(u64),
+}
+
+/// How many other commands are remembered while no message is pending.
+const MAX_OTHERS: usize = 32;
+
+impl LocalEcho {
+    pub fn new() -> LocalEcho {
+        LocalEcho {
+            next_id: 1,
+            sent: VecDeque::new(),
+            checking: None,
+        }
+    }
+
+    /// Records an outgoing PRIVMSG, returning its pending event and the PING to send after
+    /// it, unless one is already in flight.
+    pub fn sent(&mut self, cmd: &Command) -> (ClientEvent, Option<Command>) {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let target = cmd.get_param(0).unwrap_or("").to_string();
+        let message = cmd.get_param(1).unwrap_or("").to_string();
+        self.sent.push_back(Sent::Message(id, target.clone()));
+
+        (ClientEvent::PendingMessage(id, target, message, Timestamp::now()), self.check())
+    }
+
+    /// Records any other outgoing command, in the order it is written.
+    pub fn written(&mut self, cmd: &Command) {
+        if cmd.command == CommandType::Ping {
+            if let Some(id) = token(cmd) {
+                self.sent.push_back(Sent::Ping(id));
+                return;
+            }
+        }
+        let targets = cmd.params.data.iter()
+            .flat_map(|x| x.split(','))
+            .map(|x| x.to_lowercase())
+            .collect();
+        self.sent.push_back(Sent::Other(targets));
+
+        if !self.sent.iter().any(|x| match *x { Sent::Message(..) => true, _ => false }) {
+            while self.sent.len() > MAX_OTHERS {
+                self.sent.pop_front();
+            }
+        }
+    }
+
+    /// Feeds a command received from the server. Returns the events it settles, the PING to
+    /// send next, and whether the command was a reply to our own PING and should be hidden.
+    pub fn handle(&mut self, cmd: &Command) -> (Vec<ClientEvent>, Option<Command>, bool) {
+        match cmd.command {
+            CommandType::Pong => {
+                let id = match token(cmd) {
+                    Some(id) => id,
+                    None => return (Vec::new(), None, false),
+                };
+
+                // Everything written before the PING has been handled.
+                let mut events = Vec::new();
+                let answered = self.sent.iter().any(|x| match *x { Sent::Ping(p) => p == id, _ => false });
+                while answered {
+                    match self.sent.pop_front() {
+                        Some(Sent::Message(id, target)) => events.push(ClientEvent::MessageConfirmed(id, target)),
+                        Some(Sent::Ping(p)) if p == id => break,
+                        Some(_) => {},
+                        None => break,
+                    }
+                }
+                if self.checking == Some(id) {
+                    self.checking = None;
+                }
+                (events, self.check(), true)
+            },
+            CommandType::Err_NoSuchNick |
+            CommandType::Err_NoSuchChannel |
+            CommandType::Err_CannotSendToChan |
+            CommandType::Err_TooManyTargets => {
+                let target = cmd.get_param(1).unwrap_or("").to_lowercase();
+                let reason = cmd.params.data.last().cloned().unwrap_or("".to_string());
+                // The reply belongs to the oldest unanswered command to that target.
+                let pos = self.sent.iter().position(|x| match *x {
+                    Sent::Message(_, ref t) => t.to_lowercase() == target,
+                    Sent::Other(ref targets) => targets.contains(&target),
+                    Sent::Ping(_) => false,
+                });
+                match pos.and_then(|pos| self.sent.remove(pos)) {
+                    Some(Sent::Message(id, target)) => {
+                        (vec![ClientEvent::MessageRejected(id, target, reason)], None, false)
+                    },
+                    _ => (Vec::new(), None, false),
+                }
+            },
+            _ => (Vec::new(), None, false),
+        }
+    }
+
+    fn check(&mut self) -> Option<Command> {
+        if self.checking.is_some() { return None; }
+
+        let newest = self.sent.iter().rev()
+            .filter_map(|x| match *x { Sent::Message(id, _) => Some(id), _ => None })
+            .next();
+        newest.map(|id| {
+            self.checking = Some(id);
+            CommandBuilder::new()
+                .command(CommandType::Ping)
+                .add_param(format!("{}{}", TOKEN_PREFIX, id))
+                .build().unwrap()
+        })
+    }
+}
+
+/// The id in an `echo-N` PING or PONG token.
+fn token(cmd: &Command) -> Option<u64> {
+    cmd.params.data.last()
+        .and_then(|x| if x.starts_with(TOKEN_PREFIX) { x[TOKEN_PREFIX.len()..].parse().ok() } else { None })
+}
@@ -1,73 +1,207 @@
-use irc::UserCommand;
+use std::fmt;
 
+use irc::{Address, Arg, Args, CommandParser, CommandSpec, IrcUrl, UserCommand};
+
+#[derive(Clone, Debug, PartialEq)]
 pub enum ParseError {
     InputRequired,
+    /// An argument couldn't be understood, such as a malformed address.
     InvalidCommand,
+    /// A `/quote` line that isn't a valid IRC message.
+    InvalidMessage,
+    /// No command has this name.
+    UnknownCommand(String),
+    /// The arguments don't fit the command, whose usage this is.
+    Usage(String),
+    /// An alias that expands into itself, or too deeply.
+    AliasLoop(String),
 }
 
-#[derive(Debug, Clone, Default)]
-struct State {
-    active_window: String,
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ParseError::InputRequired => write!(f, "Input required"),
+            ParseError::InvalidCommand => write!(f, "Invalid argument"),
+            ParseError::InvalidMessage => write!(f, "Not a valid IRC message, nothing was sent"),
+            ParseError::UnknownCommand(ref name) => write!(f, "Unknown command /{}, see /help", name),
+            ParseError::Usage(ref usage) => write!(f, "Usage: {}", usage),
+            ParseError::AliasLoop(ref name) => write!(f, "Alias /{} expands into itself", name),
+        }
+    }
 }
 
 pub struct UserInputParser {
 }
 
 impl UserInputParser {
-    pub fn parse(input: String, active_window: Option<&str>) -> Result<UserCommand, ParseError> {
-        let state = State { active_window: active_window.unwrap_or("").to_string() };
-        if input.len() == 0 { return Err(ParseError::InputRequired); }
-        if !input.starts_with('/') {
-            return Ok(UserCommand::PrivMsg(state.active_window.clone(),
-                                        input));
+    fn parse_address(args: &str) -> Result<Address, ParseError> {
+        let args: Vec<&str> = args.split_whitespace().collect();
+        let tls = args.iter().any(|x| *x == "-tls");
+        match args.iter().find(|x| !x.starts_with('-')) {
+            Some(addr) => Address::parse(addr, tls).ok_or(ParseError::InvalidCommand),
+            None => Err(ParseError::InputRequired),
         }
+    }
+}
 
-        let split: Vec<&str> =  input.splitn(2, ' ').collect();
-
-        let parts = if split.len() == 1 { (split[0], "") } else { (split[0], split[1]) };
-
-        let command = match parts.0 {
-            "/nick" => UserCommand::Nick(parts.1.to_string()),
-            "/join" | "/j" => UserCommand::Join(parts.1.to_string()),
-            "/part" => {
-                if parts.1.len() == 0 {
-                    UserCommand::Part(state.active_window.clone())
-                } else {
-                    UserCommand::Part(parts.1.to_string())
-                }
-            },
-            "/msg" => {
-                let msg_parts: Vec<&str> = parts.1.splitn(2, ' ').collect();
-                UserCommand::PrivMsg(msg_parts.get(0).unwrap().to_string(), msg_parts.get(1).unwrap_or(&"").to_string())
-            },
-            "/away" => {
-                UserCommand::Away(parts.1.to_string())
-            },
-            "/back" | "/noaway" => {
-                UserCommand::Away("".to_string())
-            },
-            "/whois" => {
-                UserCommand::WhoIs(parts.1.to_string())
-            },
-            "/quit" => {
-                UserCommand::Quit(parts.1.to_string())
-            },
-            "/topic" => {
-                if parts.1.trim().len() > 0 {
-                    UserCommand::SetTopic(state.active_window.clone(), parts.1.to_string())
-                } else if parts.1.len() > 0 {
-                    UserCommand::SetTopic(state.active_window.clone(), "".to_string())
-                } else {
-                    UserCommand::GetTopic(state.active_window.clone())
-                }
-            },
-            _ => {
-                println!("{:?}", parts);
-                return Err(ParseError::InvalidCommand);
-            }
-
-        };
+fn required(name: &str) -> Arg { Arg::Required(name.to_string()) }
+fn optional(name: &str) -> Arg { Arg::Optional(name.to_string()) }
+fn target(name: &str) -> Arg { Arg::Target(name.to_string()) }
+fn text(name: &str) -> Arg { Arg::Text(name.to_string()) }
+fn optional_text(name: &str) -> Arg { Arg::OptionalText(name.to_string()) }
+fn list(name: &str) -> Arg { Arg::List(name.to_string()) }
 
-        Ok(command)
-    }
+/// The commands every `CommandRegistry` starts with.
+pub fn builtin_commands() -> Vec<CommandSpec> {
+    vec![
+        CommandSpec::new("nick", "Changes your nickname",
+                         |a: &Args| Ok(UserCommand::Nick(a.get(0))))
+            .arg(required("nick")),
+        CommandSpec::new("join", "Joins a channel, or the network and channels of an irc:// URL",
+                         |a: &Args| {
+                             let chan = a.get(0);
+                             if IrcUrl::is_url(&chan) {
+                                 Ok(UserCommand::JoinUrl(try!(IrcUrl::parse(&chan).ok_or(ParseError::InvalidCommand))))
+                             } else {
+                                 Ok(UserCommand::Join(chan, a.get_opt(1)))
+                             }
+                         })
+            .alias("j").arg(required("channel")).arg(optional("key")),
+        CommandSpec::new("part", "Leaves a channel",
+                         |a: &Args| Ok(UserCommand::Part(a.get(0))))
+            .arg(Arg::Channel),
+        CommandSpec::new("msg", "Sends a private message",
+                         |a: &Args| Ok(UserCommand::PrivMsg(a.get(0), a.get(1))))
+            .arg(required("target")).arg(text("message")),
+        CommandSpec::new("notice", "Sends a notice",
+                         |a: &Args| Ok(UserCommand::Notice(a.get(0), a.get(1))))
+            .arg(required("target")).arg(text("message")),
+        CommandSpec::new("away", "Marks you as away, or back without a message",
+                         |a: &Args| Ok(UserCommand::Away(a.get(0))))
+            .arg(optional_text("message")),
+        CommandSpec::new("back", "Marks you as no longer away",
+                         |_: &Args| Ok(UserCommand::Away("".to_string())))
+            .alias("noaway"),
+        CommandSpec::new("whois", "Shows information about a user",
+                         |a: &Args| Ok(UserCommand::WhoIs(a.get(0))))
+            .arg(required("nick")),
+        CommandSpec::new("whowas", "Shows information about a user who has left",
+                         |a: &Args| Ok(UserCommand::WhoWas(a.get(0))))
+            .arg(required("nick")),
+        CommandSpec::new("who", "Lists the users matching a mask or in a channel",
+                         |a: &Args| Ok(UserCommand::Who(a.get(0))))
+            .arg(target("mask")),
+        CommandSpec::new("names", "Lists the users in a channel",
+                         |a: &Args| Ok(UserCommand::Names(a.get(0))))
+            .arg(target("channel")),
+        CommandSpec::new("ison", "Shows which of the nicks are online",
+                         |a: &Args| Ok(UserCommand::IsOn(a.words(0))))
+            .arg(list("nick")),
+        CommandSpec::new("userhost", "Shows the hosts of users",
+                         |a: &Args| Ok(UserCommand::UserHost(a.words(0))))
+            .arg(list("nick")),
+        CommandSpec::new("topic", "Shows the channel topic, or sets it",
+                         |a: &Args| {
+                             if a.raw.trim().len() > 0 {
+                                 Ok(UserCommand::SetTopic(a.active.clone(), a.raw.clone()))
+                             } else if a.raw.len() > 0 {
+                                 Ok(UserCommand::SetTopic(a.active.clone(), "".to_string()))
+                             } else {
+                                 Ok(UserCommand::GetTopic(a.active.clone()))
+                             }
+                         })
+            .arg(optional_text("topic")),
+        CommandSpec::new("kick", "Removes a user from a channel",
+                         |a: &Args| Ok(UserCommand::Kick(a.get(0), a.get(1), a.get(2))))
+            .alias("k").arg(Arg::Channel).arg(required("nick")).arg(optional_text("reason")),
+        CommandSpec::new("kickban", "Bans a user from a channel and removes them",
+                         |a: &Args| Ok(UserCommand::KickBan(a.get(0), a.get(1), a.get(2))))
+            .alias("kb").arg(Arg::Channel).arg(required("nick")).arg(optional_text("reason")),
+        CommandSpec::new("ban", "Bans a nick or mask from a channel",
+                         |a: &Args| Ok(UserCommand::Ban(a.get(0), a.get(1))))
+            .arg(Arg::Channel).arg(required("mask")),
+        CommandSpec::new("unban", "Lifts a ban on a nick or mask",
+                         |a: &Args| Ok(UserCommand::Unban(a.get(0), a.get(1))))
+            .arg(Arg::Channel).arg(required("mask")),
+        CommandSpec::new("mode", "Shows or changes the modes of a channel or user",
+                         |a: &Args| {
+                             let mut args: Vec<String> = a.raw.split_whitespace().map(|x| x.to_string()).collect();
+                             let target = match args.first() {
+                                 Some(first) if !first.starts_with('+') && !first.starts_with('-') => Some(first.clone()),
+                                 _ => None,
+                             };
+                             let target = match target {
+                                 Some(target) => {
+                                     args.remove(0);
+                                     target
+                                 },
+                                 None if !a.active.is_empty() => a.active.clone(),
+                                 None => return Err(ParseError::InputRequired),
+                             };
+                             Ok(UserCommand::Mode(target, args))
+                         })
+            .arg(optional("target")).arg(optional_text("modes")),
+        CommandSpec::new("op", "Gives channel operator status",
+                         |a: &Args| Ok(UserCommand::Op(a.get(0), a.words(1))))
+            .arg(Arg::Channel).arg(list("nick")),
+        CommandSpec::new("deop", "Takes channel operator status",
+                         |a: &Args| Ok(UserCommand::Deop(a.get(0), a.words(1))))
+            .arg(Arg::Channel).arg(list("nick")),
+        CommandSpec::new("voice", "Lets users speak in a moderated channel",
+                         |a: &Args| Ok(UserCommand::Voice(a.get(0), a.words(1))))
+            .arg(Arg::Channel).arg(list("nick")),
+        CommandSpec::new("devoice", "Takes voice from users",
+                         |a: &Args| Ok(UserCommand::Devoice(a.get(0), a.words(1))))
+            .arg(Arg::Channel).arg(list("nick")),
+        CommandSpec::new("invite", "Invites a user to a channel",
+                         |a: &Args| Ok(UserCommand::Invite(a.get(0), a.get(1))))
+            .arg(required("nick")).arg(target("channel")),
+        CommandSpec::new("oper", "Logs in as an IRC operator",
+                         |a: &Args| Ok(UserCommand::Oper(a.get(0), a.get(1))))
+            .arg(required("name")).arg(required("password")),
+        CommandSpec::new("quote", "Sends a line to the server as is",
+                         |a: &Args| {
+                             match CommandParser::new().try_parse(format!("{}\r\n", a.get(0).trim()).as_bytes()) {
+                                 Some(cmd) => Ok(UserCommand::Raw(cmd)),
+                                 None => Err(ParseError::InvalidMessage),
+                             }
+                         })
+            .alias("raw").arg(text("line")),
+        CommandSpec::new("list", "Browses the network's channels, like /list >10 *rust* !*-ops",
+                         |a: &Args| Ok(UserCommand::List(a.get_opt(0).map(|x| x.trim().to_string()))))
+            .arg(optional_text("filter")),
+        CommandSpec::new("motd", "Shows the message of the day",
+                         |_: &Args| Ok(UserCommand::Motd)),
+        CommandSpec::new("quit", "Disconnects from every network and exits",
+                         |a: &Args| Ok(UserCommand::Quit(a.get(0))))
+            .arg(optional_text("message")),
+        CommandSpec::new("connect", "Connects to a server, or reconnects the current network",
+                         |a: &Args| {
+                             if a.raw.trim().len() == 0 {
+                                 Ok(UserCommand::Connect(None))
+                             } else {
+                                 Ok(UserCommand::Connect(Some(try!(UserInputParser::parse_address(&a.raw)))))
+                             }
+                         })
+            .arg(optional("address")).arg(optional("-tls")),
+        CommandSpec::new("server", "Moves the current network to another server",
+                         |a: &Args| Ok(UserCommand::Server(try!(UserInputParser::parse_address(&a.raw)))))
+            .arg(required("address")).arg(optional("-tls")),
+        CommandSpec::new("disconnect", "Disconnects from the current network",
+                         |a: &Args| Ok(UserCommand::Disconnect(a.get(0))))
+            .arg(optional_text("message")),
+        CommandSpec::new("reconnect", "Reconnects to the current network",
+                         |_: &Args| Ok(UserCommand::Reconnect)),
+        CommandSpec::new("reload", "Reloads the configuration file",
+                         |_: &Args| Ok(UserCommand::Reload)),
+        CommandSpec::new("alias", "Lists the aliases, shows one, or defines it, like /alias kb /kickban $1 Bye; /ban $1",
+                         |a: &Args| Ok(UserCommand::Alias(a.get_opt(0), a.get_opt(1))))
+            .arg(optional("name")).arg(optional_text("expansion")),
+        CommandSpec::new("unalias", "Removes an alias",
+                         |a: &Args| Ok(UserCommand::Unalias(a.get(0))))
+            .arg(required("name")),
+        CommandSpec::new("help", "Lists the commands, or explains one",
+                         |a: &Args| Ok(UserCommand::Help(a.get_opt(0))))
+            .arg(optional("command")),
+    ]
 }
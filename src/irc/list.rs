@@ -0,0 +1,138 @@
+use irc::{ClientEvent, Command, CommandType};
+
+/// A channel from the server's LIST reply.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ListEntry {
+    pub channel: String,
+    pub users: u32,
+    pub topic: String,
+}
+
+/// Conditions on LIST entries, written as in `/list >10 <500 *rust* !*-ops`: more or fewer
+/// users than a count, names matching a mask, and names not matching one. A word without
+/// wildcards matches as a substring.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ListFilter {
+    pub min_users: Option<u32>,
+    pub max_users: Option<u32>,
+    pub masks: Vec<String>,
+    pub excludes: Vec<String>,
+}
+
+impl ListFilter {
+    pub fn parse(input: &str) -> ListFilter {
+        let mut filter = ListFilter::default();
+        for word in input.split(|c| c == ' ' || c == ',').filter(|x| !x.is_empty()) {
+            if word.starts_with('>') {
+                if let Ok(n) = word[1..].parse::<u32>() { filter.min_users = Some(n.saturating_add(1)); }
+            } else if word.starts_with('<') {
+                if let Ok(n) = word[1..].parse::<u32>() { filter.max_users = Some(if n > 0 { n - 1 } else { 0 }); }
+            } else if word.starts_with('!') && word.len() > 1 {
+                filter.excludes.push(mask(&word[1..]));
+            } else {
+                filter.masks.push(mask(word));
+            }
+        }
+        filter
+    }
+
+    pub fn matches(&self, entry: &ListEntry) -> bool {
+        self.min_users.map(|n| entry.users >= n).unwrap_or(true) &&
+            self.max_users.map(|n| entry.users <= n).unwrap_or(true) &&
+            (self.masks.is_empty() || self.masks.iter().any(|m| glob(m, &entry.channel))) &&
+            !self.excludes.iter().any(|m| glob(m, &entry.channel))
+    }
+
+    /// The LIST parameter asking the server to apply the conditions it supports, going by
+    /// the `ELIST` token it advertises: `U` for user counts, `M` and `N` for masks. The rest
+    /// are left to `matches`.
+    pub fn elist(&self, elist: &str) -> Option<String> {
+        let elist = elist.to_uppercase();
+        let mut conditions = Vec::new();
+        if elist.contains('U') {
+            if let Some(n) = self.min_users { conditions.push(format!(">{}", n - 1)); }
+            if let Some(n) = self.max_users { conditions.push(format!("<{}", n + 1)); }
+        }
+        // A server can only apply a single mask, since several would each widen the result.
+        if elist.contains('M') && self.masks.len() == 1 {
+            conditions.push(self.masks[0].clone());
+        }
+        if elist.contains('N') {
+            conditions.extend(self.excludes.iter().map(|x| format!("!{}", x)));
+        }
+        if conditions.is_empty() { None } else { Some(conditions.join(",")) }
+    }
+}
+
+fn mask(word: &str) -> String {
+    if word.contains('*') || word.contains('?') {
+        word.to_string()
+    } else {
+        format!("*{}*", word)
+    }
+}
+
+/// Case-insensitive wildcard match, where `*` is any run of characters and `?` is one.
+pub fn glob(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, t));
+            p += 1;
+        } else if let Some((sp, st)) = star {
+            p = sp + 1;
+            t = st + 1;
+            star = Some((sp, st + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|x| *x == '*')
+}
+
+/// Collects a LIST reply into one `ClientEvent::ChannelList`.
+pub struct Listing {
+    entries: Option<Vec<ListEntry>>,
+}
+
+impl Listing {
+    pub fn new() -> Listing {
+        Listing {
+            entries: None,
+        }
+    }
+
+    /// Feeds a command received from the server. Returns `None` when it is not part of a
+    /// LIST reply, otherwise the events to send now, if any.
+    pub fn handle(&mut self, cmd: &Command) -> Option<Vec<ClientEvent>> {
+        match cmd.command {
+            CommandType::Rpl_ListStart => {
+                self.entries = Some(Vec::new());
+                Some(Vec::new())
+            },
+            CommandType::Rpl_List => {
+                let entry = ListEntry {
+                    channel: cmd.get_param(1).unwrap_or("").to_string(),
+                    users: cmd.get_param(2).and_then(|x| x.parse().ok()).unwrap_or(0),
+                    topic: cmd.get_param(3).unwrap_or("").to_string(),
+                };
+                if self.entries.is_none() {
+                    self.entries = Some(Vec::new());
+                }
+                self.entries.as_mut().unwrap().push(entry);
+                Some(Vec::new())
+            },
+            CommandType::Rpl_ListEnd => {
+                let entries = self.entries.take().unwrap_or(Vec::new());
+                Some(vec![ClientEvent::ChannelList(entries)])
+            },
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,229 @@
+use irc::{Command, CommandType, Sender};
+
+/// The channel modes of a server, from the `PREFIX` and `CHANMODES` tokens of ISUPPORT. Until
+/// the server sends them, or if it sends them malformed, the common ones are assumed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChannelModes {
+    /// Membership prefixes from highest to lowest rank, as in `PREFIX=(qaohv)~&@%+`.
+    prefixes: String,
+    /// The modes that grant each prefix.
+    prefix_modes: String,
+    /// List modes and the modes that always take a parameter, like `b` and `k`.
+    params: String,
+    /// Modes that take a parameter only when set, like `l`.
+    set_params: String,
+}
+
+impl ChannelModes {
+    pub fn new() -> ChannelModes {
+        ChannelModes {
+            prefixes: "~&@%+".to_string(),
+            prefix_modes: "qaohv".to_string(),
+            params: "beIk".to_string(),
+            set_params: "lfjL".to_string(),
+        }
+    }
+
+    /// Reads the tokens of an ISUPPORT reply, ignoring any other command.
+    pub fn handle(&mut self, command: &Command) {
+        if command.command != CommandType::Rpl_ISupport {
+            return;
+        }
+        // The tokens sit between our nick and the closing text.
+        let count = command.params.data.len();
+        for token in command.params.data.iter().take(count.saturating_sub(1)).skip(1) {
+            self.update(token);
+        }
+    }
+
+    /// Applies one ISUPPORT token, such as `PREFIX=(ov)@+`, or `-PREFIX` to go back to the
+    /// defaults.
+    pub fn update(&mut self, token: &str) {
+        let defaults = ChannelModes::new();
+        let mut parts = token.splitn(2, '=');
+        let key = parts.next().unwrap_or("");
+        let value = parts.next().unwrap_or("");
+        match key {
+            "PREFIX" => {
+                // An empty value means the server has no prefixes at all.
+                let prefix = if value.is_empty() { Some(("", "")) } else { parse_prefix(value) };
+                let (modes, prefixes) = prefix.unwrap_or((&defaults.prefix_modes, &defaults.prefixes));
+                self.prefix_modes = modes.to_string();
+                self.prefixes = prefixes.to_string();
+            },
+            "-PREFIX" => {
+                self.prefix_modes = defaults.prefix_modes;
+                self.prefixes = defaults.prefixes;
+            },
+            "CHANMODES" => {
+                let groups: Vec<&str> = value.split(',').collect();
+                if groups.len() >= 3 {
+                    self.params = format!("{}{}", groups[0], groups[1]);
+                    self.set_params = groups[2].to_string();
+                } else {
+                    self.params = defaults.params;
+                    self.set_params = defaults.set_params;
+                }
+            },
+            "-CHANMODES" => {
+                self.params = defaults.params;
+                self.set_params = defaults.set_params;
+            },
+            _ => {},
+        }
+    }
+
+    pub fn is_prefix(&self, c: char) -> bool {
+        self.prefixes.contains(c)
+    }
+
+    /// The prefix a membership mode like `o` grants.
+    pub fn prefix_for(&self, mode: char) -> Option<char> {
+        self.prefix_modes.chars().position(|x| x == mode)
+            .and_then(|i| self.prefixes.chars().nth(i))
+    }
+
+    /// Position of a prefix from the highest, or the number of prefixes for anything else.
+    pub fn rank(&self, prefix: Option<char>) -> usize {
+        prefix.and_then(|p| self.prefixes.chars().position(|x| x == p))
+            .unwrap_or(self.prefixes.chars().count())
+    }
+
+    /// Whether a channel mode takes a parameter, for reading MODE lines. List modes and
+    /// membership modes always do; keys and limits only when set.
+    pub fn has_param(&self, mode: char, add: bool) -> bool {
+        self.prefix_modes.contains(mode) || self.params.contains(mode) ||
+            (add && self.set_params.contains(mode))
+    }
+}
+
+/// Splits `(ov)@+` into its modes and prefixes, which must pair up.
+fn parse_prefix(value: &str) -> Option<(&str, &str)> {
+    if !value.starts_with('(') {
+        return None;
+    }
+    let close = match value.find(')') {
+        Some(close) => close,
+        None => return None,
+    };
+    let (modes, prefixes) = (&value[1..close], &value[close + 1..]);
+    if modes.chars().count() == prefixes.chars().count() { Some((modes, prefixes)) } else { None }
+}
+
+/// A user in a channel, as much as is known from NAMES, JOIN, WHO and the IRCv3
+/// `extended-join`, `account-notify`, `away-notify`, `multi-prefix` and `userhost-in-names`
+/// capabilities.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Member {
+    pub nick: String,
+    /// Every prefix the user has in the channel, highest first.
+    pub prefixes: String,
+    pub user: Option<String>,
+    pub host: Option<String>,
+    pub account: Option<String>,
+    pub realname: Option<String>,
+    pub away: bool,
+}
+
+impl Member {
+    pub fn new(nick: String) -> Member {
+        Member {
+            nick: nick,
+            prefixes: String::new(),
+            user: None,
+            host: None,
+            account: None,
+            realname: None,
+            away: false,
+        }
+    }
+
+    /// Whether this is the user with `nick`, which IRC compares case-insensitively.
+    pub fn is(&self, nick: &str) -> bool {
+        self.nick.to_lowercase() == nick.to_lowercase()
+    }
+
+    /// Parses a NAMES entry such as `@+nick!user@host`.
+    pub fn parse(entry: &str, modes: &ChannelModes) -> Member {
+        let name = entry.trim_left_matches(|c| modes.is_prefix(c));
+        let mut member = Member::new(name.to_string());
+        member.add_prefixes(&entry[..entry.len() - name.len()], modes);
+
+        if let Some(bang) = name.find('!') {
+            member.nick = name[..bang].to_string();
+            let userhost = &name[bang + 1..];
+            match userhost.find('@') {
+                Some(at) => {
+                    member.user = Some(userhost[..at].to_string());
+                    member.host = Some(userhost[at + 1..].to_string());
+                },
+                None => member.user = Some(userhost.to_string()),
+            }
+        }
+        member
+    }
+
+    pub fn from_sender(sender: &Sender) -> Member {
+        match *sender {
+            Sender::User(ref nick, ref user, ref host) => Member {
+                user: user.clone(),
+                host: host.clone(),
+                ..Member::new(nick.clone())
+            },
+            Sender::Server(ref name) => Member::new(name.clone()),
+        }
+    }
+
+    /// `nick!user@host`, with `*` for the parts not known.
+    pub fn hostmask(&self) -> String {
+        format!("{}!{}@{}", self.nick,
+                self.user.as_ref().map(|x| &**x).unwrap_or("*"),
+                self.host.as_ref().map(|x| &**x).unwrap_or("*"))
+    }
+
+    /// The mask to ban the user by: `*!*@host` when the host is known, which still matches
+    /// after a nick change, otherwise `nick!*@*`.
+    pub fn ban_mask(&self) -> String {
+        match self.host {
+            Some(ref host) => format!("*!*@{}", host),
+            None => format!("{}!*@*", self.nick),
+        }
+    }
+
+    /// The highest prefix, if any.
+    pub fn prefix(&self) -> Option<char> {
+        self.prefixes.chars().next()
+    }
+
+    /// Position of the highest prefix among the server's, or their count for users without
+    /// one, so that sorting by rank puts operators first.
+    pub fn rank(&self, modes: &ChannelModes) -> usize {
+        modes.rank(self.prefix())
+    }
+
+    pub fn add_prefixes(&mut self, prefixes: &str, modes: &ChannelModes) {
+        for p in prefixes.chars() {
+            if modes.is_prefix(p) && !self.prefixes.contains(p) {
+                self.prefixes.push(p);
+            }
+        }
+        let mut sorted: Vec<char> = self.prefixes.chars().collect();
+        sorted.sort_by_key(|p| modes.rank(Some(*p)));
+        self.prefixes = sorted.into_iter().collect();
+    }
+
+    /// Applies a channel mode change such as `+o`. Returns false for modes that don't grant a
+    /// prefix.
+    pub fn set_mode(&mut self, mode: char, add: bool, modes: &ChannelModes) -> bool {
+        let prefix = match modes.prefix_for(mode) {
+            Some(prefix) => prefix,
+            None => return false,
+        };
+        if add {
+            self.add_prefixes(&prefix.to_string(), modes);
+        } else {
+            self.prefixes = self.prefixes.chars().filter(|x| *x != prefix).collect();
+        }
+        true
+    }
+}
@@ -4,15 +4,41 @@ pub mod server;
 mod command_parser;
 mod command_builder;
 mod input_parser;
+mod command_registry;
 mod user_command;
 mod command;
 mod client_event;
+mod address;
+mod capabilities;
+mod url;
+mod proxy;
+mod transport;
+mod echo;
+mod timestamp;
+mod batch;
+mod member;
+mod response;
+mod whois;
+mod list;
+mod motd;
+mod numeric;
 
-pub use self::input_parser::UserInputParser;
+pub use self::input_parser::ParseError;
+pub use self::command_registry::{Arg, Args, CommandRegistry, CommandSpec};
 pub use self::user_command::UserCommand;
 pub use self::command_parser::CommandParser;
 pub use self::command_builder::CommandBuilder;
 pub use self::command::{Command, Sender};
 pub use self::command_type::CommandType;
-pub use self::client_event::{ClientEvent};
+pub use self::client_event::ClientEvent;
 pub use self::client::Client;
+pub use self::response::Response;
+pub use self::whois::Whois;
+pub use self::list::{ListEntry, ListFilter};
+pub use self::capabilities::Capabilities;
+pub use self::url::IrcUrl;
+pub use self::transport::Transport;
+pub use self::proxy::{Proxy, ProxyKind, DEFAULT_SOCKS5_PORT, DEFAULT_HTTP_PORT};
+pub use self::timestamp::Timestamp;
+pub use self::member::{ChannelModes, Member};
+pub use self::address::{Address, DEFAULT_PORT, DEFAULT_TLS_PORT};
@@ -0,0 +1,43 @@
+use irc::{ClientEvent, Command, CommandType};
+
+/// Collects the message of the day into one `ClientEvent::Motd`.
+pub struct MotdCollector {
+    lines: Option<Vec<String>>,
+}
+
+impl MotdCollector {
+    pub fn new() -> MotdCollector {
+        MotdCollector {
+            lines: None,
+        }
+    }
+
+    /// Feeds a command received from the server. Returns `None` when it is not part of the
+    /// MOTD, otherwise the events to send now, if any.
+    pub fn handle(&mut self, cmd: &Command) -> Option<Vec<ClientEvent>> {
+        match cmd.command {
+            CommandType::Rpl_MOTDStart => {
+                self.lines = Some(Vec::new());
+                Some(Vec::new())
+            },
+            CommandType::Rpl_MOTD => {
+                // Servers start each line with "- ", which is not part of the text.
+                let line = cmd.params.data.last().map(|x| &**x).unwrap_or("");
+                let line = if line.starts_with("- ") { &line[2..] } else if line == "-" { "" } else { line };
+                if self.lines.is_none() {
+                    self.lines = Some(Vec::new());
+                }
+                self.lines.as_mut().unwrap().push(line.to_string());
+                Some(Vec::new())
+            },
+            CommandType::Rpl_EndOfMOTD => {
+                Some(vec![ClientEvent::Motd(self.lines.take().unwrap_or(Vec::new()))])
+            },
+            CommandType::Err_NoMOTD => {
+                self.lines = None;
+                Some(vec![ClientEvent::Motd(Vec::new())])
+            },
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,99 @@
+use irc::{Command, Timestamp};
+use irc::CommandType as CT;
+
+impl Command {
+    /// Whether this is a numeric reply rather than a named command.
+    pub fn is_numeric(&self) -> bool {
+        let code: &str = match self.unknown {
+            Some(ref name) => name,
+            None => self.command.into(),
+        };
+        code.len() == 3 && code.chars().all(|c| c.is_digit(10))
+    }
+
+    /// The reply as a line for the user: the meaningful parameters and text, without the
+    /// server prefix or the nick it is addressed to. Commands that aren't numerics are shown
+    /// as sent.
+    pub fn describe(&self) -> String {
+        if !self.is_numeric() {
+            return self.to_string().trim_right().to_string();
+        }
+
+        // The first parameter of a numeric is our own nick, or `*` before registration.
+        let params: Vec<&str> = self.params.data.iter().skip(1).map(|x| &**x).collect();
+        let param = |i: usize| params.get(i).map(|x| *x).unwrap_or("");
+        let text = params.last().map(|x| *x).unwrap_or("");
+        let subjects = if params.is_empty() { "".to_string() } else { params[..params.len() - 1].join(" ") };
+
+        match self.command {
+            CT::Rpl_Welcome | CT::Rpl_YourHost | CT::Rpl_Created | CT::Rpl_LoggedIn |
+            CT::Rpl_LoggedOut | CT::Rpl_UnAway | CT::Rpl_NoAway | CT::Rpl_YoureOper |
+            CT::Rpl_SaslSuccess | CT::Err_SaslFail | CT::Err_SaslTooLong | CT::Err_SaslAborted |
+            CT::Err_SaslAlready | CT::Err_NickLocked => text.to_string(),
+            CT::Rpl_MyInfo => format!("Server {} running {}, user modes {}, channel modes {}",
+                                      param(0), param(1), param(2), param(3)),
+            CT::Rpl_ISupport => format!("Supported: {}", subjects),
+            CT::Rpl_LUserOp | CT::Rpl_LUserUnkown | CT::Rpl_LUserChannels => params.join(" "),
+            CT::Rpl_LocalUsers | CT::Rpl_GlobalUsers => text.to_string(),
+            CT::Rpl_UModeIs => format!("Your modes: {}", params.join(" ")),
+            CT::Rpl_SaslMechs => format!("Available SASL mechanisms: {}", param(0)),
+            CT::Rpl_UserHost => format!("Userhost: {}", text.trim()),
+            CT::Rpl_IsOn => format!("Online: {}", text.trim()),
+            CT::Rpl_Away => format!("{} is away: {}", param(0), param(1)),
+            CT::Rpl_WhoIsUser | CT::Rpl_WhoWasUser =>
+                format!("{} is {}@{} ({})", param(0), param(1), param(2), param(4)),
+            CT::Rpl_WhoIsServer => format!("{} is on {} ({})", param(0), param(1), param(2)),
+            CT::Rpl_WhoIsOperator => format!("{} is an IRC operator", param(0)),
+            CT::Rpl_WhoIsIdle => format!("{} has been idle for {} seconds", param(0), param(1)),
+            CT::Rpl_WhoIsChannels => format!("{} is on {}", param(0), param(1).trim()),
+            CT::Rpl_WhoIsAccount => format!("{} is logged in as {}", param(0), param(1)),
+            CT::Rpl_WhoIsSecure => format!("{} is using a secure connection", param(0)),
+            CT::Rpl_ChannelModeIs => {
+                let modes: Vec<&str> = params.iter().skip(1).cloned().collect();
+                format!("Modes for {}: {}", param(0), modes.join(" "))
+            },
+            CT::Rpl_NoTopic => format!("No topic is set for {}", param(0)),
+            CT::Rpl_Topic => format!("Topic for {}: {}", param(0), param(1)),
+            CT::Rpl_TopicWhoTime => {
+                match param(2).parse::<i64>() {
+                    Ok(time) => format!("Topic for {} set by {} on {}", param(0), param(1),
+                                        Timestamp::from_millis(time * 1000).format("%d %b %Y %H:%M")),
+                    Err(_) => format!("Topic for {} set by {}", param(0), param(1)),
+                }
+            },
+            CT::Rpl_Inviting => format!("Invited {} to {}", param(0), param(1)),
+            CT::Rpl_Version => format!("Version: {}", params.join(" ")),
+            CT::Rpl_WhoReply => {
+                // The text starts with the hop count.
+                let realname = text.splitn(2, ' ').nth(1).unwrap_or("");
+                format!("{} {} {}@{} {} ({})", param(0), param(4), param(1), param(2), param(5), realname)
+            },
+            CT::Rpl_NamReply => format!("Names in {}: {}", param(1), param(2)),
+            CT::Rpl_BanList => {
+                if params.len() > 3 {
+                    format!("{} bans {} (set by {})", param(0), param(1), param(2))
+                } else {
+                    format!("{} bans {}", param(0), param(1))
+                }
+            },
+            CT::Rpl_Time => format!("Time on {}: {}", param(0), text),
+            CT::Err_NicknameInUse => format!("Nickname {} is already in use", param(0)),
+            CT::Err_UserNotInChannel => format!("{} is not on {}", param(0), param(1)),
+            CT::Err_UserOnChannel => format!("{} is already on {}", param(0), param(1)),
+            _ if subjects.is_empty() => text.to_string(),
+            _ => format!("{}: {}", subjects, text),
+        }
+    }
+
+    /// The channel an error is about, for those best shown in the channel's tab: failing to
+    /// send to it, join it, or act on it.
+    pub fn error_channel(&self) -> Option<&str> {
+        match self.command {
+            CT::Err_NoSuchChannel | CT::Err_CannotSendToChan | CT::Err_NotOnChannel |
+            CT::Err_ChannelIsFull | CT::Err_InviteOnlyChan | CT::Err_BannedFromChan |
+            CT::Err_BadChannelKey | CT::Err_ChanOPrivsNeeded | CT::Err_KeySet => self.get_param(1),
+            CT::Err_UserNotInChannel | CT::Err_UserOnChannel => self.get_param(2),
+            _ => None,
+        }
+    }
+}
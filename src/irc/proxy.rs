@@ -0,0 +1,244 @@
+use tokio_core::net::TcpStream;
+use tokio_core::io::{read_exact, write_all};
+use tokio_core::reactor::Handle;
+use futures::{future, Async, Future, Poll};
+use std::io::{self, Read};
+use std::mem;
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs};
+
+use irc::Address;
+use irc::capabilities::base64;
+
+pub const DEFAULT_SOCKS5_PORT: u16 = 1080;
+pub const DEFAULT_HTTP_PORT: u16 = 8080;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ProxyKind {
+    Socks5,
+    Http,
+}
+
+/// A proxy the connection is tunnelled through before any TLS handshake.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Proxy {
+    pub kind: ProxyKind,
+    pub host: String,
+    pub port: u16,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Lets a SOCKS5 proxy resolve the server's host name rather than resolving it locally.
+    pub remote_dns: bool,
+}
+
+type Connecting = Box<Future<Item=TcpStream, Error=io::Error>>;
+
+impl Proxy {
+    pub fn new(kind: ProxyKind, host: String, port: u16) -> Proxy {
+        Proxy {
+            kind: kind,
+            host: host,
+            port: port,
+            username: None,
+            password: None,
+            remote_dns: true,
+        }
+    }
+
+    /// Connects to the proxy and asks it for a tunnel to `target`.
+    pub fn connect(&self, target: &Address, handle: &Handle) -> Connecting {
+        let addr = match resolve((&*self.host, self.port)) {
+            Ok(addr) => addr,
+            Err(e) => return Box::new(future::err(e)),
+        };
+        let stream = TcpStream::connect(&addr, handle);
+
+        let proxy = self.clone();
+        let target = target.clone();
+        Box::new(stream.and_then(move |stream| -> Connecting {
+            match proxy.kind {
+                ProxyKind::Socks5 => proxy.socks5(stream, target),
+                ProxyKind::Http => proxy.http(stream, target),
+            }
+        }))
+    }
+
+    fn credentials(&self) -> Option<(String, String)> {
+        self.username.as_ref().map(|user| (user.clone(), self.password.clone().unwrap_or("".to_string())))
+    }
+
+    fn socks5(self, stream: TcpStream, target: Address) -> Connecting {
+        let credentials = self.credentials();
+        let greeting = if credentials.is_some() { vec![5, 2, 0, 2] } else { vec![5, 1, 0] };
+
+        let request = match socks5_request(&target, self.remote_dns) {
+            Ok(request) => request,
+            Err(e) => return Box::new(future::err(e)),
+        };
+
+        let c = write_all(stream, greeting)
+            .and_then(|(stream, _)| read_exact(stream, [0u8; 2]))
+            .and_then(move |(stream, reply)| -> Connecting {
+                if reply[0] != 5 {
+                    return Box::new(future::err(proxy_error("Not a SOCKS5 proxy")));
+                }
+                match (reply[1], credentials) {
+                    (0, _) => Box::new(future::ok(stream)),
+                    (2, Some((user, pass))) => {
+                        if user.len() > 255 || pass.len() > 255 {
+                            return Box::new(future::err(proxy_error("SOCKS5 credentials are too long")));
+                        }
+                        let mut auth = vec![1, user.len() as u8];
+                        auth.extend(user.as_bytes());
+                        auth.push(pass.len() as u8);
+                        auth.extend(pass.as_bytes());
+                        Box::new(write_all(stream, auth)
+                            .and_then(|(stream, _)| read_exact(stream, [0u8; 2]))
+                            .and_then(|(stream, reply)| {
+                                if reply[1] == 0 {
+                                    Ok(stream)
+                                } else {
+                                    Err(proxy_error("SOCKS5 authentication failed"))
+                                }
+                            }))
+                    },
+                    _ => Box::new(future::err(proxy_error("SOCKS5 proxy refused every authentication method"))),
+                }
+            })
+            .and_then(move |stream| write_all(stream, request))
+            .and_then(|(stream, _)| read_exact(stream, [0u8; 4]))
+            .and_then(|(stream, reply)| -> Connecting {
+                if reply[1] != 0 {
+                    return Box::new(future::err(proxy_error(socks5_reply(reply[1]))));
+                }
+                // Skip the bound address, its length depends on the address type.
+                match reply[3] {
+                    1 => Box::new(read_exact(stream, vec![0u8; 4 + 2]).map(|(stream, _)| stream)),
+                    4 => Box::new(read_exact(stream, vec![0u8; 16 + 2]).map(|(stream, _)| stream)),
+                    3 => Box::new(read_exact(stream, [0u8; 1])
+                        .and_then(|(stream, len)| read_exact(stream, vec![0u8; len[0] as usize + 2]))
+                        .map(|(stream, _)| stream)),
+                    _ => Box::new(future::err(proxy_error("Invalid SOCKS5 reply"))),
+                }
+            });
+
+        Box::new(c)
+    }
+
+    fn http(self, stream: TcpStream, target: Address) -> Connecting {
+        let host = if target.host.contains(':') {
+            format!("[{}]:{}", target.host, target.port)
+        } else {
+            format!("{}:{}", target.host, target.port)
+        };
+        let mut request = format!("CONNECT {} HTTP/1.1\r\nHost: {}\r\n", host, host);
+        if let Some((user, pass)) = self.credentials() {
+            request.push_str(&format!("Proxy-Authorization: Basic {}\r\n",
+                                      base64(format!("{}:{}", user, pass).as_bytes())));
+        }
+        request.push_str("\r\n");
+
+        let c = write_all(stream, request.into_bytes())
+            .and_then(|(stream, _)| ReadHeaders { stream: Some(stream), buf: Vec::new() })
+            .and_then(|(stream, headers)| {
+                let headers = String::from_utf8_lossy(&headers);
+                let status = headers.lines().next().unwrap_or("");
+                let code = status.split_whitespace().nth(1).unwrap_or("");
+                if status.starts_with("HTTP/") && code.starts_with('2') {
+                    Ok(stream)
+                } else {
+                    Err(proxy_error(&format!("HTTP proxy refused the connection: {}", status)))
+                }
+            });
+
+        Box::new(c)
+    }
+}
+
+fn socks5_request(target: &Address, remote_dns: bool) -> io::Result<Vec<u8>> {
+    let mut request = vec![5, 1, 0];
+    let ip = match target.host.parse::<IpAddr>() {
+        Ok(ip) => Some(ip),
+        Err(_) if remote_dns => None,
+        Err(_) => Some(try!(resolve((&*target.host, target.port))).ip()),
+    };
+
+    match ip {
+        Some(IpAddr::V4(ip)) => {
+            request.push(1);
+            request.extend(&ip.octets());
+        },
+        Some(IpAddr::V6(ip)) => {
+            request.push(4);
+            request.extend(&ip.octets());
+        },
+        None => {
+            if target.host.len() > 255 {
+                return Err(proxy_error("Host name is too long for SOCKS5"));
+            }
+            request.push(3);
+            request.push(target.host.len() as u8);
+            request.extend(target.host.as_bytes());
+        },
+    }
+    request.push((target.port >> 8) as u8);
+    request.push(target.port as u8);
+
+    Ok(request)
+}
+
+fn socks5_reply(code: u8) -> &'static str {
+    match code {
+        1 => "SOCKS5 general failure",
+        2 => "Connection not allowed by SOCKS5 ruleset",
+        3 => "Network unreachable",
+        4 => "Host unreachable",
+        5 => "Connection refused",
+        6 => "TTL expired",
+        7 => "SOCKS5 command not supported",
+        8 => "SOCKS5 address type not supported",
+        _ => "Unknown SOCKS5 error",
+    }
+}
+
+fn proxy_error(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, msg.to_string())
+}
+
+pub fn resolve<S: ToSocketAddrs>(addr: S) -> io::Result<SocketAddr> {
+    try!(addr.to_socket_addrs()).next()
+        .ok_or(io::Error::new(io::ErrorKind::InvalidInput, "No address found"))
+}
+
+/// Reads an HTTP response head a byte at a time, so nothing sent after it by the server
+/// is consumed.
+struct ReadHeaders<S> {
+    stream: Option<S>,
+    buf: Vec<u8>,
+}
+
+impl<S: Read> Future for ReadHeaders<S> {
+    type Item = (S, Vec<u8>);
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<(S, Vec<u8>), io::Error> {
+        loop {
+            let mut byte = [0u8; 1];
+            let n = match self.stream.as_mut().expect("polled after completion").read(&mut byte) {
+                Ok(n) => n,
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(Async::NotReady),
+                Err(e) => return Err(e),
+            };
+            if n == 0 {
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "Proxy closed the connection"));
+            }
+            self.buf.push(byte[0]);
+            if self.buf.ends_with(b"\r\n\r\n") {
+                let buf = mem::replace(&mut self.buf, Vec::new());
+                return Ok(Async::Ready((self.stream.take().unwrap(), buf)));
+            }
+            if self.buf.len() > 8192 {
+                return Err(proxy_error("HTTP proxy response is too long"));
+            }
+        }
+    }
+}
@@ -0,0 +1,214 @@
+use futures::{Future, Poll};
+use futures::sync::oneshot;
+use std::collections::{HashMap, VecDeque};
+use std::io;
+
+use irc::{Command, CommandType};
+use irc::CommandType as CT;
+
+/// The replies to a command sent with `Client::request`, resolved once the server has
+/// finished answering. Fails if the connection closes first.
+pub struct Response {
+    receiver: oneshot::Receiver<Vec<Command>>,
+}
+
+impl Response {
+    pub fn new(receiver: oneshot::Receiver<Vec<Command>>) -> Response {
+        Response { receiver: receiver }
+    }
+}
+
+impl Future for Response {
+    type Item = Vec<Command>;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Vec<Command>, io::Error> {
+        self.receiver.poll()
+            .map_err(|_| io::Error::new(io::ErrorKind::NotConnected, "Connection closed"))
+    }
+}
+
+/// The numerics that answer a query, and those among them that end the answer.
+struct Expect {
+    replies: &'static [CommandType],
+    ends: &'static [CommandType],
+    /// Whether replies name the query's first parameter, which tells apart answers to
+    /// queries of the same kind.
+    targeted: bool,
+}
+
+fn expect(command: CommandType) -> Option<Expect> {
+    let (replies, ends, targeted): (&'static [CommandType], &'static [CommandType], bool) = match command {
+        CT::WhoIs => (&[CT::Rpl_WhoIsUser, CT::Rpl_WhoIsServer, CT::Rpl_WhoIsOperator,
+                        CT::Rpl_WhoIsIdle, CT::Rpl_WhoIsChannels, CT::Rpl_WhoIsAccount,
+                        CT::Rpl_WhoIsSecure, CT::Rpl_Away, CT::Err_NoSuchNick, CT::Err_NoSuchServer],
+                      &[CT::Rpl_EndOfWhoIs], true),
+        CT::WhoWas => (&[CT::Rpl_WhoWasUser, CT::Rpl_WhoIsServer, CT::Err_WasNoSuchNick],
+                       &[CT::Rpl_EndOfWhoWas], true),
+        CT::Who => (&[CT::Rpl_WhoReply], &[CT::Rpl_EndOfWho], true),
+        CT::Names => (&[CT::Rpl_NamReply], &[CT::Rpl_EndOfNames], true),
+        CT::Topic => (&[CT::Rpl_Topic],
+                      &[CT::Rpl_NoTopic, CT::Rpl_TopicWhoTime, CT::Err_NoSuchChannel,
+                        CT::Err_NotOnChannel, CT::Err_ChanOPrivsNeeded], true),
+        CT::List => (&[CT::Rpl_ListStart, CT::Rpl_List], &[CT::Rpl_ListEnd], false),
+        CT::IsOn => (&[], &[CT::Rpl_IsOn], false),
+        CT::UserHost => (&[], &[CT::Rpl_UserHost], false),
+        _ => return None,
+    };
+    Some(Expect { replies: replies, ends: ends, targeted: targeted })
+}
+
+struct Pending {
+    command: CommandType,
+    target: Option<String>,
+    replies: Vec<Command>,
+    done: oneshot::Sender<Vec<Command>>,
+}
+
+/// Matches replies to the commands sent with `Client::request`. With `labeled-response`
+/// each command carries a `label` tag that the server copies onto its replies, or onto a
+/// batch holding them. Without it, the replies are recognised by their numerics, in the
+/// order the commands were sent.
+pub struct Requests {
+    next_label: u64,
+    labeled: HashMap<String, Pending>,
+    unlabeled: VecDeque<Pending>,
+    /// Open batches that belong to a label, and whether each is the outermost one.
+    batches: HashMap<String, (String, bool)>,
+}
+
+impl Requests {
+    pub fn new() -> Requests {
+        Requests {
+            next_label: 1,
+            labeled: HashMap::new(),
+            unlabeled: VecDeque::new(),
+            batches: HashMap::new(),
+        }
+    }
+
+    /// Records an outgoing request and returns the command to send for it.
+    pub fn sent(&mut self, mut cmd: Command, done: oneshot::Sender<Vec<Command>>, labeled: bool) -> Command {
+        let pending = Pending {
+            command: cmd.command,
+            target: cmd.get_param(0).map(|x| x.to_lowercase()),
+            replies: Vec::new(),
+            done: done,
+        };
+
+        if labeled {
+            let label = format!("{}", self.next_label);
+            self.next_label += 1;
+            cmd.add_tag("label".to_string(), label.clone());
+            self.labeled.insert(label, pending);
+        } else if expect(cmd.command).is_some() {
+            self.unlabeled.push_back(pending);
+        } else {
+            // Nothing would tell us when the answer is complete.
+            pending.done.complete(Vec::new());
+        }
+        cmd
+    }
+
+    /// Feeds a command received from the server. Returns true if it answered a request and
+    /// shouldn't be handled further.
+    pub fn handle(&mut self, cmd: &Command) -> bool {
+        if cmd.command == CT::Batch {
+            if let Some(handled) = self.batch(cmd) {
+                return handled;
+            }
+        }
+
+        if let Some(label) = cmd.get_tag("label") {
+            if let Some(pending) = self.labeled.remove(label) {
+                let mut replies = pending.replies;
+                if cmd.command != CT::Ack {
+                    replies.push(cmd.clone());
+                }
+                pending.done.complete(replies);
+                return true;
+            }
+        }
+
+        if let Some(label) = cmd.get_tag("batch").and_then(|b| self.batches.get(b)).map(|x| x.0.clone()) {
+            if let Some(pending) = self.labeled.get_mut(&label) {
+                pending.replies.push(cmd.clone());
+            }
+            return true;
+        }
+
+        self.heuristic(cmd)
+    }
+
+    fn batch(&mut self, cmd: &Command) -> Option<bool> {
+        let reference = match cmd.get_param(0) {
+            Some(r) if r.len() > 1 => r,
+            _ => return None,
+        };
+        let (start, name) = (reference.starts_with('+'), &reference[1..]);
+
+        if start {
+            if let Some(label) = cmd.get_tag("label") {
+                if self.labeled.contains_key(label) {
+                    self.batches.insert(name.to_string(), (label.to_string(), true));
+                    return Some(true);
+                }
+            }
+            // A batch nested inside a labeled one is part of the reply.
+            let parent = cmd.get_tag("batch").and_then(|b| self.batches.get(b)).map(|x| x.0.clone());
+            if let Some(label) = parent {
+                self.batches.insert(name.to_string(), (label.clone(), false));
+                if let Some(pending) = self.labeled.get_mut(&label) {
+                    pending.replies.push(cmd.clone());
+                }
+                return Some(true);
+            }
+            None
+        } else {
+            match self.batches.remove(name) {
+                Some((label, true)) => {
+                    if let Some(pending) = self.labeled.remove(&label) {
+                        pending.done.complete(pending.replies);
+                    }
+                    Some(true)
+                },
+                Some((label, false)) => {
+                    if let Some(pending) = self.labeled.get_mut(&label) {
+                        pending.replies.push(cmd.clone());
+                    }
+                    Some(true)
+                },
+                None => None,
+            }
+        }
+    }
+
+    fn heuristic(&mut self, cmd: &Command) -> bool {
+        let position = self.unlabeled.iter().position(|p| {
+            let expect = match expect(p.command) {
+                Some(e) => e,
+                None => return false,
+            };
+            if !expect.replies.contains(&cmd.command) && !expect.ends.contains(&cmd.command) {
+                return false;
+            }
+            match p.target {
+                // The first parameter of a numeric is our own nick.
+                Some(ref target) if expect.targeted =>
+                    cmd.params.data.iter().skip(1).any(|x| x.to_lowercase() == *target),
+                _ => true,
+            }
+        });
+        let position = match position {
+            Some(p) => p,
+            None => return false,
+        };
+
+        self.unlabeled[position].replies.push(cmd.clone());
+        if expect(self.unlabeled[position].command).map(|e| e.ends.contains(&cmd.command)).unwrap_or(false) {
+            let pending = self.unlabeled.remove(position).unwrap();
+            pending.done.complete(pending.replies);
+        }
+        true
+    }
+}
@@ -0,0 +1,164 @@
+use libc;
+
+use std::fmt;
+use std::mem;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const DAYS: [&'static str; 7] = ["Sunday", "Monday", "Tuesday", "Wednesday", "Thursday", "Friday", "Saturday"];
+const MONTHS: [&'static str; 12] = ["January", "February", "March", "April", "May", "June", "July",
+                                    "August", "September", "October", "November", "December"];
+
+/// A moment in UTC with millisecond precision, as carried by the IRCv3 `time` tag.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Timestamp {
+    millis: i64,
+}
+
+impl Timestamp {
+    pub fn now() -> Timestamp {
+        let millis = match SystemTime::now().duration_since(UNIX_EPOCH) {
+            Ok(d) => d.as_secs() as i64 * 1000 + d.subsec_nanos() as i64 / 1000000,
+            Err(_) => 0,
+        };
+        Timestamp { millis: millis }
+    }
+
+    pub fn from_millis(millis: i64) -> Timestamp {
+        Timestamp { millis: millis }
+    }
+
+    pub fn millis(&self) -> i64 {
+        self.millis
+    }
+
+    /// Parses the server-time format, `YYYY-MM-DDThh:mm:ss.sssZ`. The fraction is optional.
+    pub fn parse(input: &str) -> Option<Timestamp> {
+        let input = input.trim();
+        if !input.ends_with('Z') || input.len() < 20 || input.bytes().any(|b| b >= 128) {
+            return None;
+        }
+        let b = input.as_bytes();
+        if b[4] != b'-' || b[7] != b'-' || b[10] != b'T' || b[13] != b':' || b[16] != b':' {
+            return None;
+        }
+
+        let num = |s: &str| s.parse::<i64>().ok();
+        let (year, month, day) = match (num(&input[0..4]), num(&input[5..7]), num(&input[8..10])) {
+            (Some(y), Some(m), Some(d)) if m >= 1 && m <= 12 && d >= 1 && d <= 31 => (y, m, d),
+            _ => return None,
+        };
+        let (hour, min, sec) = match (num(&input[11..13]), num(&input[14..16]), num(&input[17..19])) {
+            (Some(h), Some(m), Some(s)) if h < 24 && m < 60 && s < 61 => (h, m, s),
+            _ => return None,
+        };
+
+        let fraction = &input[19..input.len() - 1];
+        let millis = if fraction.is_empty() {
+            0
+        } else if fraction.starts_with('.') && fraction.len() > 1 {
+            let digits: String = fraction[1..].chars().chain("000".chars()).take(3).collect();
+            match num(&digits) {
+                Some(ms) => ms,
+                None => return None,
+            }
+        } else {
+            return None;
+        };
+
+        let secs = days_from_civil(year, month, day) * 86400 + hour * 3600 + min * 60 + sec;
+        Some(Timestamp { millis: secs * 1000 + millis })
+    }
+
+    /// Formats the local time with a subset of strftime: `%H %I %M %S %p %d %e %m %b %B %a %A
+    /// %y %Y %%`.
+    pub fn format(&self, format: &str) -> String {
+        let tm = self.local();
+        let mut out = String::new();
+        let mut chars = format.chars();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                out.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('H') => out.push_str(&format!("{:02}", tm.tm_hour)),
+                Some('I') => out.push_str(&format!("{:02}", (tm.tm_hour + 11) % 12 + 1)),
+                Some('M') => out.push_str(&format!("{:02}", tm.tm_min)),
+                Some('S') => out.push_str(&format!("{:02}", tm.tm_sec)),
+                Some('p') => out.push_str(if tm.tm_hour < 12 { "AM" } else { "PM" }),
+                Some('d') => out.push_str(&format!("{:02}", tm.tm_mday)),
+                Some('e') => out.push_str(&format!("{:2}", tm.tm_mday)),
+                Some('m') => out.push_str(&format!("{:02}", tm.tm_mon + 1)),
+                Some('b') => out.push_str(&MONTHS[tm.tm_mon as usize % 12][..3]),
+                Some('B') => out.push_str(MONTHS[tm.tm_mon as usize % 12]),
+                Some('a') => out.push_str(&DAYS[tm.tm_wday as usize % 7][..3]),
+                Some('A') => out.push_str(DAYS[tm.tm_wday as usize % 7]),
+                Some('y') => out.push_str(&format!("{:02}", (tm.tm_year + 1900) % 100)),
+                Some('Y') => out.push_str(&format!("{}", tm.tm_year + 1900)),
+                Some('%') => out.push('%'),
+                Some(other) => {
+                    out.push('%');
+                    out.push(other);
+                },
+                None => out.push('%'),
+            }
+        }
+        out
+    }
+
+    /// Whether both fall on the same local calendar day.
+    pub fn same_day(&self, other: &Timestamp) -> bool {
+        let (a, b) = (self.local(), other.local());
+        a.tm_year == b.tm_year && a.tm_yday == b.tm_yday
+    }
+
+    fn local(&self) -> libc::tm {
+        let secs = floor_div(self.millis, 1000) as libc::time_t;
+        unsafe {
+            let mut tm: libc::tm = mem::zeroed();
+            libc::localtime_r(&secs, &mut tm);
+            tm
+        }
+    }
+}
+
+/// Writes the server-time format, as used in CHATHISTORY requests.
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let secs = floor_div(self.millis, 1000);
+        let millis = self.millis - secs * 1000;
+        let days = floor_div(secs, 86400);
+        let rem = secs - days * 86400;
+        let (year, month, day) = civil_from_days(days);
+        write!(f, "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z", year, month, day,
+               rem / 3600, rem % 3600 / 60, rem % 60, millis)
+    }
+}
+
+fn floor_div(a: i64, b: i64) -> i64 {
+    if a < 0 && a % b != 0 { a / b - 1 } else { a / b }
+}
+
+// Conversions between the proleptic Gregorian calendar and days since 1970-01-01, after
+// Howard Hinnant's `days_from_civil` and `civil_from_days`.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = if y >= 0 { y } else { y - 399 } / 400;
+    let yoe = y - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719468;
+    let era = if z >= 0 { z } else { z - 146096 } / 146097;
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    (if month <= 2 { yoe + era * 400 + 1 } else { yoe + era * 400 }, month, day)
+}
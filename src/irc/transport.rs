@@ -0,0 +1,231 @@
+use tokio_core::io::Io;
+use tokio_core::reactor::{Handle, PollEvented};
+use mio::{Evented, Poll, PollOpt, Ready, Token};
+use mio::unix::EventedFd;
+use libc;
+#[cfg(feature = "tls")]
+use futures::{self, Async};
+#[cfg(feature = "tls")]
+use tokio_io::{AsyncRead, AsyncWrite};
+
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::os::unix::io::AsRawFd;
+use std::os::unix::net;
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::thread;
+
+use irc::{Address, Proxy};
+
+/// How the connection to the server is made. Every transport is framed by the same codec,
+/// and TLS is layered on top of any of them when the server address asks for it.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Transport {
+    Direct,
+    Proxy(Proxy),
+    /// A shell command whose stdin and stdout carry the connection, like OpenSSH's
+    /// ProxyCommand. `%h` and `%p` are replaced with the server's host and port, each quoted
+    /// as a single word.
+    Command(String),
+    /// A Unix domain socket, such as one exposed by a local bouncer.
+    Unix(PathBuf),
+}
+
+impl Default for Transport {
+    fn default() -> Transport {
+        Transport::Direct
+    }
+}
+
+/// A non-blocking file descriptor registered with the reactor.
+pub struct Fd<T>(T);
+
+impl<T: AsRawFd> Evented for Fd<T> {
+    fn register(&self, poll: &Poll, token: Token, interest: Ready, opts: PollOpt) -> io::Result<()> {
+        EventedFd(&self.0.as_raw_fd()).register(poll, token, interest, opts)
+    }
+
+    fn reregister(&self, poll: &Poll, token: Token, interest: Ready, opts: PollOpt) -> io::Result<()> {
+        EventedFd(&self.0.as_raw_fd()).reregister(poll, token, interest, opts)
+    }
+
+    fn deregister(&self, poll: &Poll) -> io::Result<()> {
+        EventedFd(&self.0.as_raw_fd()).deregister(poll)
+    }
+}
+
+impl<T: Read> Read for Fd<T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+
+impl<T: Write> Write for Fd<T> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+pub type UnixStream = PollEvented<Fd<net::UnixStream>>;
+
+pub fn connect_unix(path: &Path, handle: &Handle) -> io::Result<UnixStream> {
+    let stream = try!(net::UnixStream::connect(path));
+    try!(stream.set_nonblocking(true));
+    PollEvented::new(Fd(stream), handle)
+}
+
+/// Joins a separate reader and writer into one stream. Both halves must be non-blocking,
+/// returning `WouldBlock` after arranging for the current task to be woken.
+pub struct Duplex<R, W> {
+    reader: R,
+    writer: W,
+}
+
+impl<R: Read, W: Write> Duplex<R, W> {
+    pub fn new(reader: R, writer: W) -> Duplex<R, W> {
+        Duplex {
+            reader: reader,
+            writer: writer,
+        }
+    }
+}
+
+impl<R: Read, W: Write> Read for Duplex<R, W> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.reader.read(buf)
+    }
+}
+
+impl<R: Read, W: Write> Write for Duplex<R, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.writer.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+impl<R: Read, W: Write> Io for Duplex<R, W> {}
+
+/// Bridges tokio-core's `Io` and tokio-io's `AsyncRead` and `AsyncWrite`, which the TLS
+/// layer is written against, in both directions.
+#[cfg(feature = "tls")]
+pub struct Compat<S>(pub S);
+
+#[cfg(feature = "tls")]
+impl<S: Read> Read for Compat<S> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+
+#[cfg(feature = "tls")]
+impl<S: Write> Write for Compat<S> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+#[cfg(feature = "tls")]
+impl<S: Read + Write> Io for Compat<S> {}
+
+#[cfg(feature = "tls")]
+impl<S: Io> AsyncRead for Compat<S> {}
+
+#[cfg(feature = "tls")]
+impl<S: Io> AsyncWrite for Compat<S> {
+    fn shutdown(&mut self) -> futures::Poll<(), io::Error> {
+        Ok(Async::Ready(()))
+    }
+}
+
+/// The stdin and stdout of a spawned proxy command, read and written as one stream. Its
+/// stderr is logged a line at a time as status messages, and the child is killed when the stream is dropped.
+pub struct CommandStream {
+    child: Child,
+    pipes: Duplex<PollEvented<Fd<ChildStdout>>, PollEvented<Fd<ChildStdin>>>,
+}
+
+impl CommandStream {
+    pub fn spawn(command: &str, address: &Address, handle: &Handle) -> io::Result<CommandStream> {
+        let command = command.replace("%h", &shell_quote(&address.host))
+            .replace("%p", &shell_quote(&address.port.to_string()));
+        let mut child = try!(Command::new("sh")
+                             .arg("-c")
+                             .arg(command)
+                             .stdin(Stdio::piped())
+                             .stdout(Stdio::piped())
+                             .stderr(Stdio::piped())
+                             .spawn());
+
+        let stdin = child.stdin.take().unwrap();
+        let stdout = child.stdout.take().unwrap();
+        let stderr = child.stderr.take().unwrap();
+        thread::spawn(move || {
+            for line in BufReader::new(stderr).lines() {
+                match line {
+                    Ok(line) => info!("Proxy command: {}", line),
+                    Err(_) => break,
+                }
+            }
+        });
+        try!(set_nonblocking(&stdin));
+        try!(set_nonblocking(&stdout));
+
+        Ok(CommandStream {
+            pipes: Duplex::new(try!(PollEvented::new(Fd(stdout), handle)),
+                               try!(PollEvented::new(Fd(stdin), handle))),
+            child: child,
+        })
+    }
+}
+
+impl Read for CommandStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.pipes.read(buf)
+    }
+}
+
+impl Write for CommandStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.pipes.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.pipes.flush()
+    }
+}
+
+impl Io for CommandStream {}
+
+impl Drop for CommandStream {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Quotes `value` as one word for `sh`.
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+fn set_nonblocking<T: AsRawFd>(fd: &T) -> io::Result<()> {
+    let fd = fd.as_raw_fd();
+    unsafe {
+        let flags = libc::fcntl(fd, libc::F_GETFL);
+        if flags < 0 || libc::fcntl(fd, libc::F_SETFL, flags | libc::O_NONBLOCK) < 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
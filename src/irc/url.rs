@@ -0,0 +1,116 @@
+use irc::{Address, DEFAULT_PORT, DEFAULT_TLS_PORT};
+
+/// An `irc://` or `ircs://` URL, following the draft IRC URL scheme:
+/// `irc://[nick@]host[:port]/[#chan1,#chan2][?key1,key2]`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct IrcUrl {
+    pub host: String,
+    /// The port, if the URL gave one.
+    pub port: Option<u16>,
+    pub tls: bool,
+    pub nick: Option<String>,
+    /// Channels to join, paired with the key in the same position of the query.
+    pub channels: Vec<(String, Option<String>)>,
+}
+
+impl IrcUrl {
+    pub fn is_url(input: &str) -> bool {
+        input.starts_with("irc://") || input.starts_with("ircs://")
+    }
+
+    /// The server to connect to, on the standard port for the scheme unless one was given.
+    pub fn address(&self) -> Address {
+        let port = self.port.unwrap_or(if self.tls { DEFAULT_TLS_PORT } else { DEFAULT_PORT });
+        Address::new(self.host.clone(), port, self.tls)
+    }
+
+    /// Whether `address` is the URL's server, on any port if the URL didn't name one.
+    pub fn matches(&self, address: &Address) -> bool {
+        self.host.to_lowercase() == address.host.to_lowercase() &&
+            self.port.map(|port| port == address.port).unwrap_or(true)
+    }
+
+    pub fn parse(input: &str) -> Option<IrcUrl> {
+        let input = input.trim();
+        let (tls, rest) = if input.starts_with("irc://") {
+            (false, &input[6..])
+        } else if input.starts_with("ircs://") {
+            (true, &input[7..])
+        } else {
+            return None;
+        };
+
+        let (authority, path) = match rest.find('/') {
+            Some(i) => (&rest[..i], &rest[i + 1..]),
+            None => (rest, ""),
+        };
+
+        let (nick, host) = match authority.rfind('@') {
+            Some(i) => (Some(decode(&authority[..i])), &authority[i + 1..]),
+            None => (None, authority),
+        };
+        // `Address` fills in a default port, so tell whether one was written.
+        let explicit_port = match host.rfind(']') {
+            Some(i) => host[i..].contains(':'),
+            None => host.contains(':'),
+        };
+        let address = match Address::parse(host, tls) {
+            Some(address) => address,
+            None => return None,
+        };
+
+        let (targets, query) = match path.find('?') {
+            Some(i) => (&path[..i], &path[i + 1..]),
+            None => (path, ""),
+        };
+        let mut keys = query.split(',').map(decode);
+
+        let targets: Vec<String> = targets.split(',').map(decode).collect();
+        // With `isnick` the target is a user, which is nothing to join.
+        let is_nick = targets.iter().any(|x| x == "isnick");
+
+        let mut channels = Vec::new();
+        for target in targets.into_iter().filter(|_| !is_nick) {
+            match &*target {
+                "" | "isnick" | "isserver" | "ischannel" | "needkey" | "needpass" => continue,
+                _ => {}
+            }
+            let channel = if target.starts_with(|c: char| c == '#' || c == '&' || c == '+' || c == '!') {
+                target
+            } else {
+                format!("#{}", target)
+            };
+            let key = keys.next().and_then(|x| if x.len() > 0 { Some(x) } else { None });
+            channels.push((channel, key));
+        }
+
+        Some(IrcUrl {
+            host: address.host,
+            port: if explicit_port { Some(address.port) } else { None },
+            tls: tls,
+            nick: nick.and_then(|x| if x.len() > 0 { Some(x) } else { None }),
+            channels: channels,
+        })
+    }
+}
+
+/// Decodes `%XX` escapes, leaving malformed ones as they are.
+fn decode(input: &str) -> String {
+    let bytes = input.as_bytes();
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = ::std::str::from_utf8(&bytes[i + 1..i + 3]).ok()
+                .and_then(|x| u8::from_str_radix(x, 16).ok());
+            if let Some(b) = hex {
+                out.push(b);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
@@ -1,22 +1,69 @@
-use irc::{Command,CommandType as CT,CommandBuilder};
+use irc::{Address,IrcUrl,Command,CommandType as CT,CommandBuilder,Timestamp};
 
 pub enum UserCommand {
+    Pass(String),
     Nick(String),
     User(String, String, String),
-    Join(String),
+    Join(String, Option<String>),
+    JoinUrl(IrcUrl),
     PrivMsg(String, String),
     WhoIs(String),
+    Who(String),
+    List(Option<String>),
+    Motd,
     Away(String),
     Part(String),
+    /// Removes a user from a channel: channel, nick, reason.
+    Kick(String, String, String),
+    /// Bans a nick or mask from a channel. A nick is sent as is, which servers take to mean
+    /// `nick!*@*`; the terminal client turns known nicks into host masks first.
+    Ban(String, String),
+    Unban(String, String),
+    /// Bans a user and then kicks them: channel, nick, reason.
+    KickBan(String, String, String),
+    /// Sets or queries the modes of a channel or user, with the mode string and arguments.
+    Mode(String, Vec<String>),
+    Op(String, Vec<String>),
+    Deop(String, Vec<String>),
+    Voice(String, Vec<String>),
+    Devoice(String, Vec<String>),
+    /// Invites a nick to a channel.
+    Invite(String, String),
+    Notice(String, String),
+    Names(String),
+    WhoWas(String),
+    IsOn(Vec<String>),
+    UserHost(Vec<String>),
+    /// Gains operator privileges with a name and password.
+    Oper(String, String),
     Quit(String),
     GetTopic(String),
     SetTopic(String, String),
+    /// Requests the newest messages in a target, up to a limit, with draft/chathistory.
+    HistoryLatest(String, u32),
+    /// Requests messages in a target sent before a time, up to a limit.
+    HistoryBefore(String, Timestamp, u32),
+    Connect(Option<Address>),
+    Server(Address),
+    Disconnect(String),
+    Reconnect,
+    Reload,
+    /// Lists the commands, or explains the one named.
+    Help(Option<String>),
+    /// Lists the aliases, shows the one named, or defines it.
+    Alias(Option<String>, Option<String>),
+    Unalias(String),
+    /// A command sent as is, for anything the other variants don't cover.
+    Raw(Command),
 }
 
 impl UserCommand {
     pub fn to_command(self) -> Result<Command, String> {
         let b = CommandBuilder::new();
         match self {
+            UserCommand::Pass(pass) => Ok(b.command(CT::Pass)
+                                       .add_param(pass)
+                                       .build().unwrap()),
             UserCommand::Nick(nick) => Ok(b.command(CT::Nick)
                                        .add_param(nick)
                                        .build().unwrap()),
@@ -26,8 +73,12 @@ impl UserCommand {
                                        .add_param("*".to_string())
                                        .add_param(name)
                                        .build().unwrap()),
-            UserCommand::Join(chan) => Ok(b.command(CT::Join)
+            UserCommand::Join(chan, None) => Ok(b.command(CT::Join)
+                                       .add_param(chan)
+                                       .build().unwrap()),
+            UserCommand::Join(chan, Some(key)) => Ok(b.command(CT::Join)
                                        .add_param(chan)
+                                       .add_param(key)
                                        .build().unwrap()),
             UserCommand::PrivMsg(targ, msg) => Ok(b.command(CT::PrivMsg)
                                        .add_param(targ)
@@ -36,12 +87,70 @@ impl UserCommand {
             UserCommand::WhoIs(targ) => Ok(b.command(CT::WhoIs)
                                        .add_param(targ)
                                        .build().unwrap()),
+            UserCommand::Who(mask) => Ok(b.command(CT::Who)
+                                       .add_param(mask)
+                                       .build().unwrap()),
+            UserCommand::List(None) => Ok(b.command(CT::List)
+                                       .build().unwrap()),
+            UserCommand::List(Some(params)) => Ok(b.command(CT::List)
+                                       .add_param(params)
+                                       .build().unwrap()),
+            UserCommand::Motd => Ok(b.command(CT::Motd)
+                                       .build().unwrap()),
             UserCommand::Away(msg) => Ok(b.command(CT::Away)
                                        .add_param(msg)
                                        .build().unwrap()),
             UserCommand::Part(targ) => Ok(b.command(CT::Part)
                                        .add_param(targ)
                                        .build().unwrap()),
+            UserCommand::Kick(chan, nick, reason) => {
+                let b = b.command(CT::Kick).add_param(chan).add_param(nick);
+                Ok(if reason.is_empty() { b } else { b.add_param(reason) }.build().unwrap())
+            },
+            UserCommand::Ban(chan, mask) => Ok(b.command(CT::Mode)
+                                       .add_param(chan)
+                                       .add_param("+b".to_string())
+                                       .add_param(mask)
+                                       .build().unwrap()),
+            UserCommand::Unban(chan, mask) => Ok(b.command(CT::Mode)
+                                       .add_param(chan)
+                                       .add_param("-b".to_string())
+                                       .add_param(mask)
+                                       .build().unwrap()),
+            UserCommand::Mode(target, args) => {
+                let b = b.command(CT::Mode).add_param(target);
+                Ok(b.add_params(args).build().unwrap())
+            },
+            UserCommand::Op(chan, nicks) => Ok(member_modes(chan, "+o", nicks)),
+            UserCommand::Deop(chan, nicks) => Ok(member_modes(chan, "-o", nicks)),
+            UserCommand::Voice(chan, nicks) => Ok(member_modes(chan, "+v", nicks)),
+            UserCommand::Devoice(chan, nicks) => Ok(member_modes(chan, "-v", nicks)),
+            UserCommand::Invite(nick, chan) => Ok(b.command(CT::Invite)
+                                       .add_param(nick)
+                                       .add_param(chan)
+                                       .build().unwrap()),
+            UserCommand::Notice(targ, msg) => Ok(b.command(CT::Notice)
+                                       .add_param(targ)
+                                       .add_param(msg)
+                                       .build().unwrap()),
+            UserCommand::Names(chan) => Ok(b.command(CT::Names)
+                                       .add_param(chan)
+                                       .build().unwrap()),
+            UserCommand::WhoWas(nick) => Ok(b.command(CT::WhoWas)
+                                       .add_param(nick)
+                                       .build().unwrap()),
+            UserCommand::IsOn(nicks) => {
+                let b = b.command(CT::IsOn);
+                Ok(b.add_params(nicks).build().unwrap())
+            },
+            UserCommand::UserHost(nicks) => {
+                let b = b.command(CT::UserHost);
+                Ok(b.add_params(nicks).build().unwrap())
+            },
+            UserCommand::Oper(name, pass) => Ok(b.command(CT::Oper)
+                                       .add_param(name)
+                                       .add_param(pass)
+                                       .build().unwrap()),
             UserCommand::Quit(msg) => Ok(b.command(CT::Quit)
                                        .add_param(msg)
                                        .build().unwrap()),
@@ -52,7 +161,38 @@ impl UserCommand {
                                                      .add_param(chan)
                                                      .add_param(topic)
                                                      .build().unwrap()),
+            UserCommand::HistoryLatest(targ, limit) => Ok(b.command(CT::ChatHistory)
+                                                          .add_param("LATEST".to_string())
+                                                          .add_param(targ)
+                                                          .add_param("*".to_string())
+                                                          .add_param(limit.to_string())
+                                                          .build().unwrap()),
+            UserCommand::HistoryBefore(targ, time, limit) => Ok(b.command(CT::ChatHistory)
+                                                                .add_param("BEFORE".to_string())
+                                                                .add_param(targ)
+                                                                .add_param(format!("timestamp={}", time))
+                                                                .add_param(limit.to_string())
+                                                                .build().unwrap()),
+            UserCommand::Raw(cmd) => Ok(cmd),
+            UserCommand::KickBan(..) |
+            UserCommand::JoinUrl(..) |
+            UserCommand::Connect(..) |
+            UserCommand::Server(..) |
+            UserCommand::Disconnect(..) |
+            UserCommand::Reconnect |
+            UserCommand::Reload |
+            UserCommand::Help(..) |
+            UserCommand::Alias(..) |
+            UserCommand::Unalias(..) => Err("Client command".to_string()),
         }
 
     }
 }
+
+/// A MODE giving or taking a prefix mode from several nicks at once, like `+oo a b`.
+fn member_modes(chan: String, mode: &str, nicks: Vec<String>) -> Command {
+    let (sign, letter) = mode.split_at(1);
+    let modes = format!("{}{}", sign, nicks.iter().map(|_| letter).collect::<String>());
+    let b = CommandBuilder::new().command(CT::Mode).add_param(chan).add_param(modes);
+    b.add_params(nicks).build().unwrap()
+}
@@ -0,0 +1,139 @@
+use std::collections::HashMap;
+
+use irc::{ClientEvent, Command, CommandType, Timestamp};
+use irc::CommandType as CT;
+
+/// Everything the server told us about a user in reply to WHOIS.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Whois {
+    pub nick: String,
+    pub user: Option<String>,
+    pub host: Option<String>,
+    pub realname: Option<String>,
+    pub server: Option<String>,
+    pub server_info: Option<String>,
+    /// Seconds since the user last spoke.
+    pub idle: Option<u64>,
+    pub signon: Option<Timestamp>,
+    /// Channels with the user's prefix in each, such as `@#mass`.
+    pub channels: Vec<String>,
+    pub account: Option<String>,
+    pub away: Option<String>,
+    pub operator: bool,
+    pub secure: bool,
+}
+
+impl Whois {
+    pub fn new(nick: String) -> Whois {
+        Whois {
+            nick: nick,
+            user: None,
+            host: None,
+            realname: None,
+            server: None,
+            server_info: None,
+            idle: None,
+            signon: None,
+            channels: Vec::new(),
+            account: None,
+            away: None,
+            operator: false,
+            secure: false,
+        }
+    }
+
+    /// Builds the result from the replies to a WHOIS, as collected by `Client::request`.
+    /// Returns `None` if they don't describe a user.
+    pub fn from_replies(replies: &[Command]) -> Option<Whois> {
+        let nick = match replies.iter().find(|x| x.command == CT::Rpl_WhoIsUser) {
+            Some(cmd) => cmd.get_param(1).unwrap_or("").to_string(),
+            None => return None,
+        };
+        let mut whois = Whois::new(nick);
+        for reply in replies {
+            whois.update(reply);
+        }
+        Some(whois)
+    }
+
+    /// Adds what a reply says about the user. Returns false if it isn't a WHOIS reply.
+    pub fn update(&mut self, cmd: &Command) -> bool {
+        let param = |i| cmd.get_param(i).map(|x| x.to_string());
+        match cmd.command {
+            CT::Rpl_WhoIsUser => {
+                self.user = param(2);
+                self.host = param(3);
+                self.realname = param(5);
+            },
+            CT::Rpl_WhoIsServer => {
+                self.server = param(2);
+                self.server_info = param(3);
+            },
+            CT::Rpl_WhoIsOperator => self.operator = true,
+            CT::Rpl_WhoIsIdle => {
+                self.idle = cmd.get_param(2).and_then(|x| x.parse().ok());
+                // Only servers that send the signon time have a fourth parameter before the text.
+                if cmd.params.data.len() > 4 {
+                    self.signon = cmd.get_param(3).and_then(|x| x.parse::<i64>().ok())
+                        .map(|x| Timestamp::from_millis(x * 1000));
+                }
+            },
+            CT::Rpl_WhoIsChannels => {
+                let channels = cmd.get_param(2).unwrap_or("");
+                self.channels.extend(channels.split(' ').filter(|x| !x.is_empty()).map(|x| x.to_string()));
+            },
+            CT::Rpl_WhoIsAccount => self.account = param(2),
+            CT::Rpl_WhoIsSecure => self.secure = true,
+            CT::Rpl_Away => self.away = param(2),
+            _ => return false,
+        }
+        true
+    }
+}
+
+/// Collects WHOIS replies into one `ClientEvent::WhoisResult` per user, delivered when the
+/// server ends the list.
+pub struct Whoises {
+    open: HashMap<String, Whois>,
+}
+
+impl Whoises {
+    pub fn new() -> Whoises {
+        Whoises {
+            open: HashMap::new(),
+        }
+    }
+
+    /// Feeds a command received from the server. Returns `None` when it is not part of a
+    /// WHOIS reply, otherwise the events to send now, if any.
+    pub fn handle(&mut self, cmd: &Command) -> Option<Vec<ClientEvent>> {
+        let nick = match cmd.get_param(1) {
+            Some(nick) => nick.to_lowercase(),
+            None => return None,
+        };
+        match cmd.command {
+            CommandType::Rpl_EndOfWhoIs => {
+                self.open.remove(&nick).map(|whois| vec![ClientEvent::WhoisResult(whois)])
+            },
+            // Also sent when messaging an away user, so only part of a WHOIS already begun.
+            CommandType::Rpl_Away => {
+                self.open.get_mut(&nick).map(|whois| {
+                    whois.update(cmd);
+                    Vec::new()
+                })
+            },
+            CommandType::Rpl_WhoIsUser |
+            CommandType::Rpl_WhoIsServer |
+            CommandType::Rpl_WhoIsOperator |
+            CommandType::Rpl_WhoIsIdle |
+            CommandType::Rpl_WhoIsChannels |
+            CommandType::Rpl_WhoIsAccount |
+            CommandType::Rpl_WhoIsSecure => {
+                let name = cmd.get_param(1).unwrap_or("").to_string();
+                self.open.entry(nick).or_insert_with(|| Whois::new(name)).update(cmd);
+                Some(Vec::new())
+            },
+            _ => None,
+        }
+    }
+}
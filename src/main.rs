@@ -3,17 +3,35 @@ extern crate nom;
 #[macro_use]
 extern crate log;
 extern crate termion;
+extern crate futures;
+extern crate tokio_core;
+extern crate libc;
+extern crate mio;
+extern crate toml;
+#[cfg(feature = "tls")]
+extern crate native_tls;
+#[cfg(feature = "tls")]
+extern crate tokio_tls;
+#[cfg(feature = "tls")]
+extern crate tokio_io;
 
 #[macro_use]
 extern crate clap;
 use clap::{App, Arg};
 
 mod irc;
-use irc::Client;
 
 mod term;
 use term::Terminal;
 
+mod config;
+use config::{AutoJoin, Config, Overrides};
+use irc::IrcUrl;
+
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process;
+
 #[cfg(test)]
 mod tests;
 
@@ -22,40 +40,104 @@ fn main() {
                 .version(crate_version!())
                 .author(crate_authors!())
                 .about("Simple terminal based IRC client")
+                .arg(Arg::with_name("url")
+                     .help("An irc:// or ircs:// URL to connect to, as in irc://nick@host:port/#channel")
+                     .conflicts_with("server")
+                     .index(1))
+                .arg(Arg::with_name("config")
+                     .short("c")
+                     .long("config")
+                     .help("Sets the configuration file, defaults to $XDG_CONFIG_HOME/mass-irc/config.toml")
+                     .takes_value(true))
                 .arg(Arg::with_name("server")
                      .short("s")
                      .long("server")
-                     .help("Sets the IRC server to connect to")
-                     .takes_value(true)
-                     .default_value("127.0.0.1"))
+                     .help("Sets the IRC server, or configured network, to connect to")
+                     .takes_value(true))
                 .arg(Arg::with_name("port")
                      .short("p")
                      .long("port")
                      .help("Sets the server port to connect to")
-                     .takes_value(true)
-                     .default_value("6667"))
+                     .takes_value(true))
+                .arg(Arg::with_name("tls")
+                     .long("tls")
+                     .help("Connects to the server using TLS"))
+                .arg(Arg::with_name("password")
+                     .long("password")
+                     .help("Sets the server password, sent before registering")
+                     .takes_value(true))
                 .arg(Arg::with_name("nick")
                      .short("n")
                      .long("nick")
                      .help("Sets your default nickname")
-                     .takes_value(true)
-                     .default_value("NickMass"))
+                     .takes_value(true))
                 .arg(Arg::with_name("realname")
                      .short("r")
                      .long("realname")
                      .help("Sets your default real name")
-                     .takes_value(true)
-                     .default_value("Nick Massey"))
+                     .takes_value(true))
                 .get_matches();
 
-    let server = matches.value_of("server").unwrap();
-    let port: u16 = matches.value_of("port").unwrap().parse().unwrap();
-    let nick = matches.value_of("nick").unwrap();
-    let realname = matches.value_of("realname").unwrap();
+    let port = match matches.value_of("port") {
+        Some(port) => match port.parse() {
+            Ok(port) => Some(port),
+            Err(_) => {
+                let _ = writeln!(io::stderr(), "Invalid port: {}", port);
+                process::exit(1);
+            }
+        },
+        None => None,
+    };
+
+    let mut overrides = Overrides {
+        nick: matches.value_of("nick").map(|x| x.to_string()),
+        realname: matches.value_of("realname").map(|x| x.to_string()),
+        server: matches.value_of("server").map(|x| x.to_string()),
+        port: port,
+        tls: matches.is_present("tls"),
+        password: matches.value_of("password").map(|x| x.to_string()),
+        channels: Vec::new(),
+    };
+
+    if let Some(url) = matches.value_of("url") {
+        let url = match IrcUrl::parse(url) {
+            Some(url) => url,
+            None => {
+                let _ = writeln!(io::stderr(), "Invalid IRC URL: {}", url);
+                process::exit(1);
+            }
+        };
+        if let (Some(port), Some(url_port)) = (overrides.port, url.port) {
+            if port != url_port {
+                let _ = writeln!(io::stderr(), "--port {} conflicts with port {} in the URL", port, url_port);
+                process::exit(1);
+            }
+        }
+        overrides.server = Some(url.host);
+        overrides.port = overrides.port.or(url.port);
+        overrides.tls = overrides.tls || url.tls;
+        overrides.nick = overrides.nick.or(url.nick);
+        overrides.channels = url.channels.into_iter()
+            .map(|(channel, key)| AutoJoin { channel: channel, key: key })
+            .collect();
+    }
 
-    let client = Client::connect((server, port));
+    let path = matches.value_of("config").map(PathBuf::from).or(Config::default_path());
+    let config = match path {
+        Some(ref path) => Config::load(path),
+        None => Ok(Config::default()),
+    };
+    let mut config = match config {
+        Ok(config) => config,
+        Err(e) => {
+            let _ = writeln!(io::stderr(), "Unable to load configuration: {}", e);
+            process::exit(1);
+        }
+    };
+    config.apply(&overrides);
 
-    let mut terminal = Terminal::new(client, nick.to_string(), realname.to_string());
+    let mut terminal = Terminal::new(config, path, overrides);
     let _ = terminal.init_log();
+    terminal.connect_all();
     terminal.run();
 }
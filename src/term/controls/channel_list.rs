@@ -0,0 +1,153 @@
+use term::{TermBuffer, Color, Surface, Point, Rect};
+use irc::{ListEntry, ListFilter};
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ListSort {
+    Users,
+    Name,
+}
+
+/// A browser for the channels from a LIST reply, drawn in place of the message pane. Rows
+/// can be filtered and sorted, and the selected one is joined with Enter.
+pub struct ChannelList {
+    entries: Vec<ListEntry>,
+    /// Indices into `entries` that pass the filter, in sort order.
+    shown: Vec<usize>,
+    filter: ListFilter,
+    sort: ListSort,
+    selected: usize,
+    scroll: usize,
+    page: usize,
+    loading: bool,
+    dirty: bool,
+}
+
+impl ChannelList {
+    pub fn new(filter: ListFilter) -> ChannelList {
+        ChannelList {
+            entries: Vec::new(),
+            shown: Vec::new(),
+            filter: filter,
+            sort: ListSort::Users,
+            selected: 0,
+            scroll: 0,
+            page: 1,
+            loading: true,
+            dirty: true,
+        }
+    }
+
+    pub fn set_dirty(&mut self) { self.dirty = true; }
+    pub fn is_dirty(&self) -> bool { self.dirty }
+
+    pub fn set_entries(&mut self, entries: Vec<ListEntry>) {
+        self.entries = entries;
+        self.loading = false;
+        self.refresh();
+    }
+
+    pub fn set_filter(&mut self, filter: ListFilter) {
+        self.filter = filter;
+        self.refresh();
+    }
+
+    pub fn toggle_sort(&mut self) {
+        self.sort = match self.sort {
+            ListSort::Users => ListSort::Name,
+            ListSort::Name => ListSort::Users,
+        };
+        self.refresh();
+    }
+
+    /// The channel on the selected row.
+    pub fn selected(&self) -> Option<&str> {
+        self.shown.get(self.selected).map(|i| &*self.entries[*i].channel)
+    }
+
+    pub fn select_next(&mut self) {
+        self.select(self.selected as i32 + 1);
+    }
+
+    pub fn select_prev(&mut self) {
+        self.select(self.selected as i32 - 1);
+    }
+
+    pub fn page_down(&mut self) {
+        self.select(self.selected as i32 + self.page as i32);
+    }
+
+    pub fn page_up(&mut self) {
+        self.select(self.selected as i32 - self.page as i32);
+    }
+
+    fn select(&mut self, row: i32) {
+        let last = self.shown.len() as i32 - 1;
+        let row = if row > last { last } else { row };
+        self.selected = if row < 0 { 0 } else { row as usize };
+        self.set_dirty();
+    }
+
+    fn refresh(&mut self) {
+        let ref filter = self.filter;
+        let ref entries = self.entries;
+        self.shown = (0..entries.len()).filter(|i| filter.matches(&entries[*i])).collect();
+        match self.sort {
+            ListSort::Users => self.shown.sort_by_key(|i| (::std::u32::MAX - entries[*i].users,
+                                                           entries[*i].channel.to_lowercase())),
+            ListSort::Name => self.shown.sort_by_key(|i| entries[*i].channel.to_lowercase()),
+        }
+        self.selected = 0;
+        self.scroll = 0;
+        self.set_dirty();
+    }
+
+    pub fn render(&mut self, window: &mut TermBuffer) {
+        if !window.is_invalid() && !self.is_dirty() { return; }
+
+        let width = window.width();
+        let height = window.height() - 3;
+        if height < 2 { return; }
+        let mut surface = Surface::new(Rect(Point(0, 0), width, height));
+
+        let sort = match self.sort {
+            ListSort::Users => "users",
+            ListSort::Name => "name",
+        };
+        let header = if self.loading {
+            " Loading channel list...".to_string()
+        } else {
+            format!(" {} of {} channels, by {}. Enter joins, Ctrl-S sorts, type to filter, Esc closes",
+                    self.shown.len(), self.entries.len(), sort)
+        };
+        surface.text(&header, Point(0, 0));
+        surface.set_color(Point(0, 0), Some(Color::Black), Some(Color::White));
+
+        // Keep the selection on screen.
+        let rows = (height - 1) as usize;
+        self.page = if rows > 1 { rows - 1 } else { 1 };
+        if self.selected < self.scroll {
+            self.scroll = self.selected;
+        } else if self.selected >= self.scroll + rows {
+            self.scroll = self.selected + 1 - rows;
+        }
+
+        let visible: Vec<usize> = self.shown.iter().skip(self.scroll).take(rows).cloned().collect();
+        let longest = visible.iter().map(|i| self.entries[*i].channel.chars().count()).max().unwrap_or(0);
+        let name_width = if longest > width as usize / 3 { width as usize / 3 } else { longest };
+        for (row, index) in visible.iter().enumerate() {
+            let ref entry = self.entries[*index];
+            let topic: String = entry.topic.chars().filter(|c| (*c as u32) >= 0x20).collect();
+            let line = format!(" {:>6}  {:<width$.width$}  {}", entry.users, entry.channel, topic,
+                               width = name_width);
+            let y = row as i32 + 1;
+            surface.text(&line, Point(0, y));
+            let selected = self.scroll + row == self.selected;
+            let (fg, bg) = if selected { (Color::LightWhite, Color::Blue) } else { (Color::White, Color::Black) };
+            surface.set_color(Point(0, y), Some(fg), Some(bg));
+        }
+        surface.set_color(Point(0, visible.len() as i32 + 1), Some(Color::White), Some(Color::Black));
+
+        window.blit(&surface, Point(0, 2));
+        self.dirty = false;
+    }
+}
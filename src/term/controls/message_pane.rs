@@ -1,12 +1,37 @@
 use term::{TabToken, TermBuffer, Color, Surface, Point, Rect};
 use term::term_string::TermString;
 use term::buffer::Glyph;
+use irc::Timestamp;
 
 pub struct MessagePane {
     messages: Vec<(Option<TabToken>, Message)>,
     dirty: bool,
-    width: i32,
+    layout: Layout,
     scroll: i32,
+    max_scroll: i32,
+    scroll_lines: i32,
+    /// Columns on the right left to other controls, such as the nick list.
+    right_margin: i32,
+}
+
+/// Everything a message's rendering depends on besides its own content.
+#[derive(Clone, PartialEq)]
+struct Layout {
+    width: i32,
+    name_width: i32,
+    time_format: String,
+}
+
+impl Layout {
+    /// The timestamp column for `time`, with its trailing space, or nothing when timestamps
+    /// are hidden.
+    fn stamp(&self, time: &Timestamp) -> String {
+        if self.time_format.is_empty() {
+            String::new()
+        } else {
+            format!("{} ", time.format(&self.time_format))
+        }
+    }
 }
 
 impl MessagePane {
@@ -14,76 +39,211 @@ impl MessagePane {
         MessagePane {
             messages: Vec::new(),
             dirty: true,
-            width: 0, //I don't like this
+            layout: Layout {
+                width: 0, //I don't like this
+                name_width: 14,
+                time_format: "%H:%M".to_string(),
+            },
             scroll: 0,
+            max_scroll: 0,
+            scroll_lines: 5,
+            right_margin: 0,
+        }
+    }
+
+    /// Sets the width of the nickname column, the strftime-style format of the timestamp
+    /// column, and how many lines a scroll moves.
+    pub fn set_layout(&mut self, name_width: i32, time_format: String, scroll_lines: i32) {
+        let layout = Layout {
+            width: self.layout.width,
+            name_width: if name_width < 5 { 5 } else { name_width },
+            time_format: time_format,
+        };
+        self.relayout(layout);
+        self.scroll_lines = scroll_lines;
+        self.set_dirty();
+    }
+
+    fn relayout(&mut self, layout: Layout) {
+        if layout != self.layout {
+            self.layout = layout;
+            let ref layout = self.layout;
+            for m in self.messages.iter_mut() {
+                m.1 = m.1.resize(layout);
+            }
         }
     }
 
     pub fn set_dirty(&mut self) { self.dirty = true; }
     pub fn is_dirty(&self) -> bool { self.dirty }
 
-    pub fn add_server_message(&mut self, tab: Option<TabToken>, msg: String) {
+    pub fn set_right_margin(&mut self, margin: i32) {
+        if margin != self.right_margin {
+            self.right_margin = margin;
+            self.set_dirty();
+        }
+    }
+
+    pub fn add_server_message(&mut self, tab: Option<TabToken>, msg: String, time: Timestamp) {
         self.set_dirty();
         let index = self.messages.iter().filter(|x| x.0 == tab).count() as u32;
 
-        let message = Message::from_server(self.width, index, msg); 
+        let message = Message::from_server(&self.layout, index, msg, time);
 
         self.messages.push((tab, message));
     }
 
-    pub fn add_chat_message(&mut self, tab: Option<TabToken>,
-                             name: String, message: String, m_type: MessageType) {
+    /// Appends preformatted lines, such as the MOTD, as one message. The lines keep their IRC
+    /// colours and are cut off at the pane's edge rather than wrapped.
+    pub fn add_server_block(&mut self, tab: Option<TabToken>, lines: Vec<String>, time: Timestamp) {
         self.set_dirty();
         let index = self.messages.iter().filter(|x| x.0 == tab).count() as u32;
 
-        let message = Message::from_chat(self.width, index, name, message, m_type);
+        let message = Message::from_block(&self.layout, index, lines.join("\n"), time);
 
         self.messages.push((tab, message));
     }
 
-    pub fn scroll_up(&mut self) {
-        self.scroll += 5;
+    /// Appends a message, unless one with the same `msgid` is already shown in the tab.
+    pub fn add_chat_message(&mut self, tab: Option<TabToken>, name: String, message: String,
+                            m_type: MessageType, time: Timestamp, msgid: Option<String>) {
+        if self.has_message(tab, &msgid) { return; }
         self.set_dirty();
+        let index = self.messages.iter().filter(|x| x.0 == tab).count() as u32;
+
+        let mut message = Message::from_chat(&self.layout, index, name, message, m_type,
+                                             Delivery::Sent, time);
+        message.msgid = msgid;
+
+        self.messages.push((tab, message));
+    }
+
+    /// Inserts an earlier message, such as one from chathistory, after every message in the
+    /// tab that is not newer than it.
+    pub fn insert_chat_message(&mut self, tab: Option<TabToken>, name: String, message: String,
+                               m_type: MessageType, time: Timestamp, msgid: Option<String>) {
+        if self.has_message(tab, &msgid) { return; }
+        self.set_dirty();
+
+        let pos = match self.messages.iter().rposition(|x| x.0 == tab && x.1.time <= time) {
+            Some(pos) => pos + 1,
+            None => self.messages.iter().position(|x| x.0 == tab).unwrap_or(self.messages.len()),
+        };
+        let mut message = Message::from_chat(&self.layout, 0, name, message, m_type,
+                                             Delivery::Sent, time);
+        message.msgid = msgid;
+        self.messages.insert(pos, (tab, message));
+
+        // Keep the alternating line colours in order below the inserted message.
+        let ref layout = self.layout;
+        let mut index = 0;
+        for m in self.messages.iter_mut().filter(|x| x.0 == tab) {
+            if m.1.index != index {
+                m.1.index = index;
+                m.1 = m.1.resize(layout);
+            }
+            index += 1;
+        }
+    }
+
+    fn has_message(&self, tab: Option<TabToken>, msgid: &Option<String>) -> bool {
+        msgid.is_some() && self.messages.iter().any(|x| x.0 == tab && x.1.msgid == *msgid)
+    }
+
+    /// The time of the earliest message in the tab.
+    pub fn oldest(&self, tab: Option<TabToken>) -> Option<Timestamp> {
+        self.messages.iter().find(|x| x.0 == tab).map(|x| x.1.time)
+    }
+
+    /// Adds a message we sent that the server has not accepted yet. It is drawn dimmed until
+    /// `set_delivered` settles it.
+    pub fn add_pending_message(&mut self, tab: Option<TabToken>, id: u64, name: String, message: String,
+                               time: Timestamp) {
+        self.set_dirty();
+        let index = self.messages.iter().filter(|x| x.0 == tab).count() as u32;
+
+        let message = Message::from_chat(&self.layout, index, name, message,
+                                         MessageType::Normal, Delivery::Pending(id), time);
+
+        self.messages.push((tab, message));
+    }
+
+    pub fn set_delivered(&mut self, tab: Option<TabToken>, id: u64, delivered: bool) {
+        let delivery = if delivered { Delivery::Sent } else { Delivery::Rejected };
+        let ref layout = self.layout;
+        for m in self.messages.iter_mut().filter(|x| x.0 == tab) {
+            if m.1.delivery == Delivery::Pending(id) {
+                m.1.delivery = delivery;
+                m.1 = m.1.resize(layout);
+                self.dirty = true;
+            }
+        }
+    }
+
+    /// Scrolls towards older messages. Returns `true` when the view was already at the top,
+    /// so there is nothing older to show.
+    pub fn scroll_up(&mut self) -> bool {
+        let at_top = self.scroll >= self.max_scroll;
+        self.scroll += self.scroll_lines;
+        self.set_dirty();
+        at_top
     }
 
     pub fn scroll_down(&mut self) {
-        self.scroll -= 5;
+        self.scroll -= self.scroll_lines;
         self.set_dirty();
     }
 
     pub fn render(&mut self, window: &mut TermBuffer, tab: Option<TabToken>) {
-        if self.width != window.width() {
-            self.width = window.width();
+        let width = window.width() - self.right_margin;
+        if self.layout.width != width {
+            let mut layout = self.layout.clone();
+            layout.width = width;
+            self.relayout(layout);
             self.set_dirty();
-            self.messages = self.messages.iter().map(|x| (x.0, x.1.resize(self.width))).collect();
         }
 
         if !window.is_invalid() && !self.is_dirty() { return; }
 
         let height = window.height() - 3;
-        let width = window.width();
 
-        let total_height = self.messages.iter().filter(|x| x.0 == tab)
-                                        .fold(0, |acc, x| acc + x.1.height);
+        // A separator line goes above the first message of each new day.
+        let tab_messages: Vec<&Message> = self.messages.iter().filter(|x| x.0 == tab).map(|x| &x.1).collect();
+        let separators: Vec<Option<Surface>> = tab_messages.iter().enumerate().map(|(i, m)| {
+            if i > 0 && !tab_messages[i - 1].time.same_day(&m.time) {
+                Some(Message::day_separator(width, &m.time))
+            } else {
+                None
+            }
+        }).collect();
+
+        let mut rows: Vec<(&Surface, i32)> = Vec::new();
+        for (m, separator) in tab_messages.iter().zip(separators.iter()) {
+            if let Some(ref separator) = *separator {
+                rows.push((separator, 1));
+            }
+            rows.push((&m.surface, m.height));
+        }
+
+        let total_height = rows.iter().fold(0, |acc, x| acc + x.1);
         let max_scroll = total_height - height;
 
         if max_scroll < 0 { self.scroll = 0; }
         if self.scroll < 0 { self.scroll = 0; }
         if self.scroll > max_scroll { self.scroll = max_scroll;}
-
-        let mut tab_messages = self.messages.iter().filter(|x| x.0 == tab);
+        self.max_scroll = if max_scroll < 0 { 0 } else { max_scroll };
 
         let mut rendered_msgs = Surface::new(Rect(Point(0, 0), width, height));
         rendered_msgs.set_color(Point(0,0), Some(Color::White), Some(Color::Black));
 
         let mut rendered_height = 0;
         let mut h = height;
-        for m in tab_messages.rev() {
-            rendered_height += m.1.height;
+        for &(surface, row_height) in rows.iter().rev() {
+            rendered_height += row_height;
             if rendered_height < self.scroll {continue;}
             if self.scroll + height < rendered_height { break; }
-            h -= m.1.height;
-            rendered_msgs.blit(&m.1.surface, Point(0, h));
+            h -= row_height;
+            rendered_msgs.blit(surface, Point(0, h));
         }
 
         window.blit(&rendered_msgs, Point(0,2));
@@ -98,6 +258,13 @@ pub enum MessageType {
     Action,
 }
 
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Delivery {
+    Sent,
+    Pending(u64),
+    Rejected,
+}
+
 struct Message {
     width: i32,
     height: i32,
@@ -106,20 +273,33 @@ struct Message {
     index: u32,
     surface: Surface,
     m_type: MessageType,
+    delivery: Delivery,
+    time: Timestamp,
+    msgid: Option<String>,
+    /// Whether `message` holds the lines of a block rather than text to wrap.
+    block: bool,
 }
 
 impl Message {
-    pub fn from_server(width: i32, index: u32, message: String) -> Message {
+    fn from_server(layout: &Layout, index: u32, message: String, time: Timestamp) -> Message {
+        let stamp = layout.stamp(&time);
+        let stamp_width = stamp.chars().count() as i32;
+        let width = layout.width;
+        let text_width = width - stamp_width;
+
         let chars: Vec<char> = message.chars().filter(|x| *x != '\r' && *x != '\n').collect();
         let msg_len = chars.len() as i32;
-        let height = if width == 0 {
+        let height = if text_width <= 0 {
             0
-        } else if msg_len % width == 0 {
-            msg_len / width
+        } else if msg_len == 0 {
+            1
+        } else if msg_len % text_width == 0 {
+            msg_len / text_width
         } else {
-            (msg_len / width) + 1
+            (msg_len / text_width) + 1
         };
         let mut surface = Surface::new(Rect(Point(0, 0), width, height));
+        Self::draw_stamp(&mut surface, &stamp, height);
 
         let line_color = if index % 2 != 0 {
             "\0color:White;background:Grayscale(76);\0"
@@ -130,18 +310,18 @@ impl Message {
         let mut char_count = msg_len as i32;
         for i in 0..height {
             let mut line_buf = String::from(line_color);
-            let line_width = if width < char_count {
-                width
+            let line_width = if text_width < char_count {
+                text_width
             } else {
                 char_count
             };
             char_count -= line_width;
-            let start = (i * width) as usize;
+            let start = (i * text_width) as usize;
             let end = start + line_width as usize;
             for c in &chars[start..end] {
                 line_buf.push(*c);
             }
-            surface.formatted_text(line_buf.into(), Point(0, i));
+            surface.formatted_text(line_buf.into(), Point(stamp_width, i));
         }
 
         Message {
@@ -152,29 +332,74 @@ impl Message {
             index: index,
             surface: surface,
             m_type: MessageType::Normal,
+            delivery: Delivery::Sent,
+            time: time,
+            msgid: None,
+            block: false,
         }
     }
 
-    pub fn from_chat(width: i32, index: u32, name: String, message: String, m_type: MessageType)
-            -> Message {
-        let name_width = 14;
-        let msg_width = width - name_width;
+    fn from_block(layout: &Layout, index: u32, message: String, time: Timestamp) -> Message {
+        let stamp = layout.stamp(&time);
+        let stamp_width = stamp.chars().count() as i32;
+        let width = layout.width;
+
+        let lines: Vec<&str> = message.split('\n').collect();
+        let height = if width - stamp_width <= 0 { 0 } else { lines.len() as i32 };
+        let mut surface = Surface::new(Rect(Point(0, 0), width, height));
+        Self::draw_stamp(&mut surface, &stamp, height);
+
+        let background = if index % 2 != 0 { Color::Grayscale(76) } else { Color::Grayscale(25) };
+        for (i, line) in lines.iter().take(height as usize).enumerate() {
+            let mut text = TermString::from_irc(line, Color::White, background);
+            // Carry the background to the edge.
+            while (text.len() as i32) < width - stamp_width {
+                text.push(Glyph(' ', Some(Color::White), Some(background)));
+            }
+            surface.formatted_text(text, Point(stamp_width, i as i32));
+        }
+
+        Message {
+            width: width,
+            height: height,
+            name: None,
+            message: message,
+            index: index,
+            surface: surface,
+            m_type: MessageType::Normal,
+            delivery: Delivery::Sent,
+            time: time,
+            msgid: None,
+            block: true,
+        }
+    }
+
+    fn from_chat(layout: &Layout, index: u32, name: String, message: String,
+                 m_type: MessageType, delivery: Delivery, time: Timestamp) -> Message {
+        let stamp = layout.stamp(&time);
+        let stamp_width = stamp.chars().count() as i32;
+        let (width, name_width) = (layout.width, layout.name_width);
+        let msg_width = width - name_width - stamp_width;
         let msg_len = message.len() as i32;
 
-        let height = if msg_len % msg_width == 0 {
+        // Too narrow for any text, like a block without room.
+        let height = if msg_width <= 0 {
+            0
+        } else if msg_len % msg_width == 0 {
             msg_len / msg_width
         } else {
             (msg_len / msg_width) + 1
         };
 
         let mut surface = Surface::new(Rect(Point(0, 0),width, height));
-        let name_string = Self::format_name(&*name, name_width, m_type);
+        Self::draw_stamp(&mut surface, &stamp, height);
+        let name_string = Self::format_name(&*name, name_width, m_type, delivery);
 
-        surface.formatted_text(name_string.into(), Point(0,0));
+        surface.formatted_text(name_string.into(), Point(stamp_width, 0));
         for i in 1..height { //Color gutter
             surface.formatted_text(
                 "\0color:White;background:Grayscale(25);\0 ".to_string().into(),
-                Point(0, i))
+                Point(stamp_width, i))
         }
 
         let line_color = if index % 2 != 0 {
@@ -198,7 +423,7 @@ impl Message {
             for c in &chars[start..end] {
                 line_buf.push(*c);
             }
-            surface.formatted_text(line_buf.into(), Point(name_width, i));
+            surface.formatted_text(line_buf.into(), Point(stamp_width + name_width, i));
         }
 
         Message {
@@ -209,21 +434,52 @@ impl Message {
             index: index,
             surface: surface,
             m_type: m_type,
+            delivery: delivery,
+            time: time,
+            msgid: None,
+            block: false,
         }
     }
 
-    fn resize(&self, width: i32) -> Message {
-        match self.name.clone() {
+    fn resize(&self, layout: &Layout) -> Message {
+        let mut message = match self.name.clone() {
             Some(name) => {
-                Message::from_chat(width, self.index, name, self.message.clone(), self.m_type)
+                Message::from_chat(layout, self.index, name, self.message.clone(), self.m_type,
+                                   self.delivery, self.time)
+            },
+            None if self.block => {
+                Message::from_block(layout, self.index, self.message.clone(), self.time)
             },
             None => {
-                Message::from_server(width, self.index, self.message.clone())
+                Message::from_server(layout, self.index, self.message.clone(), self.time)
             }
+        };
+        message.msgid = self.msgid.clone();
+        message
+    }
+
+    /// Draws the timestamp on the first line, leaving the column blank below it.
+    fn draw_stamp(surface: &mut Surface, stamp: &str, height: i32) {
+        if stamp.is_empty() { return; }
+        for i in 0..height {
+            let text = if i == 0 { stamp.to_string() } else { stamp.chars().map(|_| ' ').collect() };
+            surface.formatted_text(format!("\0color:Grayscale(128);background:Black;\0{}", text).into(),
+                                   Point(0, i));
         }
     }
 
-    fn format_name(nick: &str, width: i32, m_type: MessageType) -> String {
+    /// The line shown between messages from different days.
+    fn day_separator(width: i32, time: &Timestamp) -> Surface {
+        let mut surface = Surface::new(Rect(Point(0, 0), width, 1));
+        let text = format!(" {} ", time.format("%A, %e %B %Y").trim_left());
+        let pad = (width - text.chars().count() as i32) / 2;
+        let rule: String = (0..if pad > 0 { pad } else { 0 }).map(|_| '-').collect();
+        surface.formatted_text(format!("\0color:Grayscale(128);background:Black;\0{}{}{}", rule, text, rule).into(),
+                               Point(0, 0));
+        surface
+    }
+
+    fn format_name(nick: &str, width: i32, m_type: MessageType, delivery: Delivery) -> String {
         let color_options: [&'static str; 12] = 
             [ "Blue",
             "Cyan" ,
@@ -238,16 +494,21 @@ impl Message {
             "Red" ,
             "Yellow"];
         let index = nick.bytes().fold(0, |acc, x| acc ^ x) % 12;
+        let (color, mark) = match delivery {
+            Delivery::Sent => (color_options[index as usize], "White"),
+            Delivery::Pending(_) => ("Grayscale(128)", "Grayscale(128)"),
+            Delivery::Rejected => ("Grayscale(128)", "Red"),
+        };
 
         match m_type {
-            MessageType::Normal => format!("\0color:White;background:Black;\0 [\0color:{};\0{: >width$.width$}\0color:White;\0] "
-                                         ,color_options[index as usize]
-                                         ,nick, width = width as usize - 4),
-            MessageType::Notice => format!("\0color:White;background:Black;\0 -\0color:{};\0{: >width$.width$}\0color:White;\0- "
-                                         ,color_options[index as usize]
-                                         ,nick, width = width as usize - 4),
-            MessageType::Action => format!("\0color:White;background:Black;\0 **\0color:{};\0{: >width$.width$}\0color:White;\0 "
-                    ,color_options[index as usize]
+            MessageType::Normal => format!("\0color:{};background:Black;\0 [\0color:{};\0{: >width$.width$}\0color:{};\0] "
+                                         ,mark, color
+                                         ,nick, mark, width = width as usize - 4),
+            MessageType::Notice => format!("\0color:{};background:Black;\0 -\0color:{};\0{: >width$.width$}\0color:{};\0- "
+                                         ,mark, color
+                                         ,nick, mark, width = width as usize - 4),
+            MessageType::Action => format!("\0color:{};background:Black;\0 **\0color:{};\0{: >width$.width$}\0color:White;\0 "
+                    ,mark, color
                     ,nick, width = width as usize - 4),
         }
     }
@@ -5,4 +5,10 @@ mod text_input;
 pub use self::text_input::TextInput;
 
 mod tabs;
-pub use self::tabs::{TabBar, TabStatus, TabToken};
+pub use self::tabs::{TabBar, TabStatus, TabToken, ALERT_TICK_MS};
+
+mod channel_list;
+pub use self::channel_list::ChannelList;
+
+mod nick_list;
+pub use self::nick_list::NickList;
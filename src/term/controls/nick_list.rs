@@ -0,0 +1,115 @@
+use term::{TermBuffer, Color, Surface, Point, Rect};
+use irc::{ChannelModes, Member};
+
+/// The users of the active channel, drawn down the right-hand side of the message pane.
+/// Operators come first, then voiced users, then everyone else, each by name.
+pub struct NickList {
+    members: Vec<Member>,
+    visible: bool,
+    /// Columns taken, including the separator.
+    width: i32,
+    scroll: usize,
+    page: usize,
+    dirty: bool,
+}
+
+impl NickList {
+    pub fn new() -> NickList {
+        NickList {
+            members: Vec::new(),
+            visible: true,
+            width: 16,
+            scroll: 0,
+            page: 1,
+            dirty: true,
+        }
+    }
+
+    pub fn set_dirty(&mut self) { self.dirty = true; }
+    pub fn is_dirty(&self) -> bool { self.dirty }
+
+    pub fn is_visible(&self) -> bool { self.visible }
+    pub fn width(&self) -> i32 { self.width }
+
+    pub fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+        self.set_dirty();
+    }
+
+    /// Sets the width from that of the nickname column, leaving room for the separator and
+    /// a prefix.
+    pub fn set_nick_width(&mut self, nick_width: i32) {
+        self.width = nick_width + 2;
+        self.set_dirty();
+    }
+
+    /// Shows these members, ranked by the network's prefixes, redrawing only if they changed.
+    pub fn set_members(&mut self, mut members: Vec<Member>, modes: &ChannelModes) {
+        members.sort_by_key(|x| (x.rank(modes), x.nick.to_lowercase()));
+        if members != self.members {
+            self.members = members;
+            self.set_dirty();
+        }
+    }
+
+    pub fn reset_scroll(&mut self) {
+        self.scroll = 0;
+        self.set_dirty();
+    }
+
+    pub fn scroll_up(&mut self) {
+        self.scroll = if self.scroll > self.page { self.scroll - self.page } else { 0 };
+        self.set_dirty();
+    }
+
+    pub fn scroll_down(&mut self) {
+        self.scroll += self.page;
+        self.set_dirty();
+    }
+
+    pub fn render(&mut self, window: &mut TermBuffer) {
+        if !window.is_invalid() && !self.is_dirty() { return; }
+
+        let height = window.height() - 3;
+        if height < 2 { return; }
+        let mut surface = Surface::new(Rect(Point(0, 0), self.width, height));
+
+        // The first row counts the users, the rest list them.
+        let rows = (height - 1) as usize;
+        self.page = if rows > 1 { rows - 1 } else { 1 };
+        let max_scroll = if self.members.len() > rows { self.members.len() - rows } else { 0 };
+        if self.scroll > max_scroll { self.scroll = max_scroll; }
+
+        let header = format!(" {} users", self.members.len());
+        surface.text(&format!("│{}", header), Point(0, 0));
+        surface.set_color(Point(0, 0), Some(Color::Grayscale(128)), Some(Color::Black));
+
+        for (row, member) in self.members.iter().skip(self.scroll).take(rows).enumerate() {
+            let y = row as i32 + 1;
+            let prefix = member.prefix().unwrap_or(' ');
+            let prefix_color = match prefix {
+                '~' | '&' | '@' => Color::LightGreen,
+                '%' => Color::LightMagenta,
+                '+' => Color::LightYellow,
+                _ => Color::White,
+            };
+            let name_color = if member.away { Color::LightBlack } else { Color::White };
+
+            surface.text("│", Point(0, y));
+            surface.set_color(Point(0, y), Some(Color::Grayscale(128)), Some(Color::Black));
+            surface.text(&prefix.to_string(), Point(1, y));
+            surface.set_color(Point(1, y), Some(prefix_color), Some(Color::Black));
+            surface.text(&member.nick, Point(2, y));
+            surface.set_color(Point(2, y), Some(name_color), Some(Color::Black));
+        }
+        let shown = self.members.len() - self.scroll;
+        let shown = if shown > rows { rows } else { shown };
+        for y in shown as i32 + 1..height {
+            surface.text("│", Point(0, y));
+            surface.set_color(Point(0, y), Some(Color::Grayscale(128)), Some(Color::Black));
+        }
+
+        window.blit(&surface, Point(window.width() - self.width, 2));
+        self.dirty = false;
+    }
+}
@@ -18,6 +18,7 @@ pub enum TabStatus {
 }
 
 const ALERT_TICK: [&'static str; 8]  = ["|", "/", "-", "\\", "|" , "/", "-", "\\" ];
+pub const ALERT_TICK_MS: u64 = 50;
 
 impl TabStatus {
     fn to_string(&self, tick: u32) -> String {
@@ -32,6 +33,7 @@ impl TabStatus {
 #[derive(Clone, Debug)]
 struct Tab {
     token: TabToken,
+    group: TabToken,
     title: String,
     topic: String,
     status: TabStatus,
@@ -40,9 +42,10 @@ struct Tab {
 }
 
 impl Tab {
-    fn new(token: TabToken, title: String, topic:String, status: TabStatus, tick: u32) -> Tab {
+    fn new(token: TabToken, group: TabToken, title: String, topic:String, status: TabStatus, tick: u32) -> Tab {
         Tab {
             token: token,
+            group: group,
             title: title,
             topic: topic,
             status: status,
@@ -78,8 +81,16 @@ impl Tab {
         dirty
     }
 
+    fn is_group(&self) -> bool {
+        self.token == self.group
+    }
+
     fn to_string(&self, index: u32) -> String {
-        format!(" {} {}. {}  ", self.status.to_string(self.next), index, self.title)
+        if self.is_group() {
+            format!(" {} {}. [{}]  ", self.status.to_string(self.next), index, self.title)
+        } else {
+            format!(" {} {}. {}  ", self.status.to_string(self.next), index, self.title)
+        }
     }
 }
 
@@ -103,16 +114,54 @@ impl TabBar {
     pub fn set_dirty(&mut self) { self.dirty = true; }
     pub fn is_dirty(&self) -> bool { self.dirty }
 
-    pub fn add_tab(&mut self, title: String, topic: String, status: TabStatus) -> TabToken {
+    /// Adds a group header tab at the end of the bar. Tabs added to the group are kept
+    /// together after it.
+    pub fn add_group(&mut self, title: String, topic: String, status: TabStatus) -> TabToken {
+        if status == TabStatus::Active { self.clear_active(); }
+        let token = TabToken(self.next_id);
+        self.tabs.push(Tab::new(token, token, title, topic, status, self.tick));
+        self.next_id += 1;
+        self.set_dirty();
+
+        token
+    }
+
+    pub fn add_tab(&mut self, group: TabToken, title: String, topic: String, status: TabStatus) -> TabToken {
         if status == TabStatus::Active { self.clear_active(); }
         let token = TabToken(self.next_id);
-        self.tabs.push(Tab::new(token, title, topic, status, self.tick));
+        let index = match self.tabs.iter().rposition(|x| x.group == group) {
+            Some(i) => i + 1,
+            None => self.tabs.len(),
+        };
+        self.tabs.insert(index, Tab::new(token, group, title, topic, status, self.tick));
         self.next_id += 1;
         self.set_dirty();
 
         token
     }
 
+    pub fn group(&self, tab: TabToken) -> Option<TabToken> {
+        self.tabs.iter().find(|x| x.token == tab).map(|x| x.group)
+    }
+
+    pub fn tab_at(&self, index: usize) -> Option<TabToken> {
+        self.tabs.get(index).map(|x| x.token)
+    }
+
+    pub fn next_tab(&mut self) {
+        let active = self.tabs.iter().position(|x| x.get_status() == TabStatus::Active);
+        if let Some(next) = active.and_then(|x| self.tab_at(x + 1)) {
+            self.set_active(next);
+        }
+    }
+
+    pub fn prev_tab(&mut self) {
+        let active = self.tabs.iter().position(|x| x.get_status() == TabStatus::Active);
+        if let Some(prev) = active.and_then(|x| x.checked_sub(1)).and_then(|x| self.tab_at(x)) {
+            self.set_active(prev);
+        }
+    }
+
     pub fn set_topic(&mut self, tab: TabToken, topic: String) {
         if let Some(tab) = self.tabs.iter_mut()
                 .find(|x| x.token == tab) {
@@ -121,6 +170,14 @@ impl TabBar {
         self.set_dirty();
     }
 
+    pub fn set_title(&mut self, tab: TabToken, title: String) {
+        if let Some(tab) = self.tabs.iter_mut()
+                .find(|x| x.token == tab) {
+            tab.title = title;
+        }
+        self.set_dirty();
+    }
+
     pub fn remove_tab(&mut self, token: TabToken) {
         let tab_index = self.tabs.iter().position(|x| x.token == token);
         if let Some(tab_index) = tab_index {
@@ -194,13 +251,21 @@ impl TabBar {
         self.set_dirty();
     }
 
-    pub fn render(&mut self, window: &mut TermBuffer) {
+    pub fn is_animating(&self) -> bool {
+        self.tabs.iter().any(|x| x.get_status() == TabStatus::Alert)
+    }
+
+    pub fn tick(&mut self) {
         self.tick = self.tick.wrapping_add(1);
-        let mut dirty = self.is_dirty();
+        let mut dirty = false;
         for tab in &mut self.tabs {
             dirty = dirty | tab.tick(self.tick);
         }
-        if !window.is_invalid() && !dirty { return; }
+        if dirty { self.set_dirty(); }
+    }
+
+    pub fn render(&mut self, window: &mut TermBuffer) {
+        if !window.is_invalid() && !self.is_dirty() { return; }
 
         let width = window.width();
         let mut surf = Surface::new(Rect(Point(0,0), width, 2));
@@ -213,6 +278,8 @@ impl TabBar {
                 TabStatus::Active => (Color::White, Color::Red, Color::Black),
                 TabStatus::Alert => (Color::LightBlack, Color::Red, Color::LightWhite),
                 TabStatus::Read |
+                TabStatus::Unread if tab.is_group() => (Color::Blue, Color::Black, Color::LightWhite),
+                TabStatus::Read |
                 TabStatus::Unread => (Color::LightBlack, Color::Black, Color::LightWhite)
             };
 
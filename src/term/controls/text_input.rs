@@ -9,6 +9,9 @@ pub struct TextInput {
     history_index: usize,
     cursor: i32,
     dirty: bool,
+    /// Whether a list view has the focus, which takes the arrow keys, Enter on an empty
+    /// line, Escape and Ctrl-S.
+    browsing: bool,
     reader: KeyReader,
 }
 
@@ -19,7 +22,8 @@ impl TextInput {
             history: VecDeque::new(),
             cursor: 0,
             dirty: true,
-            reader: KeyReader::stdin(),
+            browsing: false,
+            reader: KeyReader::new(),
         };
 
         input.history.push_front(Vec::new());
@@ -30,13 +34,24 @@ impl TextInput {
     pub fn set_dirty(&mut self) { self.dirty = true; }
     pub fn is_dirty(&self) -> bool { self.dirty }
 
+    pub fn set_browsing(&mut self, browsing: bool) { self.browsing = browsing; }
+
+    pub fn feed(&mut self, bytes: &[u8]) {
+        self.reader.feed(bytes);
+    }
+
     pub fn read(&mut self) -> Option<UserInput> {
-        let keys: Vec<_> = self.reader.by_ref().collect();
-        for m in keys {
+        while let Some(m) = self.reader.next() {
             match m {
                 Modifier::None(k) => {
                     match k {
                         Key::Close =>{ return Some(UserInput::Close); },
+                        Key::Up if self.browsing => { return Some(UserInput::SelectPrev); },
+                        Key::Down if self.browsing => { return Some(UserInput::SelectNext); },
+                        Key::Esc if self.browsing => { return Some(UserInput::Dismiss); },
+                        Key::Return if self.browsing && self.current_line().is_empty() => {
+                            return Some(UserInput::Activate);
+                        },
                         Key::Up => {
                             if self.history_index + 1 < self.history.len() {
                                 self.set_dirty();
@@ -106,6 +121,9 @@ impl TextInput {
                         _ => {}
                     }
                 },
+                Modifier::Ctrl(Key::Printable('s')) if self.browsing => {
+                    return Some(UserInput::ToggleSort);
+                },
                 Modifier::Alt(k) => {
                     match k {
                         Key::Printable(c) if (c as u32) >= 0x30 && (c as u32) < 0x3A => {
@@ -117,6 +135,15 @@ impl TextInput {
                         Key::Right => {
                             return Some(UserInput::NextTab);
                         },
+                        Key::Printable('n') => {
+                            return Some(UserInput::ToggleNickList);
+                        },
+                        Key::PageUp => {
+                            return Some(UserInput::ScrollNicksUp);
+                        },
+                        Key::PageDown => {
+                            return Some(UserInput::ScrollNicksDown);
+                        },
                         _ => {}
                     }
                 },
@@ -0,0 +1,82 @@
+use futures::{Poll, Stream};
+use futures::sync::mpsc::{
+    unbounded as fut_unbounded,
+    UnboundedSender as FutSender,
+    UnboundedReceiver as FutReceiver,
+};
+use libc;
+
+use std::io::{self, Read};
+use std::{mem, ptr, thread};
+
+pub enum TermEvent {
+    Input(Vec<u8>),
+    Resize,
+    Log(String),
+}
+
+/// Terminal side event sources merged into one stream. Stdin and SIGWINCH are each
+/// watched by a blocking thread, so the main loop only wakes when there is work to do.
+pub struct TermEvents {
+    sender: FutSender<TermEvent>,
+    receiver: FutReceiver<TermEvent>,
+}
+
+impl TermEvents {
+    /// Must be called before any other threads are spawned, so SIGWINCH stays blocked
+    /// everywhere except the thread waiting on it.
+    pub fn new() -> TermEvents {
+        let set = unsafe {
+            let mut set: libc::sigset_t = mem::zeroed();
+            libc::sigemptyset(&mut set);
+            libc::sigaddset(&mut set, libc::SIGWINCH);
+            libc::pthread_sigmask(libc::SIG_BLOCK, &set, ptr::null_mut());
+            set
+        };
+
+        let (tx, rx) = fut_unbounded();
+
+        let input_tx = tx.clone();
+        thread::spawn(move || {
+            let stdin = io::stdin();
+            let mut buf = [0; 1024];
+            loop {
+                match stdin.lock().read(&mut buf) {
+                    Ok(0) | Err(_) => break,
+                    Ok(n) => {
+                        if FutSender::send(&input_tx, TermEvent::Input(buf[..n].to_vec())).is_err() {
+                            break;
+                        }
+                    }
+                }
+            }
+        });
+
+        let resize_tx = tx.clone();
+        thread::spawn(move || {
+            loop {
+                let mut sig = 0;
+                if unsafe { libc::sigwait(&set, &mut sig) } != 0 { break; }
+                if FutSender::send(&resize_tx, TermEvent::Resize).is_err() { break; }
+            }
+        });
+
+        TermEvents {
+            sender: tx,
+            receiver: rx,
+        }
+    }
+
+    pub fn sender(&self) -> FutSender<TermEvent> {
+        self.sender.clone()
+    }
+}
+
+impl Stream for TermEvents {
+    type Item = TermEvent;
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<Option<TermEvent>, ()> {
+        self.receiver.poll()
+    }
+}
@@ -0,0 +1,44 @@
+/// Channels joined by hand, with the tab each JOIN was typed in, so that a server refusing
+/// the join can say so there instead of in the server tab.
+#[derive(Debug, Default)]
+pub struct PendingJoins {
+    /// Lowercased channel, and the channel tab or `None` for the server tab.
+    joins: Vec<(String, Option<String>)>,
+}
+
+impl PendingJoins {
+    pub fn new() -> PendingJoins {
+        PendingJoins { joins: Vec::new() }
+    }
+
+    /// Remembers each channel of a `JOIN #a,#b` typed in `tab`.
+    pub fn add(&mut self, channels: &str, tab: Option<String>) {
+        for channel in channels.split(',').filter(|x| !x.is_empty()) {
+            let channel = channel.to_lowercase();
+            self.joins.retain(|x| x.0 != channel);
+            self.joins.push((channel, tab.clone()));
+        }
+    }
+
+    /// Forgets a channel once we have joined it.
+    pub fn remove(&mut self, channel: &str) {
+        let channel = channel.to_lowercase();
+        self.joins.retain(|x| x.0 != channel);
+    }
+
+    /// The tab to show an error about `channel` in, given the channel tabs now `open`: the
+    /// channel's own tab, else the still open tab its JOIN was typed in. `None` is the
+    /// server tab.
+    pub fn error_tab(&mut self, channel: &str, open: &[String]) -> Option<String> {
+        let lower = channel.to_lowercase();
+        let find = |name: &str| open.iter().find(|x| x.to_lowercase() == name.to_lowercase()).cloned();
+        if let Some(tab) = find(&lower) {
+            return Some(tab);
+        }
+        let pending = match self.joins.iter().position(|x| x.0 == lower) {
+            Some(i) => self.joins.remove(i).1,
+            None => return None,
+        };
+        pending.and_then(|tab| find(&tab))
+    }
+}
@@ -1,6 +1,4 @@
-use termion::{async_stdin, AsyncReader};
 use std::collections::VecDeque;
-use std::io::Read;
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Key {
@@ -98,33 +96,26 @@ fn is_printable(c: char) -> bool {
 }
 
 pub struct TermIterator {
-    stream: AsyncReader,
     buffer: VecDeque<u8>,
 }
 
 impl TermIterator {
     fn new() -> Self {
         TermIterator {
-            stream: async_stdin(),
             buffer: VecDeque::new(),
         }
     }
+
+    fn feed(&mut self, bytes: &[u8]) {
+        self.buffer.extend(bytes)
+    }
 }
 
 impl Iterator for TermIterator {
     type Item = u8;
 
     fn next(&mut self) -> Option<Self::Item> {
-        match self.buffer.pop_front() {
-            next @ Some(..) => next,
-            None => {
-                let mut buf = [0; 1024];
-                if let Ok(bytes) = self.stream.read(&mut buf) {
-                    self.buffer.extend(&buf[0..bytes])
-                }
-                self.buffer.pop_front()
-            }
-        }
+        self.buffer.pop_front()
     }
 }
 
@@ -177,8 +168,8 @@ pub struct KeyIterator<T> {
 }
 
 pub type KeyReader = KeyIterator<Utf8Iterator<TermIterator>>;
-impl<T> KeyIterator<T> {
-    pub fn stdin() -> KeyIterator<Utf8Iterator<TermIterator>> {
+impl KeyReader {
+    pub fn new() -> KeyReader {
         KeyIterator {
             stream: Utf8Iterator::new(TermIterator::new()),
             escape_buffer: String::new(),
@@ -186,6 +177,10 @@ impl<T> KeyIterator<T> {
             alt: false,
         }
     }
+
+    pub fn feed(&mut self, bytes: &[u8]) {
+        self.stream.stream.feed(bytes);
+    }
 }
 
 impl<T> Iterator for KeyIterator<T> where T: Iterator<Item=char> {
@@ -3,18 +3,27 @@ pub use self::stream::TermStream;
 pub mod buffer;
 pub use self::buffer::{Color, Point, Rect, Surface, TermBuffer};
 pub mod controls;
-pub use self::controls::{MessagePane, MessageType, TextInput, TabBar, TabStatus, TabToken};
+pub use self::controls::{MessagePane, MessageType, TextInput, TabBar, TabToken, ALERT_TICK_MS};
 mod keys;
 pub use self::keys::{Modifier, Key, KeyReader};
 pub mod term_string;
-pub use self::term_string::{TermString};
 mod window;
-use self::window::ChatWindows;
+use self::window::{ChatWindows, NetworkToken};
 mod text;
 
-use irc::{Client as IrcClient, ClientEvent, UserInputParser, UserCommand};
-use std::thread;
-use std::time::Duration;
+mod events;
+pub mod joins;
+use self::joins::PendingJoins;
+use self::events::{TermEvent, TermEvents};
+
+use irc::{Address, Capabilities, ChannelModes, Client as IrcClient, ClientEvent, Command, CommandType, IrcUrl, Response,
+          ParseError, Sender, Timestamp, Transport, UserCommand, CommandRegistry, Whois, ListFilter};
+use config::{Config, IdentifyMethod, NetworkConfig, Overrides};
+use futures::{future, Future, Stream};
+use tokio_core::reactor::{Core, Timeout};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
 
 use log;
 
@@ -26,177 +35,1380 @@ pub enum UserInput {
     NextTab,
     ScrollUp,
     ScrollDown,
+    SelectPrev,
+    SelectNext,
+    Activate,
+    Dismiss,
+    ToggleSort,
+    ToggleNickList,
+    ScrollNicksUp,
+    ScrollNicksDown,
+}
+
+enum Wake {
+    Term(TermEvent),
+    Client(NetworkToken, ClientEvent),
+    Disconnected(NetworkToken),
+    Response(NetworkToken, u64, Option<Vec<Command>>),
+    Closed,
+    Tick,
+    /// Services didn't confirm identification in time.
+    IdentifyTimeout(NetworkToken),
+}
+
+/// How long autojoin waits for services to confirm identification.
+const IDENTIFY_TIMEOUT_SECS: u64 = 30;
+/// How long a chathistory request may go unanswered before another is allowed.
+const HISTORY_TIMEOUT_SECS: u64 = 30;
+
+type WakeFuture<'a> = Box<Future<Item=Wake, Error=()> + 'a>;
+
+struct Connection {
+    network: NetworkToken,
+    config: NetworkConfig,
+    client: Option<IrcClient>,
+    nickname: String,
+    nick_index: usize,
+    registered: bool,
+    logged_in: bool,
+    /// When autojoin gives up waiting for services, while identification is unconfirmed.
+    identifying: Option<Instant>,
+    capabilities: Vec<String>,
+    /// The server's ISUPPORT tokens, with an empty value for those without one.
+    isupport: HashMap<String, String>,
+    /// The channel modes from ISUPPORT, for reading replies the client hands back as they are.
+    modes: ChannelModes,
+    /// Lowercased channels with a chathistory request in flight, and when it was sent.
+    history_pending: Vec<(String, Instant)>,
+    /// Lowercased channels whose history has been read back to the start.
+    history_complete: Vec<String>,
+    /// Queries whose replies are shown in the tab they were issued from: id, channel or
+    /// `None` for the server tab, replies.
+    requests: Vec<(u64, Option<String>, Response)>,
+    /// The last MOTD the server sent, shown again by `/motd`.
+    motd: Option<Vec<String>>,
+    /// Whether the next MOTD was asked for and should be shown even when hidden on connect.
+    motd_requested: bool,
+    joins: PendingJoins,
 }
 
 pub struct Terminal {
-    client: IrcClient,
+    core: Core,
+    events: TermEvents,
+    connections: Vec<Connection>,
     stream: TermStream,
     window: TermBuffer,
     chat: ChatWindows,
     text_input: TextInput,
-    nickname: String,
-    realname: String,
-    error_recv: Option<Receiver<String>>,
+    next_tick: Instant,
+    next_request: u64,
+    commands: CommandRegistry,
+    config: Config,
+    config_path: Option<PathBuf>,
+    overrides: Overrides,
+    /// Set by `/quit`, which ends the run loop once every network has been quit.
+    quitting: bool,
 }
 
 impl Terminal {
-    pub fn new(client: IrcClient, nickname: String, realname: String) -> Terminal {
-        let term = Terminal {
-            client: client,
+    pub fn new(config: Config, config_path: Option<PathBuf>, overrides: Overrides) -> Terminal {
+        let mut term = Terminal {
+            events: TermEvents::new(),
+            core: Core::new().unwrap(),
+            connections: Vec::new(),
             stream: TermStream::new().unwrap(),
             window: TermBuffer::new(),
             chat: ChatWindows::new(MessagePane::new(), TabBar::new()),
             text_input: TextInput::new(),
-            nickname: nickname,
-            realname: realname,
-            error_recv: None,
+            next_tick: Instant::now(),
+            next_request: 0,
+            commands: CommandRegistry::new(),
+            config: config,
+            config_path: config_path,
+            overrides: overrides,
+            quitting: false,
         };
+        term.chat.set_layout(term.config.ui.nick_width, term.config.ui.timestamp_format.clone(),
+                             term.config.ui.scroll_lines);
+        term.chat.set_nick_list(term.config.ui.nick_list);
+        term.commands.set_config_aliases(&term.config.aliases);
 
         term
     }
 
-    pub fn run(mut self) {
-        self.client.send_message(UserCommand::Nick(
-            self.nickname.to_string()));
-        self.client.send_message(UserCommand::User(
-            self.nickname.to_string(),
-            "8".to_string(),
-            self.realname.to_string()));
-        while self.client.is_connected() {
-            for message in self.client.poll_messages() {
-                match message {
-                    ClientEvent::Command(m) => {
-                        self.chat.add_server_message(m.to_string());
-                    },
-                    ClientEvent::ChannelMessage(channel, sender, message) => {
-                        self.chat.add_chat_message(channel,
-                                              sender.as_ref().map(|x| &**x)
-                                                .unwrap_or(&*self.nickname),
-                                              &*self.nickname,
-                                              &message, MessageType::Normal);
-                    },
-                    ClientEvent::ChannelNotice(channel, sender, message) => {
-                        self.chat.add_chat_message(channel,
-                                                   sender.as_ref().map(|x| &**x)
-                                                   .unwrap_or(&*self.nickname),
-                                                   &*self.nickname,
-                                                   &message, MessageType::Notice);
+    /// The commands the input line understands, to register more on.
+    pub fn commands_mut(&mut self) -> &mut CommandRegistry {
+        &mut self.commands
+    }
+
+    /// Connects to every configured network marked `autoconnect`.
+    pub fn connect_all(&mut self) {
+        let networks: Vec<NetworkConfig> = self.config.networks.iter()
+            .filter(|x| x.autoconnect)
+            .cloned()
+            .collect();
+        for network in networks {
+            self.connect(network);
+        }
+    }
+
+    /// Opens a new network with its own server tab and registers on it.
+    pub fn connect(&mut self, config: NetworkConfig) {
+        let network = self.chat.add_network(config.name.clone());
+        self.connections.push(Connection {
+            network: network,
+            config: config,
+            client: None,
+            nickname: String::new(),
+            nick_index: 0,
+            registered: false,
+            logged_in: false,
+            identifying: None,
+            capabilities: Vec::new(),
+            isupport: HashMap::new(),
+            modes: ChannelModes::new(),
+            history_pending: Vec::new(),
+            history_complete: Vec::new(),
+            requests: Vec::new(),
+            motd: None,
+            motd_requested: false,
+            joins: PendingJoins::new(),
+        });
+        self.open(network);
+    }
+
+    /// Uses the configured network whose name or host matches `address`, if any.
+    fn network_config(&self, address: Address) -> NetworkConfig {
+        match self.config.networks.iter().find(|x| x.name == address.host || x.address == address) {
+            Some(n) => n.clone(),
+            None => NetworkConfig::from_address(address),
+        }
+    }
+
+    /// Starts a connection for a network that is currently disconnected.
+    fn open(&mut self, network: NetworkToken) {
+        let handle = self.core.handle();
+        let connection = match self.connections.iter_mut().find(|x| x.network == network) {
+            Some(c) => c,
+            None => return,
+        };
+        if connection.client.is_some() { return; }
+
+        let ref identity = self.config.identity;
+        connection.nick_index = 0;
+        connection.registered = false;
+        connection.logged_in = false;
+        connection.identifying = None;
+        connection.capabilities.clear();
+        connection.isupport.clear();
+        connection.modes = ChannelModes::new();
+        self.chat.set_channel_modes(network, ChannelModes::new());
+        connection.motd = None;
+        connection.motd_requested = false;
+        connection.history_pending.clear();
+        connection.nickname = connection.config.nicks(identity)[0].clone();
+
+        let mut capabilities = Capabilities::new()
+            .request("echo-message")
+            .request("server-time")
+            .request("message-tags")
+            .request("batch")
+            .request("draft/chathistory")
+            .request("away-notify")
+            .request("account-notify")
+            .request("extended-join")
+            .request("multi-prefix")
+            .request("userhost-in-names")
+            .request("labeled-response");
+        if let Some(ref sasl) = connection.config.sasl {
+            capabilities = capabilities.sasl(sasl.username.clone(), sasl.password.clone());
+        }
+
+        let client = IrcClient::spawn(connection.config.address.clone(), &connection.config.transport,
+                                      capabilities, &handle);
+        if let Some(ref password) = connection.config.password {
+            client.send_message(UserCommand::Pass(password.clone()));
+        }
+        client.send_message(UserCommand::Nick(
+            connection.nickname.clone()));
+        client.send_message(UserCommand::User(
+            connection.config.username(identity).to_string(),
+            identity.mode.to_string(),
+            connection.config.realname(identity).to_string()));
+        connection.client = Some(client);
+
+        let status = match connection.config.transport {
+            Transport::Direct => format!("Connecting to {}", connection.config.address),
+            Transport::Proxy(ref proxy) => format!("Connecting to {} via {}:{}", connection.config.address,
+                                                   proxy.host, proxy.port),
+            Transport::Command(ref command) => format!("Connecting to {} via `{}`", connection.config.address,
+                                                       command),
+            Transport::Unix(ref path) => format!("Connecting to {} at {}", connection.config.name,
+                                                 path.display()),
+        };
+        self.chat.set_status(network, status);
+    }
+
+    /// Quits the network but keeps its tabs open so it can be reconnected later.
+    pub fn disconnect(&mut self, network: NetworkToken, msg: String) {
+        let status = match self.connections.iter_mut().find(|x| x.network == network) {
+            Some(connection) => {
+                match connection.client.take() {
+                    Some(client) => {
+                        client.send_message(UserCommand::Quit(msg));
+                        let _ = client.close();
+                        format!("Disconnected from {}", connection.config.address)
                     },
-                    ClientEvent::JoinChannel(channel, sender) => {
-                        let sender = sender.unwrap_or("".to_string());
-                        if sender == self.nickname {
-                            self.chat.add_channel(channel);
-                        } else {
-                            self.chat.add_name(channel, sender);
+                    None => return,
+                }
+            },
+            None => return,
+        };
+        self.chat.set_status(network, status);
+    }
+
+    pub fn set_server(&mut self, network: NetworkToken, address: Address) {
+        self.disconnect(network, "Changing server".to_string());
+        let config = self.network_config(address);
+        if let Some(connection) = self.connections.iter_mut().find(|x| x.network == network) {
+            connection.config = config.clone();
+        }
+        self.chat.set_network_name(network, config.name);
+        self.open(network);
+    }
+
+    fn connection_lost(&mut self, network: NetworkToken) {
+        let status = match self.connections.iter_mut().find(|x| x.network == network) {
+            Some(connection) => {
+                connection.client = None;
+                connection.requests.clear();
+                format!("Disconnected from {}, use /reconnect to try again", connection.config.address)
+            },
+            None => return,
+        };
+        self.chat.set_status(network, status);
+    }
+
+    /// Runs the network's on-connect commands and identifies with services, then joins its
+    /// autojoin channels unless it has to wait for identification to be confirmed.
+    fn connected(&mut self, network: NetworkToken) {
+        let (status, commands, identify, logged_in) = match self.connections.iter_mut().find(|x| x.network == network) {
+            Some(connection) => {
+                connection.registered = true;
+                (format!("Connected to {}", connection.config.address),
+                 connection.config.commands.clone(),
+                 connection.config.identify.clone(),
+                 connection.logged_in)
+            },
+            None => return,
+        };
+        self.chat.set_status(network, status);
+
+        for command in commands {
+            self.run_command(network, command);
+        }
+
+        if let Some(identify) = identify {
+            if !logged_in {
+                match identify.method {
+                    IdentifyMethod::NickServ { account, password } => {
+                        let msg = match account {
+                            Some(account) => format!("IDENTIFY {} {}", account, password),
+                            None => format!("IDENTIFY {}", password),
+                        };
+                        if let Some(client) = self.client(Some(network)) {
+                            client.send_message(UserCommand::PrivMsg(identify.service.clone(), msg));
                         }
                     },
-                    ClientEvent::LeaveChannel(channel, sender) => {
-                        let sender = sender.unwrap_or("".to_string());
-                        if sender == self.nickname {
-                            self.chat.remove_channel(&channel);
-                        } else {
-                            self.chat.remove_name(channel, sender);
+                    IdentifyMethod::Commands(commands) => {
+                        for command in commands {
+                            self.run_command(network, command);
                         }
                     },
-                    ClientEvent::Topic(channel, topic) => {
-                        self.chat.add_topic(channel, topic);
-                    },
-                    ClientEvent::Names(channel, names) => {
-                        self.chat.add_names(channel, names);
-                    },
-                    ClientEvent::NamesEnd(channel) => {
-                        self.chat.set_names(channel);
-                    },
-                    _ => {},
                 }
+
+                if identify.wait {
+                    if let Some(c) = self.connections.iter_mut().find(|x| x.network == network) {
+                        c.identifying = Some(Instant::now() + Duration::from_secs(IDENTIFY_TIMEOUT_SECS));
+                    }
+                    self.chat.set_status(network, format!("Identifying with {}", identify.service));
+                    return;
+                }
+            }
+        }
+
+        self.autojoin(network);
+    }
+
+    /// Called once services confirm identification, through RPL_LOGGEDIN or a notice.
+    fn identified(&mut self, network: NetworkToken) {
+        let waiting = match self.connections.iter_mut().find(|x| x.network == network) {
+            Some(connection) => {
+                connection.logged_in = true;
+                connection.identifying.take().is_some()
+            },
+            None => return,
+        };
+        if waiting {
+            self.autojoin(network);
+        }
+    }
+
+    /// Called when services reject identification or don't answer in time. Autojoin goes
+    /// ahead anyway, so that channels open to everyone are still joined.
+    fn identify_failed(&mut self, network: NetworkToken, reason: &str) {
+        let (waiting, address) = match self.connections.iter_mut().find(|x| x.network == network) {
+            Some(connection) => (connection.identifying.take().is_some(),
+                                 connection.config.address.to_string()),
+            None => return,
+        };
+        if waiting {
+            self.chat.add_server_message(Some(network), format!("Identification failed: {}, joining channels anyway", reason));
+            self.chat.set_status(network, format!("Connected to {}", address));
+            self.autojoin(network);
+        }
+    }
+
+    /// Joins the autojoin channels along with any channel tabs left open from a previous
+    /// connection.
+    fn autojoin(&mut self, network: NetworkToken) {
+        let mut channels: Vec<(String, Option<String>)> = Vec::new();
+        if let Some(connection) = self.connections.iter().find(|x| x.network == network) {
+            for join in connection.config.autojoin.iter() {
+                channels.push((join.channel.clone(), join.key.clone()));
             }
+        }
+        for channel in self.chat.channels(network) {
+            if !channels.iter().any(|x| x.0 == channel) {
+                channels.push((channel, None));
+            }
+        }
+        if let Some(client) = self.client(Some(network)) {
+            for (channel, key) in channels {
+                client.send_message(UserCommand::Join(channel, key));
+            }
+        }
+    }
+
+    /// Joins the channels in `url`, on the open network with the same address or, failing that,
+    /// on a new network.
+    fn join_url(&mut self, url: IrcUrl) {
+        let existing = self.connections.iter()
+            .find(|x| url.matches(&x.config.address) || x.config.name == url.host)
+            .map(|x| (x.network, x.client.is_some() && x.registered));
+
+        match existing {
+            Some((network, true)) => {
+                if let Some(client) = self.client(Some(network)) {
+                    for (channel, key) in url.channels {
+                        client.send_message(UserCommand::Join(channel, key));
+                    }
+                }
+            },
+            Some((network, false)) => {
+                if let Some(c) = self.connections.iter_mut().find(|x| x.network == network) {
+                    for (channel, key) in url.channels {
+                        c.config.add_autojoin(channel, key);
+                    }
+                }
+                self.open(network);
+            },
+            None => {
+                let mut config = match self.config.networks.iter().find(|x| url.matches(&x.address) || x.name == url.host) {
+                    Some(n) => n.clone(),
+                    None => NetworkConfig::from_address(url.address()),
+                };
+                if let Some(nick) = url.nick {
+                    config.nicks.insert(0, nick);
+                }
+                for (channel, key) in url.channels {
+                    config.add_autojoin(channel, key);
+                }
+                self.connect(config);
+            },
+        }
+    }
+
+    /// Picks the next configured nickname, or appends `_` once they are all taken.
+    fn nick_in_use(&mut self, network: NetworkToken) {
+        let ref identity = self.config.identity;
+        let connection = match self.connections.iter_mut().find(|x| x.network == network) {
+            Some(c) => c,
+            None => return,
+        };
+        if connection.registered { return; }
+
+        connection.nick_index += 1;
+        connection.nickname = match connection.config.nicks(identity).get(connection.nick_index) {
+            Some(nick) => nick.clone(),
+            None => format!("{}_", connection.nickname),
+        };
+        if let Some(ref client) = connection.client {
+            client.send_message(UserCommand::Nick(connection.nickname.clone()));
+        }
+    }
+
+    /// Re-reads the configuration file. Open connections keep running and pick up their new
+    /// settings the next time they connect.
+    fn reload(&mut self) {
+        let network = self.chat.active_network();
+        let path = match self.config_path {
+            Some(ref path) => path.clone(),
+            None => {
+                self.chat.add_server_message(network, "No configuration file to reload".to_string());
+                return;
+            }
+        };
+
+        match Config::load(&path) {
+            Ok(mut config) => {
+                config.apply(&self.overrides);
+                for connection in self.connections.iter_mut() {
+                    if let Some(n) = config.find_network(&connection.config.name) {
+                        connection.config = n.clone();
+                    }
+                }
+                self.chat.set_layout(config.ui.nick_width, config.ui.timestamp_format.clone(),
+                                     config.ui.scroll_lines);
+                self.chat.set_nick_list(config.ui.nick_list);
+                self.commands.set_config_aliases(&config.aliases);
+                self.config = config;
+                self.chat.add_server_message(network, format!("Reloaded {}", path.display()));
+            },
+            Err(e) => {
+                self.chat.add_server_message(network, format!("Unable to reload configuration: {}", e));
+            }
+        }
+    }
+
+    /// Asks a server with chathistory for messages in `channel`: the newest ones when
+    /// `latest`, otherwise those before the oldest message shown.
+    fn fetch_history(&mut self, network: NetworkToken, channel: &str, latest: bool) {
+        let limit = self.config.ui.history_lines;
+        let oldest = self.chat.oldest_message(network, channel);
+        let connection = match self.connections.iter_mut().find(|x| x.network == network) {
+            Some(c) => c,
+            None => return,
+        };
+        if limit == 0 || !connection.capabilities.iter().any(|x| x == "draft/chathistory") { return; }
+        let key = channel.to_lowercase();
+        let now = Instant::now();
+        connection.history_pending.retain(|x| now.duration_since(x.1) < Duration::from_secs(HISTORY_TIMEOUT_SECS));
+        if connection.history_pending.iter().any(|x| x.0 == key) { return; }
+        if latest {
+            connection.history_complete.retain(|x| *x != key);
+        } else if connection.history_complete.iter().any(|x| *x == key) {
+            return;
+        }
+
+        let request = match oldest {
+            Some(time) if !latest => UserCommand::HistoryBefore(channel.to_string(), time, limit),
+            _ => UserCommand::HistoryLatest(channel.to_string(), limit),
+        };
+        if let Some(ref client) = connection.client {
+            client.send_message(request);
+            connection.history_pending.push((key, now));
+        }
+    }
+
+    /// Records the tokens of an ISUPPORT reply, whose parameters sit between our nick and
+    /// the closing text.
+    fn isupport(&mut self, network: NetworkToken, m: &Command) {
+        let connection = match self.connections.iter_mut().find(|x| x.network == network) {
+            Some(c) => c,
+            None => return,
+        };
+        let count = m.params.data.len();
+        for token in m.params.data.iter().take(count.saturating_sub(1)).skip(1) {
+            if token.starts_with('-') {
+                connection.isupport.remove(&token[1..]);
+                continue;
+            }
+            let mut parts = token.splitn(2, '=');
+            let key = parts.next().unwrap_or("").to_string();
+            connection.isupport.insert(key, parts.next().unwrap_or("").to_string());
+        }
+        connection.modes.handle(m);
+        self.chat.set_channel_modes(network, connection.modes.clone());
+    }
+
+    /// Shows the MOTD received on connect, or asks the server for it.
+    fn motd(&mut self, network: Option<NetworkToken>, channel: &Option<String>) {
+        let connection = self.connections.iter_mut()
+            .find(|x| Some(x.network) == network)
+            .and_then(|c| if c.client.is_some() { Some(c) } else { None });
+        let c = match connection {
+            Some(c) => c,
+            None => {
+                self.notify(network, channel, "Not connected".to_string());
+                return;
+            },
+        };
+        match c.motd.clone() {
+            Some(lines) => self.chat.add_server_block(c.network, motd_block(lines)),
+            None => {
+                c.motd_requested = true;
+                c.client.as_ref().unwrap().send_message(UserCommand::Motd);
+            },
+        }
+    }
+
+    /// Opens the channel browser and asks for the list, letting the server apply what it
+    /// can of the filter.
+    fn list(&mut self, network: Option<NetworkToken>, filter: Option<String>, channel: &Option<String>) {
+        let filter = ListFilter::parse(&filter.unwrap_or("".to_string()));
+        let (network, param) = match self.connections.iter().find(|x| Some(x.network) == network) {
+            Some(c) if c.client.is_some() => {
+                let elist = c.isupport.get("ELIST").map(|x| &**x).unwrap_or("");
+                (c.network, filter.elist(elist))
+            },
+            _ => {
+                self.notify(network, channel, "Not connected".to_string());
+                return;
+            },
+        };
+        self.chat.open_list(network, filter);
+        if let Some(client) = self.client(Some(network)) {
+            client.send_message(UserCommand::List(param));
+        }
+    }
+
+    /// Handles input meant for the channel browser. Returns `false` if it isn't open or the
+    /// input is for the rest of the client, like a command.
+    fn browse(&mut self, input: &UserInput) -> bool {
+        let (network, selected) = match self.chat.list_mut() {
+            Some((network, list)) => {
+                match *input {
+                    UserInput::SelectPrev => list.select_prev(),
+                    UserInput::SelectNext => list.select_next(),
+                    UserInput::ScrollUp => list.page_up(),
+                    UserInput::ScrollDown => list.page_down(),
+                    UserInput::ToggleSort => list.toggle_sort(),
+                    UserInput::Text(ref s) if !s.starts_with('/') => list.set_filter(ListFilter::parse(s)),
+                    UserInput::Activate | UserInput::Dismiss => {},
+                    _ => return false,
+                }
+                (network, list.selected().map(|x| x.to_string()))
+            },
+            None => return false,
+        };
+        match *input {
+            UserInput::Activate => {
+                if let (Some(channel), Some(client)) = (selected, self.client(Some(network))) {
+                    client.send_message(UserCommand::Join(channel, None));
+                }
+                self.chat.close_list();
+            },
+            UserInput::Dismiss => self.chat.close_list(),
+            _ => {},
+        }
+        true
+    }
+
+    /// Asks for the away flags and realnames of a channel's users, which NAMES doesn't carry
+    /// but `away-notify` keeps current afterwards.
+    fn fetch_members(&mut self, network: NetworkToken, channel: &str) {
+        let connection = match self.connections.iter().find(|x| x.network == network) {
+            Some(c) => c,
+            None => return,
+        };
+        if !connection.capabilities.iter().any(|x| x == "away-notify") { return; }
+        if let Some(ref client) = connection.client {
+            client.send_message(UserCommand::Who(channel.to_string()));
+        }
+    }
+
+    /// Shows netsplits and netjoins as a single line and inserts history playback, while the
+    /// messages of any other batch are handled one by one.
+    fn batch(&mut self, network: NetworkToken, kind: String, params: Vec<String>, messages: Vec<ClientEvent>) {
+        match &*kind {
+            "chathistory" => {
+                let target = params.get(0).cloned().unwrap_or("".to_string());
+                self.history(network, target, messages);
+            },
+            "netsplit" | "netjoin" => {
+                let mut nicks: Vec<String> = Vec::new();
+                for message in messages {
+                    match message {
+                        ClientEvent::Command(ref cmd) if cmd.command == CommandType::Quit => {
+                            if let Some(Sender::User(ref nick, _, _)) = cmd.prefix {
+                                for channel in self.chat.channels(network) {
+                                    self.chat.remove_name(network, channel, nick.clone());
+                                }
+                                if !nicks.contains(nick) { nicks.push(nick.clone()); }
+                            }
+                        },
+                        ClientEvent::JoinChannel(channel, member) => {
+                            let nick = member.nick.clone();
+                            self.chat.add_name(network, channel, member);
+                            if !nicks.contains(&nick) { nicks.push(nick); }
+                        },
+                        other => self.client_event(network, other),
+                    }
+                }
+                let servers = params.join(" and ");
+                let msg = if kind == "netsplit" {
+                    format!("Netsplit between {}, {} quit: {}", servers, nicks.len(), nicks.join(", "))
+                } else {
+                    format!("Netjoin between {}, {} joined: {}", servers, nicks.len(), nicks.join(", "))
+                };
+                self.chat.add_server_message(Some(network), msg);
+            },
+            _ => {
+                for message in messages {
+                    self.client_event(network, message);
+                }
+            },
+        }
+    }
+
+    /// Lets the channels a `FAIL CHATHISTORY` names be asked again, or every channel when
+    /// it names none we are waiting on.
+    fn history_failed(&mut self, network: NetworkToken, m: &Command) {
+        if let Some(c) = self.connections.iter_mut().find(|x| x.network == network) {
+            let named: Vec<String> = m.params.data.iter().map(|x| x.to_lowercase()).collect();
+            if c.history_pending.iter().any(|x| named.contains(&x.0)) {
+                c.history_pending.retain(|x| !named.contains(&x.0));
+            } else {
+                c.history_pending.clear();
+            }
+        }
+    }
+
+    fn history(&mut self, network: NetworkToken, target: String, events: Vec<ClientEvent>) {
+        let nickname = self.nickname(network).to_string();
+        if let Some(c) = self.connections.iter_mut().find(|x| x.network == network) {
+            let key = target.to_lowercase();
+            c.history_pending.retain(|x| x.0 != key);
+            if events.len() == 0 {
+                c.history_complete.push(key);
+            }
+        }
+
+        for event in events {
+            let (sender, message, m_type, meta) = match event {
+                ClientEvent::ChannelMessage(_, sender, message, meta) => (sender, message, MessageType::Normal, meta),
+                ClientEvent::ChannelNotice(_, sender, message, meta) => (sender, message, MessageType::Notice, meta),
+                _ => continue,
+            };
+            self.chat.insert_chat_message(network, &target,
+                                          sender.as_ref().map(|x| &**x).unwrap_or(&*nickname),
+                                          &message, m_type, meta.time, meta.msgid);
+        }
+    }
 
-            match self.text_input.read() {
-                Some(UserInput::Close) => break,
-                Some(UserInput::SetTab(c)) => {
+    fn nickname(&self, network: NetworkToken) -> &str {
+        self.connections.iter()
+            .find(|x| x.network == network)
+            .map(|x| &*x.nickname)
+            .unwrap_or("")
+    }
+
+    fn client(&self, network: Option<NetworkToken>) -> Option<&IrcClient> {
+        self.connections.iter()
+            .find(|x| Some(x.network) == network)
+            .and_then(|x| x.client.as_ref())
+    }
+
+    pub fn run(mut self) {
+        self.render();
+        loop {
+            match self.next_event() {
+                Wake::Client(network, message) => {
+                    self.client_event(network, message);
+                    let mut pending = Vec::new();
+                    if let Some(c) = self.connections.iter_mut().find(|x| x.network == network) {
+                        if let Some(ref mut client) = c.client {
+                            pending.extend(client.poll_messages());
+                        }
+                    }
+                    for message in pending {
+                        self.client_event(network, message);
+                    }
+                },
+                Wake::Disconnected(network) => {
+                    self.connection_lost(network);
+                },
+                Wake::Response(network, id, replies) => {
+                    self.response(network, id, replies.unwrap_or(Vec::new()));
+                },
+                Wake::Term(TermEvent::Input(bytes)) => {
+                    self.text_input.feed(&bytes);
+                    if !self.user_input() { break; }
+                },
+                Wake::Term(TermEvent::Log(msg)) => {
+                    let network = self.chat.active_network();
+                    self.chat.add_server_message(network, msg);
+                },
+                Wake::Term(TermEvent::Resize) => {},
+                Wake::Tick => {
+                    self.chat.tick();
+                },
+                Wake::IdentifyTimeout(network) => {
+                    self.identify_failed(network, "services did not respond");
+                },
+                Wake::Closed => break,
+            }
+            if self.quitting { break; }
+
+            self.render();
+        }
+
+        drop(self.stream);
+        for connection in self.connections {
+            if let Some(client) = connection.client {
+                match client.close() {
+                    Err(e) => panic!("From Client: {}", e),
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    /// Blocks on the reactor until a terminal event, a client event, or the next alert
+    /// animation frame is due. The timer is only armed while a tab is alerting.
+    fn next_event(&mut self) -> Wake {
+        let mut sources: Vec<WakeFuture> = Vec::new();
+
+        sources.push(Box::new((&mut self.events).into_future()
+            .map(|(e, _)| e.map(Wake::Term).unwrap_or(Wake::Closed))
+            .map_err(|_| ())));
+
+        for connection in self.connections.iter_mut() {
+            let network = connection.network;
+            for &mut (id, _, ref mut response) in connection.requests.iter_mut() {
+                sources.push(Box::new(response
+                    .then(move |r| Ok(Wake::Response(network, id, r.ok())))));
+            }
+            if let Some(deadline) = connection.identifying {
+                sources.push(Box::new(Timeout::new_at(deadline, &self.core.handle())
+                    .unwrap()
+                    .map(move |_| Wake::IdentifyTimeout(network))
+                    .map_err(|_| ())));
+            }
+            let client = match connection.client {
+                Some(ref mut client) => client,
+                None => continue,
+            };
+            sources.push(Box::new(client.into_future()
+                .map(move |(e, _)| match e {
+                    Some(e) => Wake::Client(network, e),
+                    None => Wake::Disconnected(network),
+                })
+                .map_err(|_| ())));
+        }
+
+        if self.chat.is_animating() {
+            let now = Instant::now();
+            if self.next_tick <= now {
+                self.next_tick = now + Duration::from_millis(ALERT_TICK_MS);
+            }
+            sources.push(Box::new(Timeout::new_at(self.next_tick, &self.core.handle())
+                .unwrap()
+                .map(|_| Wake::Tick)
+                .map_err(|_| ())));
+        }
+
+        self.core.run(future::select_all(sources).map(|(w, _, _)| w).map_err(|_| ()))
+            .unwrap_or(Wake::Closed)
+    }
+
+    fn client_event(&mut self, network: NetworkToken, message: ClientEvent) {
+        let nickname = self.nickname(network).to_string();
+        match message {
+            ClientEvent::Command(m) => {
+                if m.command == CommandType::Err_NicknameInUse {
+                    self.nick_in_use(network);
+                }
+                if m.command == CommandType::Rpl_ISupport {
+                    self.isupport(network, &m);
+                }
+                if m.command == CommandType::Err_SaslFail || m.command == CommandType::Err_NickLocked {
+                    self.identify_failed(network, &m.describe());
+                }
+                if m.unknown.as_ref().map(|x| x == "FAIL").unwrap_or(false) &&
+                   m.get_param(0) == Some("CHATHISTORY") {
+                    self.history_failed(network, &m);
+                }
+                let time = m.time().unwrap_or_else(Timestamp::now);
+                let open = self.chat.channels(network);
+                let channel = match (m.error_channel(), self.connections.iter_mut().find(|x| x.network == network)) {
+                    (Some(c), Some(connection)) => connection.joins.error_tab(c, &open),
+                    _ => None,
+                };
+                match channel {
+                    Some(channel) => self.chat.add_info_message(network, &channel, m.describe(), time),
+                    None => self.chat.add_server_message_at(Some(network), m.describe(), time),
+                }
+            },
+            ClientEvent::ChannelMessage(channel, sender, message, meta) => {
+                self.chat.add_chat_message(network, channel,
+                                      sender.as_ref().map(|x| &**x)
+                                        .unwrap_or(&*nickname),
+                                      &*nickname,
+                                      &message, MessageType::Normal, meta.time, meta.msgid);
+            },
+            ClientEvent::PendingMessage(id, target, message, time) => {
+                self.chat.add_pending_message(network, &target, id, &nickname, &message, time);
+            },
+            ClientEvent::MessageConfirmed(id, target) => {
+                self.chat.set_delivered(network, &target, id, None);
+            },
+            ClientEvent::MessageRejected(id, target, reason) => {
+                self.chat.set_delivered(network, &target, id, Some(reason));
+            },
+            ClientEvent::ChannelNotice(channel, sender, message, meta) => {
+                self.chat.add_chat_message(network, channel,
+                                           sender.as_ref().map(|x| &**x)
+                                           .unwrap_or(&*nickname),
+                                           &*nickname,
+                                           &message, MessageType::Notice, meta.time, meta.msgid);
+            },
+            ClientEvent::JoinChannel(channel, member) => {
+                if member.is(&nickname) {
+                    if let Some(connection) = self.connections.iter_mut().find(|x| x.network == network) {
+                        connection.joins.remove(&channel);
+                    }
+                    self.chat.add_channel(network, channel.clone());
+                    self.fetch_history(network, &channel, true);
+                    self.fetch_members(network, &channel);
+                } else {
+                    self.chat.add_name(network, channel, member);
+                }
+            },
+            ClientEvent::LeaveChannel(channel, sender) => {
+                let sender = sender.unwrap_or("".to_string());
+                if sender.to_lowercase() == nickname.to_lowercase() {
+                    self.chat.remove_channel(network, &channel);
+                } else {
+                    self.chat.remove_name(network, channel, sender);
+                }
+            },
+            ClientEvent::Connected => {
+                self.connected(network);
+            },
+            ClientEvent::LoggedIn(account) => {
+                self.chat.add_server_message(Some(network), format!("Logged in as {}", account));
+                self.identified(network);
+            },
+            ClientEvent::Capabilities(enabled) => {
+                if let Some(c) = self.connections.iter_mut().find(|x| x.network == network) {
+                    c.capabilities = enabled;
+                }
+            },
+            ClientEvent::WhoisResult(whois) => {
+                for line in whois_card(&whois) {
+                    self.chat.add_server_message(Some(network), line);
+                }
+            },
+            ClientEvent::Motd(lines) => {
+                let show = self.config.ui.show_motd;
+                if let Some(c) = self.connections.iter_mut().find(|x| x.network == network) {
+                    c.motd = Some(lines.clone());
+                    if show || c.motd_requested {
+                        self.chat.add_server_block(network, motd_block(lines));
+                    }
+                    c.motd_requested = false;
+                }
+            },
+            ClientEvent::ChannelList(entries) => {
+                self.chat.set_list_entries(network, entries);
+            },
+            ClientEvent::Batch { kind, params, messages } => {
+                self.batch(network, kind, params, messages);
+            },
+            ClientEvent::PrivateNotice(sender, message, meta) => {
+                let sender = sender.unwrap_or("".to_string());
+                let (confirmed, failed) = match self.connections.iter()
+                    .find(|x| x.network == network)
+                    .and_then(|x| x.config.identify.as_ref()) {
+                    Some(identify) if identify.service.to_lowercase() == sender.to_lowercase() => {
+                        (message.contains(&*identify.pattern),
+                         identify.failure_patterns.iter().any(|x| message.contains(&**x)))
+                    },
+                    _ => (false, false),
+                };
+                self.chat.add_server_message_at(Some(network), format!("-{}- {}", sender, message), meta.time);
+                if confirmed {
+                    self.identified(network);
+                } else if failed {
+                    self.identify_failed(network, &message);
+                }
+            },
+            ClientEvent::Topic(channel, topic) => {
+                self.chat.add_topic(network, channel, topic);
+            },
+            ClientEvent::Names(channel, names) => {
+                self.chat.add_names(network, channel, names);
+            },
+            ClientEvent::NamesEnd(channel) => {
+                self.chat.set_names(network, channel);
+            },
+            ClientEvent::WhoReply(channel, member) => {
+                self.chat.update_member(network, channel, member);
+            },
+            ClientEvent::Away(nick, message) => {
+                self.chat.set_away(network, &nick, message.is_some());
+            },
+            ClientEvent::Account(nick, account) => {
+                self.chat.set_account(network, &nick, account);
+            },
+            ClientEvent::ChannelMode(channel, sender, changes, meta) => {
+                let mut modes = String::new();
+                let mut params = Vec::new();
+                for (add, mode, param) in changes {
+                    modes.push(if add { '+' } else { '-' });
+                    modes.push(mode);
+                    if let Some(param) = param {
+                        self.chat.set_member_mode(network, &channel, &param, mode, add);
+                        params.push(param);
+                    }
+                }
+                let sender = sender.unwrap_or("".to_string());
+                self.chat.add_info_message(network, &channel,
+                                           format!("{} sets mode {} {}", sender, modes, params.join(" ")),
+                                           meta.time);
+            },
+            _ => {},
+        }
+    }
+
+    /// Handles every complete key sequence buffered in the text input. Returns `false` once
+    /// the user asks to close.
+    fn user_input(&mut self) -> bool {
+        loop {
+            let browsing = self.chat.list_mut().is_some();
+            self.text_input.set_browsing(browsing);
+            let input = match self.text_input.read() {
+                Some(input) => input,
+                None => break,
+            };
+            if self.browse(&input) { continue; }
+
+            match input {
+                UserInput::Close => return false,
+                UserInput::SetTab(c) => {
+                    self.chat.close_list();
                     self.chat.set_tab(c);
                 },
-                Some(UserInput::PrevTab) => {
+                UserInput::PrevTab => {
+                    self.chat.close_list();
                     self.chat.prev_tab();
                 },
-                Some(UserInput::NextTab) => {
+                UserInput::NextTab => {
+                    self.chat.close_list();
                     self.chat.next_tab();
                 },
-                Some(UserInput::ScrollUp) => {
-                    self.chat.scroll_up();
+                UserInput::ScrollUp => {
+                    if self.chat.scroll_up() {
+                        let network = self.chat.active_network();
+                        let channel = self.chat.active_channel().map(|x| x.to_string());
+                        if let (Some(network), Some(channel)) = (network, channel) {
+                            self.fetch_history(network, &channel, false);
+                        }
+                    }
                 },
-                Some(UserInput::ScrollDown) => {
+                UserInput::ScrollDown => {
                     self.chat.scroll_down();
                 },
-                Some(UserInput::Text(s)) => {
-                    let channel = self.chat.active_channel();
-
-                    match UserInputParser::parse(s, channel) {
-                        Ok(msg) => { let _ = self.client.send_message(msg); },
-                        Err(_) =>{ error!("Unknown command") },
-                    }
+                UserInput::ToggleNickList => self.chat.toggle_nick_list(),
+                UserInput::ScrollNicksUp => self.chat.scroll_nick_list_up(),
+                UserInput::ScrollNicksDown => self.chat.scroll_nick_list_down(),
+                UserInput::Text(s) => {
+                    let network = self.chat.active_network();
+                    let channel = self.chat.active_channel().map(|x| x.to_string());
+                    self.execute(network, s, channel);
+                    if self.quitting { return false; }
                 },
-                _ => {},
+                UserInput::SelectPrev | UserInput::SelectNext | UserInput::Activate |
+                UserInput::Dismiss | UserInput::ToggleSort => {},
             }
+        }
+
+        true
+    }
+
+    /// Runs a configured on-connect command on `network`.
+    fn run_command(&mut self, network: NetworkToken, line: String) {
+        self.execute(Some(network), line, None);
+    }
+
+    /// Runs a line from the input box, after expanding aliases into the commands they stand for.
+    fn execute(&mut self, network: Option<NetworkToken>, line: String, channel: Option<String>) {
+        let nickname = network.map(|n| self.nickname(n).to_string()).unwrap_or("".to_string());
+        match self.commands.expand(&line, &nickname, channel.as_ref().map(|x| &**x)) {
+            Ok(lines) => {
+                for line in lines {
+                    if self.quitting { break; }
+                    self.execute_line(network, line, channel.clone());
+                }
+            },
+            Err(e) => self.notify(network, &channel, e.to_string()),
+        }
+    }
 
-            if self.error_recv.is_some() {
-                match self.error_recv.as_ref().unwrap().try_recv() {
-                    Ok(msg) => {
-                        self.chat.add_server_message(msg);
+    fn execute_line(&mut self, network: Option<NetworkToken>, line: String, channel: Option<String>) {
+        let parsed = self.commands.parse(&line, channel.as_ref().map(|x| &**x));
+
+        match parsed {
+            Ok(UserCommand::JoinUrl(url)) => {
+                self.join_url(url);
+            },
+            Ok(UserCommand::Connect(Some(address))) => {
+                let config = self.network_config(address);
+                self.connect(config);
+            },
+            Ok(UserCommand::Connect(None)) => {
+                if let Some(network) = network {
+                    self.open(network);
+                }
+            },
+            Ok(UserCommand::Server(address)) => {
+                match network {
+                    Some(network) => self.set_server(network, address),
+                    None => {
+                        let config = self.network_config(address);
+                        self.connect(config);
                     },
-                    _ => {}
                 }
-            }
+            },
+            Ok(UserCommand::Disconnect(msg)) => {
+                if let Some(network) = network {
+                    self.disconnect(network, msg);
+                }
+            },
+            Ok(UserCommand::Reconnect) => {
+                if let Some(network) = network {
+                    self.disconnect(network, "Reconnecting".to_string());
+                    self.open(network);
+                }
+            },
+            Ok(UserCommand::Quit(msg)) => {
+                let networks: Vec<NetworkToken> = self.connections.iter().map(|x| x.network).collect();
+                for network in networks {
+                    self.disconnect(network, msg.clone());
+                }
+                self.quitting = true;
+            },
+            Ok(UserCommand::Reload) => {
+                self.reload();
+            },
+            Ok(UserCommand::List(filter)) => {
+                self.list(network, filter, &channel);
+            },
+            Ok(UserCommand::Motd) => {
+                self.motd(network, &channel);
+            },
+            Ok(UserCommand::Ban(chan, target)) => {
+                if let Some(network) = network {
+                    let mask = self.ban_mask(network, &target);
+                    self.send(network, UserCommand::Ban(chan, mask), &channel);
+                }
+            },
+            Ok(UserCommand::Unban(chan, target)) => {
+                if let Some(network) = network {
+                    let mask = self.ban_mask(network, &target);
+                    self.send(network, UserCommand::Unban(chan, mask), &channel);
+                }
+            },
+            Ok(UserCommand::KickBan(chan, nick, reason)) => {
+                if let Some(network) = network {
+                    let mask = self.ban_mask(network, &nick);
+                    self.send(network, UserCommand::Ban(chan.clone(), mask), &channel);
+                    self.send(network, UserCommand::Kick(chan, nick, reason), &channel);
+                }
+            },
+            Ok(UserCommand::Notice(target, message)) => {
+                if let Some(network) = network {
+                    // Without echo-message the server won't show us our own notice.
+                    let echoed = self.connections.iter().find(|x| x.network == network)
+                        .map(|c| c.capabilities.iter().any(|x| x == "echo-message"))
+                        .unwrap_or(false);
+                    if !echoed {
+                        let nickname = self.nickname(network).to_string();
+                        self.chat.add_chat_message(network, target.clone(), &nickname, &nickname,
+                                                   &message, MessageType::Notice, Timestamp::now(), None);
+                    }
+                    self.send(network, UserCommand::Notice(target, message), &channel);
+                }
+            },
+            Ok(UserCommand::Alias(name, expansion)) => {
+                let lines = self.alias(name, expansion);
+                for line in lines {
+                    self.notify(network, &channel, line);
+                }
+            },
+            Ok(UserCommand::Unalias(name)) => {
+                let msg = if self.commands.remove_alias(&name) {
+                    format!("Removed alias /{}", name.trim_left_matches('/'))
+                } else {
+                    format!("No alias named /{}", name.trim_left_matches('/'))
+                };
+                self.notify(network, &channel, msg);
+            },
+            Ok(UserCommand::Help(name)) => {
+                let lines = self.help(name);
+                for line in lines {
+                    self.notify(network, &channel, line);
+                }
+            },
+            Ok(msg) => {
+                // Queries are answered in the tab they came from.
+                let query = match msg {
+                    UserCommand::WhoIs(..) | UserCommand::GetTopic(..) | UserCommand::Names(..) |
+                    UserCommand::Who(..) | UserCommand::WhoWas(..) | UserCommand::IsOn(..) |
+                    UserCommand::UserHost(..) => true,
+                    _ => false,
+                };
+                if query {
+                    self.request(network, msg, channel);
+                } else {
+                    if let (Some(network), &UserCommand::Join(ref chan, _)) = (network, &msg) {
+                        if let Some(connection) = self.connections.iter_mut().find(|x| x.network == network) {
+                            connection.joins.add(chan, channel.clone());
+                        }
+                    }
+                    let sent = match self.client(network) {
+                        Some(client) => {
+                            client.send_message(msg);
+                            true
+                        },
+                        None => false,
+                    };
+                    if !sent {
+                        self.notify(network, &channel, "Not connected".to_string());
+                    }
+                }
+            },
+            Err(ParseError::InputRequired) => {},
+            Err(e) => self.notify(network, &channel, e.to_string()),
+        }
+    }
 
-            self.window.init();
-            self.text_input.render(&mut self.window);
-            self.chat.render(&mut self.window);
-            self.window.render(&mut self.stream);
-            self.text_input.set_cursor(&mut self.stream, &self.window);
+    /// Shows a line from the client itself in the tab a command was typed in.
+    fn notify(&mut self, network: Option<NetworkToken>, channel: &Option<String>, msg: String) {
+        match (network, channel.as_ref()) {
+            (Some(network), Some(channel)) => self.chat.add_info_message(network, channel, msg, Timestamp::now()),
+            _ => self.chat.add_server_message(network, msg),
+        }
+    }
+
+    /// Defines an alias when given an expansion, otherwise describes the aliases.
+    fn alias(&mut self, name: Option<String>, expansion: Option<String>) -> Vec<String> {
+        match (name, expansion) {
+            (Some(name), Some(expansion)) => {
+                self.commands.set_alias(&name, &expansion);
+                vec![format!("/{} is now an alias for {}", name.trim_left_matches('/'), expansion)]
+            },
+            (Some(name), None) => {
+                let name = name.trim_left_matches('/').to_lowercase();
+                match self.commands.aliases().get(&name) {
+                    Some(expansion) => vec![format!("/{} = {}", name, expansion)],
+                    None => vec![format!("No alias named /{}", name)],
+                }
+            },
+            (None, _) => {
+                if self.commands.aliases().is_empty() {
+                    vec!["No aliases, define one with /alias <name> <expansion>".to_string()]
+                } else {
+                    self.commands.aliases().iter().map(|(k, v)| format!("/{} = {}", k, v)).collect()
+                }
+            },
+        }
+    }
 
-            thread::sleep(Duration::from_millis(50));
+    /// The list of commands, or the usage and description of one.
+    fn help(&self, name: Option<String>) -> Vec<String> {
+        match name {
+            None => {
+                let commands = self.commands.commands();
+                let width = commands.iter().map(|x| x.usage().chars().count()).max().unwrap_or(0);
+                let mut lines = vec!["Commands, see /help <command> for one:".to_string()];
+                lines.extend(commands.iter().map(|x| format!("  {:<width$}  {}", x.usage(), x.help(),
+                                                             width = width)));
+                lines
+            },
+            Some(name) => match self.commands.find(&name) {
+                Some(spec) => {
+                    let mut lines = vec![format!("Usage: {}", spec.usage()), spec.help().to_string()];
+                    if !spec.aliases().is_empty() {
+                        let aliases: Vec<String> = spec.aliases().iter().map(|x| format!("/{}", x)).collect();
+                        lines.push(format!("Also: {}", aliases.join(", ")));
+                    }
+                    lines
+                },
+                None => vec![format!("No command named /{}", name.trim_left_matches('/'))],
+            },
         }
+    }
 
-        drop(self.stream);
-        match self.client.close() {
-            Err(e) => panic!("From Client: {}", e),
-            _ => {}
+    fn send(&mut self, network: NetworkToken, msg: UserCommand, channel: &Option<String>) {
+        match self.client(Some(network)) {
+            Some(client) => client.send_message(msg),
+            None => self.notify(Some(network), channel, "Not connected".to_string()),
+        }
+    }
+
+    /// The mask to ban for `target`: a mask is used as given, and a nick we've seen with
+    /// its host becomes `*!*@host`.
+    fn ban_mask(&self, network: NetworkToken, target: &str) -> String {
+        if target.contains(|c| c == '!' || c == '@' || c == '*' || c == '$') {
+            return target.to_string();
+        }
+        match self.chat.member(network, target) {
+            Some(member) => member.ban_mask(),
+            None => format!("{}!*@*", target),
+        }
+    }
+
+    /// Sends a query whose replies are shown in `channel`'s tab, or the server tab for `None`.
+    fn request(&mut self, network: Option<NetworkToken>, msg: UserCommand, channel: Option<String>) {
+        let id = self.next_request;
+        let connection = self.connections.iter_mut()
+            .find(|x| Some(x.network) == network)
+            .and_then(|c| if c.client.is_some() { Some(c) } else { None });
+        match connection {
+            Some(c) => {
+                let response = c.client.as_ref().unwrap().request(msg);
+                c.requests.push((id, channel, response));
+                self.next_request += 1;
+            },
+            None => self.notify(network, &channel, "Not connected".to_string()),
+        }
+    }
+
+    fn response(&mut self, network: NetworkToken, id: u64, replies: Vec<Command>) {
+        let (channel, modes) = match self.connections.iter_mut().find(|x| x.network == network) {
+            Some(c) => match c.requests.iter().position(|x| x.0 == id) {
+                Some(pos) => (c.requests.remove(pos).1, c.modes.clone()),
+                None => return,
+            },
+            None => return,
+        };
+        let lines = match Whois::from_replies(&replies) {
+            Some(whois) => whois_card(&whois).into_iter().map(|x| (x, Timestamp::now())).collect(),
+            None => replies.iter().map(|reply| {
+                if let Some(ClientEvent::Topic(target, topic)) = ClientEvent::from_command(reply, &modes) {
+                    self.chat.add_topic(network, target, topic);
+                }
+                (reply.describe(), reply.time().unwrap_or_else(Timestamp::now))
+            }).collect::<Vec<_>>(),
+        };
+        for (line, time) in lines {
+            match channel {
+                Some(ref channel) => self.chat.add_info_message(network, channel, line, time),
+                None => self.chat.add_server_message_at(Some(network), line, time),
+            }
+        }
+    }
+
+    fn render(&mut self) {
+        self.window.init();
+        self.text_input.render(&mut self.window);
+        self.chat.render(&mut self.window);
+        if self.window.is_dirty() {
+            self.window.render(&mut self.stream);
+            self.text_input.set_cursor(&mut self.stream, &self.window);
         }
     }
 
     pub fn init_log(&mut self) -> Result<(), log::SetLoggerError> {
-        let (tx, rx) = channel();
-        self.error_recv = Some(rx);
+        let tx = self.events.sender();
         log::set_logger(|max_log_level| {
-            max_log_level.set(log::LogLevelFilter::Error);
+            max_log_level.set(log::LogLevelFilter::Info);
             Box::new(TerminalLogger::new(tx))
         })
     }
 }
 
+/// The lines shown for a MOTD, which a server without one sends empty.
+fn motd_block(lines: Vec<String>) -> Vec<String> {
+    if lines.is_empty() { vec!["No message of the day".to_string()] } else { lines }
+}
+
+/// Lays out a WHOIS result as a block of lines.
+fn whois_card(whois: &Whois) -> Vec<String> {
+    let mut lines = Vec::new();
+    lines.push(format!("\u{250c} {} ({}@{})", whois.nick,
+                       whois.user.as_ref().map(|x| &**x).unwrap_or("?"),
+                       whois.host.as_ref().map(|x| &**x).unwrap_or("?")));
+    if let Some(ref realname) = whois.realname {
+        lines.push(format!("Name: {}", realname));
+    }
+    if let Some(ref account) = whois.account {
+        lines.push(format!("Account: {}", account));
+    }
+    if let Some(ref server) = whois.server {
+        match whois.server_info {
+            Some(ref info) => lines.push(format!("Server: {} ({})", server, info)),
+            None => lines.push(format!("Server: {}", server)),
+        }
+    }
+    if whois.channels.len() > 0 {
+        lines.push(format!("Channels: {}", whois.channels.join(" ")));
+    }
+    if let Some(ref away) = whois.away {
+        lines.push(format!("Away: {}", away));
+    }
+    if let Some(idle) = whois.idle {
+        lines.push(format!("Idle: {}", duration(idle)));
+    }
+    if let Some(signon) = whois.signon {
+        lines.push(format!("Signed on: {}", signon.format("%a %e %b %Y %H:%M")));
+    }
+    if whois.operator {
+        lines.push("Is an IRC operator".to_string());
+    }
+    if whois.secure {
+        lines.push("Is using a secure connection".to_string());
+    }
+
+    let last = lines.len() - 1;
+    for (i, line) in lines.iter_mut().enumerate().skip(1) {
+        line.insert_str(0, if i == last { "\u{2514} " } else { "\u{2502} " });
+    }
+    lines
+}
+
+/// Formats seconds as days, hours, minutes and seconds, leaving out leading zero units.
+fn duration(secs: u64) -> String {
+    let units = [(secs / 86400, "d"), (secs / 3600 % 24, "h"), (secs / 60 % 60, "m"), (secs % 60, "s")];
+    let parts: Vec<String> = units.iter()
+        .skip_while(|x| x.0 == 0)
+        .map(|x| format!("{}{}", x.0, x.1))
+        .collect();
+    if parts.is_empty() { "0s".to_string() } else { parts.join(" ") }
+}
+
 struct TerminalLogger {
-    log_sink: Arc<Mutex<Sender<String>>>,
+    log_sink: Arc<Mutex<FutSender<TermEvent>>>,
 }
 
-use std::sync::mpsc::{channel, Sender, Receiver};
+use futures::sync::mpsc::UnboundedSender as FutSender;
 use std::sync::{Arc, Mutex};
 
 
 impl TerminalLogger {
-    fn new(log_sink: Sender<String>) -> TerminalLogger {
+    fn new(log_sink: FutSender<TermEvent>) -> TerminalLogger {
         TerminalLogger { log_sink: Arc::new(Mutex::new(log_sink)) }
     }
 }
 
 impl log::Log for TerminalLogger {
-    fn enabled(&self, _metadata: &log::LogMetadata) -> bool {
-        true
+    /// Errors from anywhere, and status messages logged at info by this crate.
+    fn enabled(&self, metadata: &log::LogMetadata) -> bool {
+        metadata.level() <= log::LogLevel::Error ||
+            metadata.target().split("::").next() == module_path!().split("::").next()
     }
 
     fn log(&self, record: &log::LogRecord) {
         if self.enabled(record.metadata()) {
-            self.log_sink.lock().unwrap().send(
-                format!("{}: {} @ {:?}", record.level(), record.args(), record.location()));
+            let msg = if record.level() <= log::LogLevel::Error {
+                format!("{}: {} @ {:?}", record.level(), record.args(), record.location())
+            } else {
+                record.args().to_string()
+            };
+            let _ = FutSender::send(&*self.log_sink.lock().unwrap(), TermEvent::Log(msg));
         }
     }
 }
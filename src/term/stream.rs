@@ -1,5 +1,5 @@
 use termion::raw::{IntoRawMode, RawTerminal};
-use std::io::{Read, Write, Result, Stdout, stdout};
+use std::io::{Write, Result, Stdout, stdout};
 
 pub struct TermStream {
     out_stream: RawTerminal<Stdout>,
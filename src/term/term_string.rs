@@ -136,6 +136,66 @@ impl TermString {
         r.unwrap().1
     }
 
+    /// Renders IRC formatting: mIRC colour codes (`\x03fg,bg`), reverse (`\x16`) and reset
+    /// (`\x0F`), over the given default colours. Bold, italics and underline have no glyph
+    /// attribute here and are dropped.
+    pub fn from_irc(text: &str, fg: Color, bg: Color) -> TermString {
+        fn irc_color(code: u8) -> Option<Color> {
+            let colors = [Color::LightWhite, Color::Black, Color::Blue, Color::Green,
+                          Color::LightRed, Color::Red, Color::Magenta, Color::Yellow,
+                          Color::LightYellow, Color::LightGreen, Color::Cyan, Color::LightCyan,
+                          Color::LightBlue, Color::LightMagenta, Color::LightBlack, Color::White];
+            colors.get(code as usize).cloned()
+        }
+
+        fn digits(chars: &mut ::std::iter::Peekable<::std::str::Chars>) -> Option<u8> {
+            let mut code = None;
+            for _ in 0..2 {
+                match chars.peek().and_then(|c| c.to_digit(10)) {
+                    Some(d) => code = Some(code.unwrap_or(0) * 10 + d as u8),
+                    None => break,
+                }
+                chars.next();
+            }
+            code
+        }
+
+        let (mut cur_fg, mut cur_bg) = (fg, bg);
+        let mut internal = Vec::new();
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '\x03' => {
+                    match digits(&mut chars) {
+                        Some(code) => {
+                            cur_fg = irc_color(code).unwrap_or(cur_fg);
+                            let comma = chars.peek() == Some(&',');
+                            if comma {
+                                chars.next();
+                                match digits(&mut chars) {
+                                    Some(code) => cur_bg = irc_color(code).unwrap_or(cur_bg),
+                                    None => internal.push(Glyph(',', Some(cur_fg), Some(cur_bg))),
+                                }
+                            }
+                        },
+                        None => {
+                            cur_fg = fg;
+                            cur_bg = bg;
+                        },
+                    }
+                },
+                '\x16' => ::std::mem::swap(&mut cur_fg, &mut cur_bg),
+                '\x0F' => {
+                    cur_fg = fg;
+                    cur_bg = bg;
+                },
+                c if (c as u32) < 0x20 => {},
+                c => internal.push(Glyph(c, Some(cur_fg), Some(cur_bg))),
+            }
+        }
+        TermString { internal: internal }
+    }
+
     pub fn len(&self) -> usize {
         self.internal.len()
     }
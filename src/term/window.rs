@@ -1,14 +1,26 @@
-use term::controls::{TabBar, TabToken, TabStatus, MessagePane, MessageType};
+use term::controls::{TabBar, TabToken, TabStatus, MessagePane, MessageType, ChannelList, NickList};
 use term::{TermBuffer};
+use irc::{Timestamp, ChannelModes, Member, ListEntry, ListFilter};
 
 use std::collections::HashMap;
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct ChannelToken(u32);
 #[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NetworkToken(u32);
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct WindowToken(u32);
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum WindowKind {
+    Server,
+    Channel,
+}
+
 pub struct Window {
     token: WindowToken,
+    network: NetworkToken,
+    kind: WindowKind,
     name: String,
     tab: TabToken,
 }
@@ -16,15 +28,28 @@ pub struct Window {
 pub struct Channel {
     name: String,
     window: WindowToken,
-    users: Vec<String>,
-    new_users: Vec<String>,
+    users: Vec<Member>,
+    new_users: Vec<Member>,
+}
+
+pub struct Network {
+    token: NetworkToken,
+    window: WindowToken,
+    channels: Vec<Channel>,
+    modes: ChannelModes,
 }
 
 pub struct ChatWindows {
     message_pane: MessagePane,
     tab_bar: TabBar,
+    /// The channel browser open in place of the message pane, and the network it lists.
+    list: Option<(NetworkToken, ChannelList)>,
+    nick_list: NickList,
+    /// The tab whose users the nick list last showed.
+    nick_list_tab: Option<TabToken>,
     next_window: u32,
-    channels: Vec<Channel>,
+    next_network: u32,
+    networks: Vec<Network>,
     tabs: HashMap<TabToken, WindowToken>,
     windows: HashMap<WindowToken, Window>,
 }
@@ -34,19 +59,24 @@ impl ChatWindows {
         ChatWindows {
             message_pane: message_pane,
             tab_bar: tab_bar,
+            list: None,
+            nick_list: NickList::new(),
+            nick_list_tab: None,
             next_window: 0,
-            channels: Vec::new(),
+            next_network: 0,
+            networks: Vec::new(),
             tabs: HashMap::new(),
             windows: HashMap::new(),
         }
     }
 
-    pub fn add_chat_message(&mut self, target: String, from: &str, to: &str,  msg: &str, m_type: MessageType) {
-        match self.find_tab(&target) {
+    pub fn add_chat_message(&mut self, network: NetworkToken, target: String, from: &str, to: &str,
+                            msg: &str, m_type: MessageType, time: Timestamp, msgid: Option<String>) {
+        match self.find_tab(network, &target) {
             Some(wt) => {
                 let ref win = self.windows[&wt];
                 self.message_pane.add_chat_message(Some(win.tab),
-                    from.to_string(), msg.to_string(), m_type);
+                    from.to_string(), msg.to_string(), m_type, time, msgid);
                 if Some(win.tab) != self.tab_bar.active_tab() {
                     if msg.contains(to) {
                         self.tab_bar.set_alert(win.tab)
@@ -59,77 +89,86 @@ impl ChatWindows {
         }
     }
 
-    pub fn next_tab(&mut self) {
-        match self.tab_bar.active_tab() {
-            Some(t) => {
-                match self.find_channel(&t) {
-                    Some(token) => {
-                        let mut tokens: Vec<WindowToken> = self.windows.keys()
-                            .map(|x| x.clone())
-                            .collect();
-                        tokens.sort();
-                        match tokens.binary_search(&token) {
-                            Ok(pos) => {
-                                if pos + 1 < tokens.len() {
-                                    let token = tokens[pos + 1];
-                                    let tab = self.windows[&token].tab;
-                                    self.tab_bar.set_active(tab);
-                                    self.message_pane.set_dirty();
-                                }
-                            },
-                            Err(_) => {}
-                        }
-                    },
-                    None =>  {},
-                }
-            },
-            None => {}
+    /// Adds a message played back from history in time order, without marking the tab unread.
+    pub fn insert_chat_message(&mut self, network: NetworkToken, target: &str, from: &str, msg: &str,
+                               m_type: MessageType, time: Timestamp, msgid: Option<String>) {
+        if let Some(wt) = self.find_tab(network, target) {
+            let tab = self.windows[&wt].tab;
+            self.message_pane.insert_chat_message(Some(tab), from.to_string(), msg.to_string(),
+                                                  m_type, time, msgid);
         }
     }
 
-    pub fn prev_tab(&mut self) {
-        match self.tab_bar.active_tab() {
-            Some(t) => {
-                match self.find_channel(&t) {
-                    Some(token) => {
-                        let mut tokens: Vec<WindowToken> = self.windows.keys()
-                            .map(|x| x.clone())
-                            .collect();
-                        tokens.sort();
-                        match tokens.binary_search(&token) {
-                            Ok(pos) => {
-                                if pos > 0 {
-                                    let token = tokens[pos - 1];
-                                    let tab = self.windows[&token].tab;
-                                    self.tab_bar.set_active(tab);
-                                    self.message_pane.set_dirty();
-                                }
-                            },
-                            Err(_) => {}}
-                    },
-                    None =>  {},
-                }
-            },
-            None => {}
+    /// The time of the earliest message shown for `target`.
+    pub fn oldest_message(&self, network: NetworkToken, target: &str) -> Option<Timestamp> {
+        self.find_tab(network, target)
+            .and_then(|wt| self.message_pane.oldest(Some(self.windows[&wt].tab)))
+    }
+
+    /// Shows a message we sent before the server has accepted it.
+    pub fn add_pending_message(&mut self, network: NetworkToken, target: &str, id: u64, from: &str,
+                               msg: &str, time: Timestamp) {
+        if let Some(wt) = self.find_tab(network, target) {
+            let tab = self.windows[&wt].tab;
+            self.message_pane.add_pending_message(Some(tab), id, from.to_string(), msg.to_string(), time);
         }
     }
 
-    pub fn add_server_message(&mut self, msg: String) {
-        self.message_pane.add_server_message(None, msg);
+    /// Shows a line from the server in a channel's tab, such as a reply to a command issued there.
+    pub fn add_info_message(&mut self, network: NetworkToken, target: &str, msg: String, time: Timestamp) {
+        if let Some(wt) = self.find_tab(network, target) {
+            let tab = self.windows[&wt].tab;
+            self.message_pane.add_server_message(Some(tab), msg, time);
+        }
+    }
+
+    /// Settles a pending message, logging `reason` in its tab when it was rejected.
+    pub fn set_delivered(&mut self, network: NetworkToken, target: &str, id: u64, reason: Option<String>) {
+        if let Some(wt) = self.find_tab(network, target) {
+            let tab = self.windows[&wt].tab;
+            self.message_pane.set_delivered(Some(tab), id, reason.is_none());
+            if let Some(reason) = reason {
+                self.message_pane.add_server_message(Some(tab), format!("Message not delivered: {}", reason),
+                                                     Timestamp::now());
+            }
+        }
+    }
+
+    pub fn next_tab(&mut self) {
+        self.tab_bar.next_tab();
+        self.message_pane.set_dirty();
+    }
+
+    pub fn prev_tab(&mut self) {
+        self.tab_bar.prev_tab();
+        self.message_pane.set_dirty();
+    }
+
+    /// Adds a message to `network`'s server tab, or to the tab shown before any network
+    /// exists when `network` is `None`.
+    pub fn add_server_message(&mut self, network: Option<NetworkToken>, msg: String) {
+        self.add_server_message_at(network, msg, Timestamp::now());
+    }
+
+    pub fn add_server_message_at(&mut self, network: Option<NetworkToken>, msg: String, time: Timestamp) {
+        let tab = network.and_then(|n| self.find_network(n))
+            .map(|n| self.windows[&n.window].tab);
+        self.message_pane.add_server_message(tab, msg, time);
+    }
+
+    /// Adds preformatted lines, such as the MOTD, to `network`'s server tab as one block.
+    pub fn add_server_block(&mut self, network: NetworkToken, lines: Vec<String>) {
+        let tab = self.find_network(network).map(|n| self.windows[&n.window].tab);
+        self.message_pane.add_server_block(tab, lines, Timestamp::now());
     }
 
     pub fn set_tab(&mut self, index: u32) {
         match index {
             0 => self.server_tab(),
             _ => {
-                let mut tokens: Vec<WindowToken> = self.windows.keys()
-                        .map(|x| x.clone())
-                        .collect();
-                tokens.sort();
-                match tokens.get(index as usize - 1) {
-                    Some(t) => {
-                        let ref tab = self.windows[t].tab;
-                        self.tab_bar.set_active(tab.clone());
+                match self.tab_bar.tab_at(index as usize - 1) {
+                    Some(tab) => {
+                        self.tab_bar.set_active(tab);
                         self.message_pane.set_dirty();
                     },
                     None => {}
@@ -138,46 +177,109 @@ impl ChatWindows {
         }
     }
 
+    pub fn active_network(&self) -> Option<NetworkToken> {
+        self.active_window().map(|w| w.network)
+    }
+
     pub fn active_channel(&self) -> Option<&str> {
-        let tab = self.tab_bar.active_tab();
-        match tab {
-            Some(t) => {
-                match self.find_channel(&t) {
-                    Some(wt) => {
-                        let ref w = self.windows[&wt];
-                        Some(&*w.name)
-                    },
-                    None => None,
-                }
-            },
-            None => None,
+        match self.active_window() {
+            Some(w) if w.kind == WindowKind::Channel => Some(&*w.name),
+            _ => None,
         }
     }
 
+    fn active_window(&self) -> Option<&Window> {
+        self.tab_bar.active_tab()
+            .and_then(|t| self.find_window(&t))
+            .map(|wt| &self.windows[&wt])
+    }
+
     pub fn server_tab(&mut self) {
-        self.tab_bar.clear_active();
+        let tab = self.active_network()
+            .and_then(|n| self.find_network(n))
+            .map(|n| self.windows[&n.window].tab);
+        if let Some(tab) = tab {
+            self.tab_bar.set_active(tab);
+        }
         self.message_pane.set_dirty();
     }
 
-    pub fn add_channel(&mut self, channel: String) {
-        let tab = self.tab_bar.add_tab(channel.clone(), "".to_string(), TabStatus::Active);
-        self.next_window += 1;
-        let window = WindowToken(self.next_window);
-        self.tabs.insert(tab.clone(), window.clone());
-        self.channels.push( Channel { name: channel.clone(),
-                                      window: window.clone(),
-                                      users: Vec::new(),
-                                      new_users: Vec::new()});
-        self.windows.insert(window, Window { token: window, name: channel, tab: tab});
+    pub fn add_network(&mut self, name: String) -> NetworkToken {
+        self.next_network += 1;
+        let network = NetworkToken(self.next_network);
+        let tab = self.tab_bar.add_group(name.clone(), "".to_string(), TabStatus::Active);
+        let window = self.add_window(network, WindowKind::Server, name, tab);
+        self.networks.push(Network { token: network,
+                                     window: window,
+                                     channels: Vec::new(),
+                                     modes: ChannelModes::new() });
+        self.message_pane.set_dirty();
+
+        network
+    }
+
+    pub fn remove_network(&mut self, network: NetworkToken) {
+        if let Some(pos) = self.networks.iter().position(|x| x.token == network) {
+            let network = self.networks.remove(pos);
+            for chan in network.channels {
+                self.remove_window(&chan.window);
+            }
+            self.remove_window(&network.window);
+            self.message_pane.set_dirty();
+        }
+    }
+
+    /// Shows `status` as the topic line of `network`'s server tab and logs it there.
+    pub fn set_status(&mut self, network: NetworkToken, status: String) {
+        let tab = match self.find_network(network) {
+            Some(n) => self.windows[&n.window].tab,
+            None => return,
+        };
+        self.tab_bar.set_topic(tab, status.clone());
+        self.message_pane.add_server_message(Some(tab), status, Timestamp::now());
+    }
+
+    pub fn set_network_name(&mut self, network: NetworkToken, name: String) {
+        let tab = match self.find_network(network) {
+            Some(n) => self.windows[&n.window].tab,
+            None => return,
+        };
+        self.tab_bar.set_title(tab, name);
+    }
+
+    pub fn channels(&self, network: NetworkToken) -> Vec<String> {
+        self.find_network(network)
+            .map(|n| n.channels.iter().map(|x| x.name.clone()).collect())
+            .unwrap_or(Vec::new())
+    }
+
+    pub fn add_channel(&mut self, network: NetworkToken, channel: String) {
+        if let Some(wt) = self.find_tab(network, &channel) {
+            let tab = self.windows[&wt].tab;
+            self.tab_bar.set_active(tab);
+            self.message_pane.set_dirty();
+            return;
+        }
+        let group = match self.find_network(network) {
+            Some(n) => self.windows[&n.window].tab,
+            None => return,
+        };
+        let tab = self.tab_bar.add_tab(group, channel.clone(), "".to_string(), TabStatus::Active);
+        let window = self.add_window(network, WindowKind::Channel, channel.clone(), tab);
+        if let Some(n) = self.find_network_mut(network) {
+            n.channels.push( Channel { name: channel,
+                                       window: window,
+                                       users: Vec::new(),
+                                       new_users: Vec::new()});
+        }
         self.message_pane.set_dirty();
     }
 
-    pub fn remove_channel(&mut self, channel: &str) {
-        match self.find_tab(&channel) {
+    pub fn remove_channel(&mut self, network: NetworkToken, channel: &str) {
+        match self.find_tab(network, &channel) {
             Some(wt) => {
-                {
-                    let ref w = self.windows[&wt];
-                    self.tab_bar.remove_tab(w.tab);
+                if let Some(n) = self.find_network_mut(network) {
+                    n.channels.retain(|x| x.window != wt);
                 }
                 self.remove_window(&wt);
                 self.message_pane.set_dirty();
@@ -186,65 +288,156 @@ impl ChatWindows {
         }
     }
 
-    pub fn add_topic(&mut self, target:String, topic: String) {
-        let tab = self.find_tab(&target);
+    pub fn add_topic(&mut self, network: NetworkToken, target:String, topic: String) {
+        let tab = self.find_tab(network, &target);
         match tab {
             Some(w) => self.tab_bar.set_topic(self.windows[&w].tab, topic),
             None => {}
         }
     }
 
-    pub fn add_names(&mut self, target: String, mut names: Vec<String>) {
-        if let Some(chan) = self.channels.iter_mut().find(|x|x.name == target) {
+    pub fn add_names(&mut self, network: NetworkToken, target: String, mut names: Vec<Member>) {
+        if let Some(chan) = self.find_channel_mut(network, &target) {
             chan.new_users.append(&mut names);
         }
     }
 
-    pub fn set_names(&mut self, target: String) {
-        if let Some(chan) = self.channels.iter_mut().find(|x|x.name == target) {
-            for name in &mut chan.new_users {
-                if name.starts_with('@') || name.starts_with('+') { name.remove(0); }
+    pub fn set_names(&mut self, network: NetworkToken, target: String) {
+        if let Some(chan) = self.find_channel_mut(network, &target) {
+            // Keep what WHO and the notify capabilities told us about users still present.
+            for member in &mut chan.new_users {
+                if let Some(old) = chan.users.iter().find(|x| x.is(&member.nick)) {
+                    merge(member, old);
+                }
             }
+            chan.users.clear();
             chan.users.append(&mut chan.new_users);
-        } 
+        }
     }
 
-    pub fn add_name(&mut self, target: String, mut name: String) {
-        if let Some(chan) = self.channels.iter_mut().find(|x|x.name == target) {
-            if name.starts_with('@') || name.starts_with('+') { name.remove(0); }
-            chan.users.push(name);
+    pub fn add_name(&mut self, network: NetworkToken, target: String, member: Member) {
+        if let Some(chan) = self.find_channel_mut(network, &target) {
+            chan.users.retain(|x| !x.is(&member.nick));
+            chan.users.push(member);
         }
     }
 
-    pub fn remove_name(&mut self, target: String, mut name: String) {
-        if let Some(chan) = self.channels.iter_mut().find(|x|x.name == target) {
-            if name.starts_with('@') || name.starts_with('+') { name.remove(0); }
-            if let Some(pos) = chan.users.iter().position(|x|x == &name) {
-                chan.users.remove(pos);
+    pub fn remove_name(&mut self, network: NetworkToken, target: String, nick: String) {
+        if let Some(chan) = self.find_channel_mut(network, &target) {
+            chan.users.retain(|x| !x.is(&nick));
+        }
+    }
+
+    /// Fills in a member from a WHO reply, keeping the prefixes from NAMES if WHO had none.
+    pub fn update_member(&mut self, network: NetworkToken, target: String, mut member: Member) {
+        if let Some(chan) = self.find_channel_mut(network, &target) {
+            if let Some(old) = chan.users.iter_mut().find(|x| x.is(&member.nick)) {
+                if member.prefixes.is_empty() {
+                    member.prefixes = old.prefixes.clone();
+                }
+                member.account = member.account.take().or(old.account.take());
+                *old = member;
+            }
+        }
+    }
+
+    /// What we know of a user from the channels we share with them.
+    pub fn member(&self, network: NetworkToken, nick: &str) -> Option<Member> {
+        self.find_network(network).and_then(|n| {
+            n.channels.iter()
+                .flat_map(|chan| chan.users.iter())
+                .filter(|x| x.is(nick))
+                .max_by_key(|x| x.host.is_some())
+                .cloned()
+        })
+    }
+
+    /// Sets the away flag of a user in every channel of the network.
+    pub fn set_away(&mut self, network: NetworkToken, nick: &str, away: bool) {
+        self.for_member(network, nick, |m| m.away = away);
+    }
+
+    pub fn set_account(&mut self, network: NetworkToken, nick: &str, account: Option<String>) {
+        self.for_member(network, nick, |m| m.account = account.clone());
+    }
+
+    /// Applies a prefix mode like `+o` to a channel member.
+    pub fn set_member_mode(&mut self, network: NetworkToken, target: &str, nick: &str, mode: char, add: bool) {
+        if let Some(n) = self.find_network_mut(network) {
+            let modes = &n.modes;
+            if let Some(chan) = n.channels.iter_mut().find(|x| same_name(&x.name, target)) {
+                if let Some(member) = chan.users.iter_mut().find(|x| x.is(nick)) {
+                    member.set_mode(mode, add, modes);
+                }
             }
         }
     }
 
-    pub fn scroll_up(&mut self) {
-        self.message_pane.scroll_up();
+    /// The channel modes the network's server has told us of.
+    pub fn set_channel_modes(&mut self, network: NetworkToken, modes: ChannelModes) {
+        if let Some(n) = self.find_network_mut(network) {
+            n.modes = modes;
+        }
+    }
+
+    pub fn set_layout(&mut self, nick_width: i32, time_format: String, scroll_lines: i32) {
+        self.nick_list.set_nick_width(nick_width);
+        self.message_pane.set_layout(nick_width, time_format, scroll_lines);
+    }
+
+    /// Returns `true` when the active tab was already scrolled to its oldest message.
+    pub fn scroll_up(&mut self) -> bool {
+        self.message_pane.scroll_up()
     }
 
     pub fn scroll_down(&mut self) {
         self.message_pane.scroll_down();
     }
 
+    fn add_window(&mut self, network: NetworkToken, kind: WindowKind, name: String, tab: TabToken)
+            -> WindowToken {
+        self.next_window += 1;
+        let window = WindowToken(self.next_window);
+        self.tabs.insert(tab, window);
+        self.windows.insert(window, Window { token: window,
+                                             network: network,
+                                             kind: kind,
+                                             name: name,
+                                             tab: tab });
+        window
+    }
+
     fn remove_window(&mut self, wt: &WindowToken) {
-        {
-            let ref window = self.windows[wt];
+        if let Some(window) = self.windows.remove(wt) {
             self.tabs.remove(&window.tab);
-            if let Some(pos) = self.channels.iter().position(|x|x.window == *wt) {
-                self.channels.remove(pos);
+            self.tab_bar.remove_tab(window.tab);
+        }
+    }
+
+    fn find_network(&self, network: NetworkToken) -> Option<&Network> {
+        self.networks.iter().find(|x| x.token == network)
+    }
+
+    fn find_network_mut(&mut self, network: NetworkToken) -> Option<&mut Network> {
+        self.networks.iter_mut().find(|x| x.token == network)
+    }
+
+    fn find_channel_mut(&mut self, network: NetworkToken, channel: &str) -> Option<&mut Channel> {
+        self.find_network_mut(network)
+            .and_then(|n| n.channels.iter_mut().find(|x| same_name(&x.name, channel)))
+    }
+
+    fn for_member<F: FnMut(&mut Member)>(&mut self, network: NetworkToken, nick: &str, mut f: F) {
+        if let Some(n) = self.find_network_mut(network) {
+            for chan in &mut n.channels {
+                for member in chan.users.iter_mut().filter(|x| x.is(nick)) {
+                    f(member);
+                }
             }
         }
-        self.windows.remove(wt);
     }
 
-    fn find_channel(&self, tab: &TabToken) -> Option<WindowToken> {
+    fn find_window(&self, tab: &TabToken) -> Option<WindowToken> {
         match self.tabs.get(tab) {
             Some(wt) => {
                 self.windows.get(&wt).map(|x| x.token)
@@ -253,17 +446,113 @@ impl ChatWindows {
         }
     }
 
-    fn find_tab(&self, channel: &str) -> Option<WindowToken> {
-        match self.channels.iter().find(|x|x.name == channel) {
-            Some(c) => {
-                Some(c.window)
-            },
-            None => None
+    fn find_tab(&self, network: NetworkToken, channel: &str) -> Option<WindowToken> {
+        self.find_network(network)
+            .and_then(|n| n.channels.iter().find(|x| same_name(&x.name, channel)))
+            .map(|c| c.window)
+    }
+
+    /// Opens the channel browser for `network`, empty until `set_list_entries`.
+    pub fn open_list(&mut self, network: NetworkToken, filter: ListFilter) {
+        self.list = Some((network, ChannelList::new(filter)));
+    }
+
+    pub fn set_list_entries(&mut self, network: NetworkToken, entries: Vec<ListEntry>) {
+        if let Some((n, ref mut list)) = self.list {
+            if n == network {
+                list.set_entries(entries);
+            }
         }
     }
 
+    /// The open channel browser and the network it lists.
+    pub fn list_mut(&mut self) -> Option<(NetworkToken, &mut ChannelList)> {
+        self.list.as_mut().map(|x| (x.0, &mut x.1))
+    }
+
+    pub fn close_list(&mut self) {
+        if self.list.take().is_some() {
+            self.message_pane.set_dirty();
+        }
+    }
+
+    pub fn is_animating(&self) -> bool {
+        self.tab_bar.is_animating()
+    }
+
+    pub fn tick(&mut self) {
+        self.tab_bar.tick();
+    }
+
+    pub fn set_nick_list(&mut self, visible: bool) {
+        self.nick_list.set_visible(visible);
+    }
+
+    pub fn toggle_nick_list(&mut self) {
+        let visible = !self.nick_list.is_visible();
+        self.nick_list.set_visible(visible);
+    }
+
+    pub fn scroll_nick_list_up(&mut self) {
+        self.nick_list.scroll_up();
+    }
+
+    pub fn scroll_nick_list_down(&mut self) {
+        self.nick_list.scroll_down();
+    }
+
+    /// The users of the active tab when it's a channel, with the network's channel modes.
+    fn active_members(&self) -> Option<(Vec<Member>, ChannelModes)> {
+        let network = match self.active_network().and_then(|n| self.find_network(n)) {
+            Some(network) => network,
+            None => return None,
+        };
+        self.active_channel()
+            .filter(|name| name.starts_with('#') || name.starts_with('&'))
+            .and_then(|name| network.channels.iter().find(|x| same_name(&x.name, name)))
+            .map(|chan| (chan.users.clone(), network.modes.clone()))
+    }
+
     pub fn render(&mut self, window: &mut TermBuffer) {
-        self.message_pane.render(window, self.tab_bar.active_tab());
+        // The nick list needs the message pane to keep a readable width.
+        let members = if self.list.is_none() && self.nick_list.is_visible() &&
+                         window.width() >= self.nick_list.width() * 3 {
+            self.active_members()
+        } else {
+            None
+        };
+        let shown = members.is_some();
+        if let Some((members, modes)) = members {
+            let tab = self.tab_bar.active_tab();
+            if tab != self.nick_list_tab {
+                self.nick_list_tab = tab;
+                self.nick_list.reset_scroll();
+            }
+            self.nick_list.set_members(members, &modes);
+        }
+        self.message_pane.set_right_margin(if shown { self.nick_list.width() } else { 0 });
+
+        match self.list {
+            Some((_, ref mut list)) => list.render(window),
+            None => self.message_pane.render(window, self.tab_bar.active_tab()),
+        }
+        if shown {
+            self.nick_list.render(window);
+        }
         self.tab_bar.render(window);
     }
 }
+
+/// Copies into a member from NAMES what only the older entry knows.
+fn merge(member: &mut Member, old: &Member) {
+    if member.user.is_none() { member.user = old.user.clone(); }
+    if member.host.is_none() { member.host = old.host.clone(); }
+    if member.account.is_none() { member.account = old.account.clone(); }
+    if member.realname.is_none() { member.realname = old.realname.clone(); }
+    member.away = old.away;
+}
+
+/// Whether two channel names are the same, which IRC compares case-insensitively.
+fn same_name(a: &str, b: &str) -> bool {
+    a.to_lowercase() == b.to_lowercase()
+}
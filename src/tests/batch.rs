@@ -0,0 +1,47 @@
+use irc::{Capabilities, ClientEvent, CommandType};
+use tests::mock::MockServer;
+
+#[test]
+fn batch_nested() {
+    let mut server = MockServer::new();
+    let client = server.client(Capabilities::new());
+
+    server.send(":irc.test BATCH +outer example.com/wrapper");
+    server.send("@batch=outer :irc.test BATCH +split netsplit irc.hub other.host");
+    server.send("@batch=split :aaa!u@h QUIT :irc.hub other.host");
+    server.send("@batch=split :bbb!u@h QUIT :irc.hub other.host");
+    server.send("@batch=outer :ccc!u@h PRIVMSG #mass :after");
+    server.send(":irc.test BATCH -split");
+    server.send(":irc.test BATCH -outer");
+    server.send("@batch=unknown :ddd!u@h PRIVMSG #mass :loose");
+
+    let (event, client) = server.event(client);
+    match event {
+        ClientEvent::Batch { ref kind, ref messages, .. } => {
+            assert_eq!(kind, "example.com/wrapper");
+            assert_eq!(messages.len(), 2);
+            match messages[0] {
+                ClientEvent::ChannelMessage(_, _, ref msg, _) => assert_eq!(msg, "after"),
+                _ => panic!("Expected the wrapper's own message first"),
+            }
+            match messages[1] {
+                ClientEvent::Batch { ref kind, ref params, ref messages } => {
+                    assert_eq!(kind, "netsplit");
+                    assert_eq!(params, &vec!["irc.hub".to_string(), "other.host".to_string()]);
+                    assert_eq!(messages.len(), 2);
+                    match messages[0] {
+                        ClientEvent::Command(ref cmd) => assert_eq!(cmd.command, CommandType::Quit),
+                        _ => panic!("Expected a QUIT in the netsplit"),
+                    }
+                },
+                _ => panic!("Expected the nested netsplit"),
+            }
+        },
+        _ => panic!("Expected the outer batch"),
+    }
+
+    match server.event(client).0 {
+        ClientEvent::ChannelMessage(_, _, ref msg, _) => assert_eq!(msg, "loose"),
+        _ => panic!("Expected a message outside any open batch to pass through"),
+    }
+}
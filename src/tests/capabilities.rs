@@ -0,0 +1,38 @@
+use irc::{Capabilities, Command, CommandParser};
+
+fn parse(line: &str) -> Command {
+    CommandParser::new().parse(&format!("{}\r\n", line).into_bytes())
+}
+
+fn lines(commands: Vec<Command>) -> Vec<String> {
+    commands.iter().map(|x| x.to_string().trim_right().to_string()).collect()
+}
+
+#[test]
+fn caps_sasl_plain() {
+    let mut caps = Capabilities::new()
+        .request("multi-prefix")
+        .sasl("mass".to_string(), "secret".to_string());
+
+    assert_eq!(lines(caps.start()), vec!["CAP LS :302"]);
+    assert!(caps.handle(&parse(":irc.test CAP * LS * :multi-prefix sasl=PLAIN")).is_empty());
+    assert_eq!(lines(caps.handle(&parse(":irc.test CAP * LS :away-notify"))),
+               vec!["CAP REQ :multi-prefix sasl"]);
+    assert_eq!(lines(caps.handle(&parse(":irc.test CAP * ACK :multi-prefix sasl"))),
+               vec!["AUTHENTICATE :PLAIN"]);
+    assert!(caps.is_enabled("multi-prefix"));
+    assert_eq!(lines(caps.handle(&parse("AUTHENTICATE +"))),
+               vec!["AUTHENTICATE :bWFzcwBtYXNzAHNlY3JldA=="]);
+    assert_eq!(lines(caps.handle(&parse(":irc.test 903 * :SASL authentication successful"))),
+               vec!["CAP :END"]);
+}
+
+#[test]
+fn caps_unsupported() {
+    let mut caps = Capabilities::new().request("echo-message");
+    caps.start();
+    assert_eq!(lines(caps.handle(&parse(":irc.test CAP * LS :sasl"))), vec!["CAP :END"]);
+    assert!(!caps.is_enabled("echo-message"));
+
+    assert!(Capabilities::new().start().is_empty());
+}
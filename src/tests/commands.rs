@@ -0,0 +1,117 @@
+use std::collections::BTreeMap;
+
+use irc::{Arg, Args, ChannelModes, CommandRegistry, CommandSpec, Member, ParseError, UserCommand};
+
+fn sent(input: &str, active: Option<&str>) -> String {
+    let cmd = CommandRegistry::new().parse(input, active).ok().expect("Parse failed");
+    cmd.to_command().unwrap().to_string().trim_right().to_string()
+}
+
+#[test]
+fn commands_channel_defaults() {
+    let active = Some("#mass");
+    assert_eq!(sent("/kick nick go away", active), "KICK #mass nick :go away");
+    assert_eq!(sent("/kick #effect nick", active), "KICK #effect :nick");
+    assert_eq!(sent("/ban *!*@host", active), "MODE #mass +b :*!*@host");
+    assert_eq!(sent("/unban #effect nick", active), "MODE #effect -b :nick");
+    assert_eq!(sent("/mode +mi", active), "MODE #mass :+mi");
+    assert_eq!(sent("/mode nick +i", active), "MODE nick :+i");
+    assert_eq!(sent("/op a b", active), "MODE #mass +oo a :b");
+    assert_eq!(sent("/devoice #effect a", active), "MODE #effect -v :a");
+    assert_eq!(sent("/invite nick", active), "INVITE nick :#mass");
+    assert_eq!(sent("/names", active), "NAMES :#mass");
+    assert_eq!(sent("/who", active), "WHO :#mass");
+    assert_eq!(sent("/notice nick hello there", active), "NOTICE nick :hello there");
+    assert_eq!(sent("/ison a b", None), "ISON a :b");
+    assert_eq!(sent("/oper name secret", None), "OPER name :secret");
+
+    let registry = CommandRegistry::new();
+    assert!(registry.parse("/kick nick", None).is_err());
+    assert!(registry.parse("/op", active).is_err());
+}
+
+#[test]
+fn commands_ban_mask() {
+    assert_eq!(Member::parse("nick!user@host.example", &ChannelModes::new()).ban_mask(), "*!*@host.example");
+    assert_eq!(Member::new("nick".to_string()).ban_mask(), "nick!*@*");
+}
+
+#[test]
+fn commands_quote() {
+    assert_eq!(sent("/quote KNOCK #mass :let me in", None), "KNOCK #mass :let me in");
+    assert_eq!(sent("/raw PRIVMSG #mass :hi", None), "PRIVMSG #mass :hi");
+    match CommandRegistry::new().parse("/quote :::", None) {
+        Err(ParseError::InvalidMessage) => {},
+        _ => panic!("Expected a parse failure"),
+    }
+}
+
+#[test]
+fn commands_registry() {
+    let mut registry = CommandRegistry::new();
+    assert_eq!(registry.find("/k").map(|x| x.name()), Some("kick"));
+    assert_eq!(registry.find("kick").unwrap().usage(), "/kick [channel] <nick> [reason...]");
+
+    match registry.parse("/kick", Some("#mass")) {
+        Err(e) => assert_eq!(e.to_string(), "Usage: /kick [channel] <nick> [reason...]"),
+        Ok(_) => panic!("Expected a usage error"),
+    }
+    match registry.parse("/whois a b", None) {
+        Err(ParseError::Usage(_)) => {},
+        _ => panic!("Expected a usage error"),
+    }
+    match registry.parse("/frobnicate", None) {
+        Err(ParseError::UnknownCommand(name)) => assert_eq!(name, "frobnicate"),
+        _ => panic!("Expected an unknown command"),
+    }
+
+    registry.register(CommandSpec::new("slap", "Slaps someone", |a: &Args| {
+        Ok(UserCommand::PrivMsg(a.active.clone(), format!("slaps {}", a.get(0))))
+    }).arg(Arg::Required("nick".to_string())));
+    let cmd = registry.parse("/slap nick", Some("#mass")).ok().unwrap();
+    assert_eq!(cmd.to_command().unwrap().to_string(), "PRIVMSG #mass :slaps nick\r\n");
+}
+
+#[test]
+fn commands_aliases() {
+    let mut registry = CommandRegistry::new();
+    registry.set_alias("kb", "/kickban $1 Bye $2-; /msg ChanServ akick $chan add $1");
+    registry.set_alias("join", "/join $1; /msg $nick joined $1");
+    registry.set_alias("rejoin", "part; j $chan");
+    registry.set_alias("a", "/b $1");
+    registry.set_alias("b", "/a $1");
+
+    assert_eq!(registry.expand("/kb nick you fool", "me", Some("#mass")).ok().unwrap(),
+               vec!["/kickban nick Bye you fool", "/msg ChanServ akick #mass add nick"]);
+    // An alias using its own name runs the command it shadows.
+    assert_eq!(registry.expand("/join #effect", "me", None).ok().unwrap(),
+               vec!["/join #effect", "/msg me joined #effect"]);
+    assert_eq!(registry.expand("/rejoin", "me", Some("#mass")).ok().unwrap(), vec!["/part", "/j #mass"]);
+    assert_eq!(registry.expand("hello $1", "me", Some("#mass")).ok().unwrap(), vec!["hello $1"]);
+    match registry.expand("/a x", "me", None) {
+        Err(ParseError::AliasLoop(name)) => assert_eq!(name, "a"),
+        _ => panic!("Expected a loop"),
+    }
+
+    assert!(registry.remove_alias("/kb"));
+    assert_eq!(registry.expand("/kb nick", "me", None).ok().unwrap(), vec!["/kb nick"]);
+}
+
+#[test]
+fn commands_config_aliases() {
+    let mut registry = CommandRegistry::new();
+    let mut config = BTreeMap::new();
+    config.insert("gone".to_string(), "/part".to_string());
+    config.insert("mine".to_string(), "/quit".to_string());
+    registry.set_config_aliases(&config);
+    registry.set_alias("mine", "/away");
+    registry.set_alias("typed", "/back");
+
+    // A reload drops aliases taken out of the config, but not those from `/alias`.
+    config.remove("gone");
+    config.insert("new".to_string(), "/names".to_string());
+    registry.set_config_aliases(&config);
+    let names: Vec<&str> = registry.aliases().keys().map(|x| &**x).collect();
+    assert_eq!(names, vec!["mine", "new", "typed"]);
+    assert_eq!(registry.aliases()["mine"], "/away");
+}
@@ -0,0 +1,114 @@
+use config::{Config, Overrides, AutoJoin, IdentifyMethod};
+
+const EXAMPLE: &'static str = r##"
+# Sample configuration
+[identity]
+nicks = ["mass", "mass_", "mass__"]
+realname = "Nick Massey"
+
+[ui]
+nick_width = 16
+timestamp_format = "[%H:%M:%S]"
+show_motd = false
+nick_list = false
+
+[aliases]
+kb = "/kickban $1 Bye; /msg ChanServ akick $chan add $1"
+
+[[network]]
+name = "libera"
+host = "irc.libera.chat"
+tls = true
+autojoin = [
+    "#rust",
+    { channel = "#secret", key = "hunter2" }, # keyed channel
+]
+commands = ["/msg NickServ help"]
+
+[network.sasl]
+username = "mass"
+password = "p#ss"
+
+[[network]]
+host = "127.0.0.1"
+port = 6668
+autoconnect = false
+"##;
+
+#[test]
+fn config_syntax() {
+    let config = Config::parse("ui.nick_width = 12\nui.timestamp_format = \"\"\"%H:%M\"\"\"\n[identity]\nmode = 0").unwrap();
+    assert_eq!(config.ui.nick_width, 12);
+    assert_eq!(config.ui.timestamp_format, "%H:%M");
+    assert_eq!(config.identity.mode, 0);
+
+    let error = Config::parse("[ui]\nnick_width = 1\nscroll_lines = ").unwrap_err().to_string();
+    assert!(error.contains("line 3"), "{}", error);
+    assert!(Config::parse("[table").is_err());
+    assert!(Config::parse("[ui]\nnick_width = 1.5").is_err());
+    assert!(Config::parse("[ui]\nnick_width = -4").is_err());
+    assert!(Config::parse("[ui]\nscroll_lines = 4294967297").is_err());
+    assert!(Config::parse("[identity]\nmode = 256").is_err());
+}
+
+#[test]
+fn config_full() {
+    let config = Config::parse(EXAMPLE).unwrap();
+    assert_eq!(config.identity.nicks, vec!["mass", "mass_", "mass__"]);
+    assert_eq!(config.ui.nick_width, 16);
+    assert_eq!(config.ui.scroll_lines, 5);
+    assert_eq!(config.ui.timestamp_format, "[%H:%M:%S]");
+    assert!(!config.ui.show_motd);
+    assert!(!config.ui.nick_list);
+    assert_eq!(config.aliases["kb"], "/kickban $1 Bye; /msg ChanServ akick $chan add $1");
+    assert_eq!(config.networks.len(), 2);
+
+    let libera = &config.networks[0];
+    assert_eq!(libera.name, "libera");
+    assert_eq!(libera.address.port, 6697);
+    assert!(libera.address.tls);
+    assert_eq!(libera.autojoin[1], AutoJoin { channel: "#secret".to_string(), key: Some("hunter2".to_string()) });
+    assert_eq!(libera.sasl.as_ref().unwrap().password, "p#ss");
+    assert_eq!(libera.username(&config.identity), "mass");
+
+    let mut libera = libera.clone();
+    libera.add_autojoin("#Rust".to_string(), None);
+    libera.add_autojoin("#Rust".to_string(), None);
+    assert_eq!(libera.autojoin.len(), 2);
+    assert_eq!(libera.autojoin[1].channel, "#Rust");
+
+    let local = &config.networks[1];
+    assert_eq!(local.name, "127.0.0.1");
+    assert_eq!(local.address.port, 6668);
+    assert!(!local.autoconnect);
+}
+
+#[test]
+fn config_overrides() {
+    let mut config = Config::parse(EXAMPLE).unwrap();
+    config.apply(&Overrides {
+        nick: Some("other".to_string()),
+        server: Some("127.0.0.1".to_string()),
+        .. Overrides::default()
+    });
+    assert_eq!(config.identity.nicks, vec!["other"]);
+    assert_eq!(config.networks[0].address.port, 6668);
+    assert!(config.networks[0].autoconnect);
+    assert!(!config.networks[1].autoconnect);
+
+    let mut config = Config::default();
+    config.apply(&Overrides::default());
+    assert_eq!(config.networks[0].address.host, "127.0.0.1");
+}
+
+#[test]
+fn config_identify() {
+    let config = Config::parse("[[network]]\nhost = 'irc.test'\n[network.identify]\npassword = 'pw'\nwait = false").unwrap();
+    let identify = config.networks[0].identify.as_ref().unwrap();
+    assert_eq!(identify.method, IdentifyMethod::NickServ { account: None, password: "pw".to_string() });
+    assert_eq!(identify.service, "NickServ");
+    assert!(identify.failure_patterns.iter().any(|x| x == "Invalid password"));
+    assert!(!identify.wait);
+
+    assert!(Config::parse("[[network]]\nhost = 'irc.test'\n[network.identify]\nwait = true").is_err());
+}
@@ -0,0 +1,87 @@
+use irc::{Capabilities, ClientEvent, UserCommand};
+use tests::mock::MockServer;
+
+#[test]
+fn echo_message() {
+    let mut server = MockServer::new();
+    let client = server.client(Capabilities::new().request("echo-message"));
+    server.expect("CAP LS :302");
+    server.send(":irc.test CAP * LS :echo-message");
+    server.expect("CAP REQ :echo-message");
+    server.send(":irc.test CAP * ACK :echo-message");
+    server.expect("CAP :END");
+
+    let client = server.user(client, UserCommand::PrivMsg("#mass".to_string(), "hello".to_string()));
+    server.expect("PRIVMSG #mass :hello");
+    server.expect_nothing();
+
+    server.send("@time=2017-01-02T03:04:05.000Z :mass!~mass@host PRIVMSG #mass :hello");
+    let mut client = client;
+    loop {
+        let (event, next) = server.event(client);
+        client = next;
+        match event {
+            ClientEvent::Capabilities(_) => continue,
+            ClientEvent::ChannelMessage(ref target, Some(ref sender), ref msg, ref meta) => {
+                assert_eq!((&**target, &**sender, &**msg), ("#mass", "mass", "hello"));
+                assert_eq!(meta.time.to_string(), "2017-01-02T03:04:05.000Z");
+                break;
+            },
+            _ => panic!("Expected the server's echo"),
+        }
+    }
+}
+
+#[test]
+fn echo_rejected() {
+    let mut server = MockServer::new();
+    let client = server.client(Capabilities::new());
+    let client = server.user(client, UserCommand::PrivMsg("#mass".to_string(), "one".to_string()));
+    let client = server.user(client, UserCommand::PrivMsg("#moderated".to_string(), "two".to_string()));
+    server.expect("PRIVMSG #mass :one");
+    server.expect("PRIVMSG #moderated :two");
+    server.expect("PING :echo-1");
+
+    server.send(":irc.test 404 mass #moderated :Cannot send to channel");
+    server.send(":irc.test PONG irc.test :echo-1");
+
+    let mut client = client;
+    let mut settled = Vec::new();
+    while settled.len() < 2 {
+        let (event, next) = server.event(client);
+        client = next;
+        match event {
+            ClientEvent::MessageConfirmed(id, _) => settled.push((id, true)),
+            ClientEvent::MessageRejected(id, _, _) => settled.push((id, false)),
+            _ => {},
+        }
+    }
+    assert_eq!(settled, vec![(2, false), (1, true)]);
+    server.expect_nothing();
+}
+
+#[test]
+fn echo_unrelated_error() {
+    let mut server = MockServer::new();
+    let client = server.client(Capabilities::new());
+    let client = server.user(client, UserCommand::WhoIs("bob".to_string()));
+    let client = server.user(client, UserCommand::PrivMsg("bob".to_string(), "hi".to_string()));
+    server.expect("WHOIS :bob");
+    server.expect("PRIVMSG bob :hi");
+    server.expect("PING :echo-1");
+
+    // The error answers the WHOIS, which was sent first.
+    server.send(":irc.test 401 mass bob :No such nick");
+    server.send(":irc.test PONG irc.test :echo-1");
+
+    let mut client = client;
+    loop {
+        let (event, next) = server.event(client);
+        client = next;
+        match event {
+            ClientEvent::MessageConfirmed(1, _) => break,
+            ClientEvent::MessageRejected(..) => panic!("Rejected by the WHOIS error"),
+            _ => {},
+        }
+    }
+}
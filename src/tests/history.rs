@@ -0,0 +1,55 @@
+use irc::{Capabilities, ClientEvent, Timestamp, UserCommand};
+use tests::mock::MockServer;
+
+#[test]
+fn history_batch() {
+    let mut server = MockServer::new();
+    let client = server.client(Capabilities::new().request("batch").request("draft/chathistory"));
+    server.expect("CAP LS :302");
+    server.send(":irc.test CAP * LS :batch draft/chathistory");
+    server.expect("CAP REQ :batch draft/chathistory");
+    server.send(":irc.test CAP * ACK :batch draft/chathistory");
+    server.expect("CAP :END");
+
+    let client = match server.event(client) {
+        (ClientEvent::Capabilities(ref caps), client) if caps.len() == 2 => client,
+        _ => panic!("Expected the enabled capabilities"),
+    };
+
+    let before = Timestamp::parse("2017-01-02T03:04:05.000Z").unwrap();
+    let client = server.user(client, UserCommand::HistoryBefore("#mass".to_string(), before, 2));
+    server.expect("CHATHISTORY BEFORE #mass timestamp=2017-01-02T03:04:05.000Z :2");
+
+    server.send(":irc.test BATCH +h1 chathistory #mass");
+    server.send("@batch=h1;msgid=a;time=2017-01-02T03:00:00.000Z :one!u@h PRIVMSG #mass :first");
+    server.send("@batch=h1;msgid=b;time=2017-01-02T03:01:00.000Z :two!u@h NOTICE #mass :second");
+    server.send(":irc.test BATCH -h1");
+    server.send(":two!u@h PRIVMSG #mass :live");
+
+    let (event, client) = server.event(client);
+    match event {
+        ClientEvent::Batch { ref kind, ref params, messages: ref events } => {
+            assert_eq!(kind, "chathistory");
+            assert_eq!(params, &vec!["#mass".to_string()]);
+            assert_eq!(events.len(), 2);
+            match events[0] {
+                ClientEvent::ChannelMessage(_, Some(ref sender), ref msg, ref meta) => {
+                    assert_eq!((&**sender, &**msg), ("one", "first"));
+                    assert_eq!(meta.msgid, Some("a".to_string()));
+                    assert_eq!(meta.time.to_string(), "2017-01-02T03:00:00.000Z");
+                },
+                _ => panic!("Expected the first message"),
+            }
+            match events[1] {
+                ClientEvent::ChannelNotice(_, _, ref msg, _) => assert_eq!(msg, "second"),
+                _ => panic!("Expected the notice"),
+            }
+        },
+        _ => panic!("Expected the history batch as one event"),
+    }
+
+    match server.event(client).0 {
+        ClientEvent::ChannelMessage(_, _, ref msg, ref meta) if msg == "live" => assert_eq!(meta.msgid, None),
+        _ => panic!("Expected the live message after the batch"),
+    }
+}
@@ -0,0 +1,44 @@
+use irc::{Capabilities, ClientEvent, ListEntry, ListFilter, UserCommand};
+use tests::mock::MockServer;
+
+fn entry(channel: &str, users: u32) -> ListEntry {
+    ListEntry { channel: channel.to_string(), users: users, topic: "".to_string() }
+}
+
+#[test]
+fn list_filter() {
+    let filter = ListFilter::parse(">10 rust !*-offtopic");
+    assert!(filter.matches(&entry("#Rust", 11)));
+    assert!(!filter.matches(&entry("#rust", 10)));
+    assert!(!filter.matches(&entry("#rust-offtopic", 50)));
+    assert!(!filter.matches(&entry("#python", 50)));
+
+    assert_eq!(filter.elist(""), None);
+    assert_eq!(filter.elist("U"), Some(">10".to_string()));
+    assert_eq!(filter.elist("MNU"), Some(">10,*rust*,!*-offtopic".to_string()));
+    assert_eq!(ListFilter::parse("a b").elist("M"), None);
+    assert!(!ListFilter::parse(">4294967295").matches(&entry("#rust", 50)));
+}
+
+#[test]
+fn list_replies() {
+    let mut server = MockServer::new();
+    let client = server.client(Capabilities::new());
+    let client = server.user(client, UserCommand::List(Some(">1".to_string())));
+    server.expect("LIST :>1");
+
+    server.send(":irc.test 321 me Channel :Users  Name");
+    server.send(":irc.test 322 me #mass 12 :Mass channel");
+    server.send(":irc.test 322 me #effect 3 :");
+    server.send(":irc.test 323 me :End of /LIST");
+
+    match server.event(client).0 {
+        ClientEvent::ChannelList(entries) => {
+            assert_eq!(entries.len(), 2);
+            assert_eq!(entries[0], ListEntry { channel: "#mass".to_string(), users: 12,
+                                               topic: "Mass channel".to_string() });
+            assert_eq!(entries[1].users, 3);
+        },
+        _ => panic!("Expected the channel list"),
+    }
+}
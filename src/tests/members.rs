@@ -0,0 +1,90 @@
+use irc::{Capabilities, ChannelModes, ClientEvent, Member};
+use tests::mock::MockServer;
+
+#[test]
+fn members_names() {
+    let modes = ChannelModes::new();
+    let member = Member::parse("@+nick!user@example.com", &modes);
+    assert_eq!(member.nick, "nick");
+    assert_eq!(member.prefixes, "@+");
+    assert_eq!(member.hostmask(), "nick!user@example.com");
+
+    let mut plain = Member::parse("other", &modes);
+    assert_eq!(plain.hostmask(), "other!*@*");
+    assert!(plain.rank(&modes) > member.rank(&modes));
+    plain.set_mode('v', true, &modes);
+    plain.set_mode('o', true, &modes);
+    assert_eq!(plain.prefixes, "@+");
+    plain.set_mode('o', false, &modes);
+    assert_eq!(plain.prefix(), Some('+'));
+}
+
+#[test]
+fn members_isupport() {
+    let mut server = MockServer::new();
+    let client = server.client(Capabilities::new());
+
+    // Here +q is a quiet list rather than an owner prefix.
+    server.send(":irc.test 005 me PREFIX=(ov)@+ CHANMODES=bqeI,k,l,imnpst :are supported by this server");
+    server.send(":irc.test 353 me = #chan :@op ~odd");
+    server.send(":op!user@host MODE #chan +qo bad!*@* odd");
+
+    let (event, client) = server.event(client);
+    match event {
+        ClientEvent::Names(_, ref names) => {
+            let nicks: Vec<&str> = names.iter().map(|x| &*x.nick).collect();
+            assert_eq!(nicks, vec!["op", "~odd"]);
+        },
+        _ => panic!("Expected names"),
+    }
+    match server.event(client).0 {
+        ClientEvent::ChannelMode(_, _, ref changes, _) => {
+            assert_eq!(changes, &vec![(true, 'q', Some("bad!*@*".to_string())),
+                                      (true, 'o', Some("odd".to_string()))]);
+        },
+        _ => panic!("Expected mode changes"),
+    }
+
+    let mut modes = ChannelModes::new();
+    modes.update("PREFIX=(ov)@+");
+    let mut member = Member::new("bad".to_string());
+    assert!(!member.set_mode('q', true, &modes));
+    assert!(member.set_mode('o', true, &modes));
+    assert_eq!(member.prefixes, "@");
+    modes.update("PREFIX=(ov)@");
+    assert!(modes.has_param('q', true));
+    assert_eq!(modes, ChannelModes::new());
+}
+
+#[test]
+fn members_notify() {
+    let mut server = MockServer::new();
+    let client = server.client(Capabilities::new());
+
+    server.send(":nick!user@host JOIN #chan account :Real Name");
+    server.send(":nick!user@host AWAY :Gone fishing");
+    server.send(":nick!user@host ACCOUNT *");
+
+    let (event, client) = server.event(client);
+    match event {
+        ClientEvent::JoinChannel(ref channel, ref member) => {
+            assert_eq!(channel, "#chan");
+            assert_eq!(member.hostmask(), "nick!user@host");
+            assert_eq!(member.account, Some("account".to_string()));
+            assert_eq!(member.realname, Some("Real Name".to_string()));
+        },
+        _ => panic!("Expected an extended join"),
+    }
+    let (event, client) = server.event(client);
+    match event {
+        ClientEvent::Away(ref nick, ref msg) => {
+            assert_eq!(nick, "nick");
+            assert_eq!(msg, &Some("Gone fishing".to_string()));
+        },
+        _ => panic!("Expected an away notification"),
+    }
+    match server.event(client).0 {
+        ClientEvent::Account(ref nick, None) => assert_eq!(nick, "nick"),
+        _ => panic!("Expected a logout"),
+    }
+}
@@ -0,0 +1,236 @@
+use irc::{Capabilities, Client, ClientEvent, Command, Response, UserCommand};
+use tokio_core::reactor::{Core, Timeout};
+use futures::{Async, Future, Poll, Sink, Stream};
+use futures::task::{self, Task};
+
+use std::collections::VecDeque;
+use std::io::{self, Read, Write};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+struct Shared {
+    to_client: VecDeque<u8>,
+    from_client: Vec<u8>,
+    reader: Option<Task>,
+    writer: Option<Task>,
+    closed: bool,
+}
+
+/// A scripted server on the other end of an in-memory stream. Lines are injected with
+/// `send`, and each line the client writes is checked in order with `expect`.
+pub struct MockServer {
+    core: Core,
+    shared: Arc<Mutex<Shared>>,
+}
+
+impl MockServer {
+    pub fn new() -> MockServer {
+        MockServer {
+            core: Core::new().unwrap(),
+            shared: Arc::new(Mutex::new(Shared {
+                to_client: VecDeque::new(),
+                from_client: Vec::new(),
+                reader: None,
+                writer: None,
+                closed: false,
+            })),
+        }
+    }
+
+    /// Starts a client talking to this server.
+    pub fn client(&self, capabilities: Capabilities) -> Client {
+        Client::from_pair(MockReader(self.shared.clone()), MockWriter(self.shared.clone()),
+                          capabilities, &self.core.handle())
+    }
+
+    /// Sends `line` to the client, the line ending is added.
+    pub fn send(&mut self, line: &str) {
+        let mut shared = self.shared.lock().unwrap();
+        shared.to_client.extend(line.as_bytes());
+        shared.to_client.extend(b"\r\n");
+        if let Some(task) = shared.reader.take() {
+            task.unpark();
+        }
+    }
+
+    /// Closes the stream, the client reads end of file once the injected lines are read.
+    pub fn close(&mut self) {
+        let mut shared = self.shared.lock().unwrap();
+        shared.closed = true;
+        if let Some(task) = shared.reader.take() {
+            task.unpark();
+        }
+    }
+
+    /// Runs the client until it writes a line, and asserts it is `expected`.
+    pub fn expect(&mut self, expected: &str) {
+        let line = self.run(NextLine(self.shared.clone()), expected);
+        assert_eq!(line, expected);
+    }
+
+    /// Asserts the client has nothing more to write once every pending event is handled.
+    pub fn expect_nothing(&mut self) {
+        if let Some(line) = self.within(NextLine(self.shared.clone()), Duration::from_millis(50)) {
+            panic!("Unexpected line {:?}", line);
+        }
+    }
+
+    /// Sends `cmd` from the user's side of the client.
+    pub fn user(&mut self, client: Client, cmd: UserCommand) -> Client {
+        self.run(client.send(cmd), "the command to be queued")
+    }
+
+    /// Runs the client until the replies to a request are complete.
+    pub fn response(&mut self, response: Response) -> Vec<Command> {
+        self.run(response, "the response")
+    }
+
+    /// Runs the client until it produces its next event, skipping commands passed through
+    /// as `ClientEvent::Command`.
+    pub fn event(&mut self, client: Client) -> (ClientEvent, Client) {
+        let mut client = client;
+        loop {
+            match self.run(client.into_future().map_err(|_| io::Error::new(io::ErrorKind::Other, "")),
+                           "an event") {
+                (Some(ClientEvent::Command(_)), next) => client = next,
+                (Some(event), client) => return (event, client),
+                (None, _) => panic!("Client closed"),
+            }
+        }
+    }
+
+    fn run<F, T, E>(&mut self, f: F, waiting_for: &str) -> T where F: Future<Item=T, Error=E> {
+        match self.within(f, Duration::from_secs(1)) {
+            Some(x) => x,
+            None => panic!("Timed out waiting for {}", waiting_for),
+        }
+    }
+
+    fn within<F, T, E>(&mut self, f: F, timeout: Duration) -> Option<T>
+            where F: Future<Item=T, Error=E> {
+        let timeout = Timeout::new(timeout, &self.core.handle()).unwrap()
+            .map(|_| None)
+            .map_err(|_| ());
+        match self.core.run(f.map(Some).map_err(|_| ()).select(timeout)) {
+            Ok((x, _)) => x,
+            Err(_) => panic!("Mock client failed"),
+        }
+    }
+}
+
+struct MockReader(Arc<Mutex<Shared>>);
+
+impl Read for MockReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut shared = self.0.lock().unwrap();
+        if shared.to_client.is_empty() {
+            if shared.closed {
+                return Ok(0);
+            }
+            shared.reader = Some(task::park());
+            return Err(io::Error::new(io::ErrorKind::WouldBlock, "No data"));
+        }
+        let n = ::std::cmp::min(buf.len(), shared.to_client.len());
+        for (i, b) in shared.to_client.drain(..n).enumerate() {
+            buf[i] = b;
+        }
+        Ok(n)
+    }
+}
+
+struct MockWriter(Arc<Mutex<Shared>>);
+
+impl Write for MockWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut shared = self.0.lock().unwrap();
+        shared.from_client.extend(buf);
+        if let Some(task) = shared.writer.take() {
+            task.unpark();
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Resolves to the next complete line written by the client, without its line ending.
+struct NextLine(Arc<Mutex<Shared>>);
+
+impl Future for NextLine {
+    type Item = String;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<String, io::Error> {
+        let mut shared = self.0.lock().unwrap();
+        match shared.from_client.iter().position(|x| *x == b'\n') {
+            Some(i) => {
+                let line: Vec<u8> = shared.from_client.drain(..i + 1).collect();
+                let line = String::from_utf8_lossy(&line).trim_right_matches(&['\r', '\n'][..]).to_string();
+                Ok(Async::Ready(line))
+            },
+            None => {
+                shared.writer = Some(task::park());
+                Ok(Async::NotReady)
+            }
+        }
+    }
+}
+
+#[test]
+fn mock_registration() {
+    let mut server = MockServer::new();
+    let client = server.client(Capabilities::new().request("multi-prefix"));
+    server.expect("CAP LS :302");
+
+    let client = server.user(client, UserCommand::Nick("mass".to_string()));
+    let client = server.user(client, UserCommand::User("mass".to_string(), "8".to_string(), "Nick Massey".to_string()));
+    server.expect("NICK :mass");
+    server.expect("USER mass 8 * :Nick Massey");
+
+    server.send(":irc.test CAP * LS :multi-prefix sasl");
+    server.expect("CAP REQ :multi-prefix");
+    server.send(":irc.test CAP * ACK :multi-prefix");
+    server.expect("CAP :END");
+
+    server.send(":irc.test 001 mass :Welcome");
+    let mut client = client;
+    loop {
+        let (event, next) = server.event(client);
+        client = next;
+        if let ClientEvent::Connected = event { break; }
+    }
+    server.expect_nothing();
+}
+
+#[test]
+fn mock_pong() {
+    let mut server = MockServer::new();
+    let client = server.client(Capabilities::new());
+    server.send("PING :irc.test");
+    server.expect("PONG :irc.test");
+    server.send(":irc.test PING :12345");
+    server.expect("PONG :12345");
+    drop(client);
+}
+
+#[test]
+fn mock_echo() {
+    let mut server = MockServer::new();
+    let client = server.client(Capabilities::new());
+    let client = server.user(client, UserCommand::PrivMsg("#mass".to_string(), "hello".to_string()));
+    server.expect("PRIVMSG #mass :hello");
+    server.expect("PING :echo-1");
+
+    let client = match server.event(client) {
+        (ClientEvent::PendingMessage(1, ref target, ref msg, _), client) if target == "#mass" && msg == "hello" => client,
+        _ => panic!("Expected the message to be echoed locally"),
+    };
+
+    server.send(":irc.test PONG irc.test :echo-1");
+    match server.event(client).0 {
+        ClientEvent::MessageConfirmed(1, ref target) if target == "#mass" => {},
+        _ => panic!("Expected the message to be confirmed"),
+    }
+}
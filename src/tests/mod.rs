@@ -1 +1,18 @@
 mod parser;
+mod config;
+mod capabilities;
+mod url;
+mod proxy;
+mod transport;
+mod mock;
+mod echo;
+mod timestamp;
+mod history;
+mod batch;
+mod members;
+mod response;
+mod whois;
+mod list;
+mod motd;
+mod numeric;
+mod commands;
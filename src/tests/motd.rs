@@ -0,0 +1,42 @@
+use irc::{Capabilities, ClientEvent};
+use term::buffer::Color;
+use term::term_string::TermString;
+use tests::mock::MockServer;
+
+#[test]
+fn motd_collected() {
+    let mut server = MockServer::new();
+    let client = server.client(Capabilities::new());
+
+    server.send(":irc.test 375 me :- irc.test Message of the Day -");
+    server.send(":irc.test 372 me :- Welcome to \x0304irc.test\x03");
+    server.send(":irc.test 372 me :-");
+    server.send(":irc.test 372 me :- Be nice");
+    server.send(":irc.test 376 me :End of /MOTD command.");
+
+    let (event, client) = server.event(client);
+    match event {
+        ClientEvent::Motd(lines) => {
+            assert_eq!(lines, vec!["Welcome to \x0304irc.test\x03".to_string(), "".to_string(),
+                                   "Be nice".to_string()]);
+        },
+        _ => panic!("Expected the MOTD"),
+    }
+
+    server.send(":irc.test 422 me :MOTD File is missing");
+    match server.event(client).0 {
+        ClientEvent::Motd(lines) => assert!(lines.is_empty()),
+        _ => panic!("Expected an empty MOTD"),
+    }
+}
+
+#[test]
+fn motd_formatting() {
+    let text = TermString::from_irc("a\x0304,02b\x02c\x0Fd\x16e", Color::White, Color::Black);
+    let glyphs: Vec<String> = (0..text.len())
+        .map(|i| text.get(i).unwrap())
+        .map(|g| format!("{} {:?} {:?}", g.0, g.1.unwrap(), g.2.unwrap()))
+        .collect();
+    assert_eq!(glyphs, vec!["a White Black", "b LightRed Blue", "c LightRed Blue",
+                            "d White Black", "e Black White"]);
+}
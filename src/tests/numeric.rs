@@ -0,0 +1,41 @@
+use irc::{Command, CommandParser};
+use term::joins::PendingJoins;
+
+fn parse(line: &str) -> Command {
+    CommandParser::new().parse(&format!("{}\r\n", line).into_bytes())
+}
+
+#[test]
+fn numeric_describe() {
+    assert_eq!(parse(":irc.test 433 * Nick :Nickname is already in use").describe(),
+               "Nickname Nick is already in use");
+    assert_eq!(parse(":irc.test 001 me :Welcome to the network, me").describe(),
+               "Welcome to the network, me");
+    assert_eq!(parse(":irc.test 252 me 3 :operator(s) online").describe(), "3 operator(s) online");
+    assert_eq!(parse(":irc.test 421 me FOO :Unknown command").describe(), "FOO: Unknown command");
+    assert_eq!(parse(":irc.test 332 me #mass :Hello").describe(), "Topic for #mass: Hello");
+    assert_eq!(parse(":nick!user@host PRIVMSG #mass :Hi").describe(), ":nick!user@host PRIVMSG #mass :Hi");
+}
+
+#[test]
+fn numeric_error_channel() {
+    let error = parse(":irc.test 474 me #mass :Cannot join channel (+b)");
+    assert_eq!(error.error_channel(), Some("#mass"));
+    assert_eq!(error.describe(), "#mass: Cannot join channel (+b)");
+    assert_eq!(parse(":irc.test 401 me nick :No such nick").error_channel(), None);
+}
+
+#[test]
+fn numeric_error_tab() {
+    let mut joins = PendingJoins::new();
+    let open = vec!["#Mass".to_string(), "#other".to_string()];
+    joins.add("#new,#gone", Some("#other".to_string()));
+    joins.add("#server", None);
+
+    let error = parse(":irc.test 474 me #new :Cannot join channel (+b)");
+    assert_eq!(joins.error_tab(error.error_channel().unwrap(), &open), Some("#other".to_string()));
+    assert_eq!(joins.error_tab("#new", &open), None);
+    assert_eq!(joins.error_tab("#mass", &open), Some("#Mass".to_string()));
+    assert_eq!(joins.error_tab("#server", &open), None);
+    assert_eq!(joins.error_tab("#gone", &["#Mass".to_string()]), None);
+}
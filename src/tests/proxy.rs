@@ -0,0 +1,120 @@
+use irc::{Address, Proxy, ProxyKind};
+use tokio_core::io::{read_exact, write_all};
+use tokio_core::reactor::Core;
+use futures::Future;
+
+use std::io::{Read, Write, BufRead, BufReader};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+
+/// A server that echoes back one line, standing in for the IRC server.
+fn echo_server() -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut line = String::new();
+        BufReader::new(stream.try_clone().unwrap()).read_line(&mut line).unwrap();
+        (&stream).write_all(line.as_bytes()).unwrap();
+    });
+    port
+}
+
+fn pipe(a: TcpStream, b: TcpStream) {
+    let (mut a2, mut b2) = (a.try_clone().unwrap(), b.try_clone().unwrap());
+    let (mut a, mut b) = (a, b);
+    thread::spawn(move || { let _ = ::std::io::copy(&mut a, &mut b2); });
+    let _ = ::std::io::copy(&mut b, &mut a2);
+}
+
+/// A SOCKS5 proxy that requires `user`/`pass` and only accepts domain name requests.
+fn socks5_proxy() -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    thread::spawn(move || {
+        let (mut client, _) = listener.accept().unwrap();
+        let mut buf = [0u8; 2];
+        client.read_exact(&mut buf).unwrap();
+        let mut methods = vec![0u8; buf[1] as usize];
+        client.read_exact(&mut methods).unwrap();
+        assert!(methods.contains(&2));
+        client.write_all(&[5, 2]).unwrap();
+
+        let mut auth = vec![0u8; 2];
+        client.read_exact(&mut auth).unwrap();
+        let mut user = vec![0u8; auth[1] as usize];
+        client.read_exact(&mut user).unwrap();
+        client.read_exact(&mut auth[..1]).unwrap();
+        let mut pass = vec![0u8; auth[0] as usize];
+        client.read_exact(&mut pass).unwrap();
+        assert_eq!((&*user, &*pass), (&b"user"[..], &b"pass"[..]));
+        client.write_all(&[1, 0]).unwrap();
+
+        let mut request = [0u8; 5];
+        client.read_exact(&mut request).unwrap();
+        assert_eq!(&request[..4], &[5, 1, 0, 3]);
+        let mut host = vec![0u8; request[4] as usize + 2];
+        client.read_exact(&mut host).unwrap();
+        let len = host.len();
+        let port = ((host[len - 2] as u16) << 8) | host[len - 1] as u16;
+        assert_eq!(&host[..len - 2], b"localhost");
+
+        let server = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        client.write_all(&[5, 0, 0, 1, 127, 0, 0, 1, 0, 0]).unwrap();
+        pipe(client, server);
+    });
+    port
+}
+
+fn http_proxy() -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    thread::spawn(move || {
+        let (client, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(client.try_clone().unwrap());
+        let mut request = String::new();
+        reader.read_line(&mut request).unwrap();
+        let target = request.split_whitespace().nth(1).unwrap().to_string();
+        assert!(request.starts_with("CONNECT "));
+        loop {
+            let mut header = String::new();
+            reader.read_line(&mut header).unwrap();
+            if header == "\r\n" { break; }
+        }
+
+        let server = TcpStream::connect(&*target).unwrap();
+        (&client).write_all(b"HTTP/1.1 200 Connection established\r\n\r\n").unwrap();
+        pipe(client, server);
+    });
+    port
+}
+
+fn roundtrip(proxy: Proxy, target: Address) -> Vec<u8> {
+    let mut core = Core::new().unwrap();
+    let handle = core.handle();
+    let c = proxy.connect(&target, &handle)
+        .and_then(|stream| write_all(stream, b"PING :proxy\r\n".to_vec()))
+        .and_then(|(stream, _)| read_exact(stream, vec![0u8; 13]))
+        .map(|(_, buf)| buf);
+    core.run(c).unwrap()
+}
+
+#[test]
+fn proxy_socks5() {
+    let server = echo_server();
+    let mut proxy = Proxy::new(ProxyKind::Socks5, "127.0.0.1".to_string(), socks5_proxy());
+    proxy.username = Some("user".to_string());
+    proxy.password = Some("pass".to_string());
+
+    let target = Address::new("localhost".to_string(), server, false);
+    assert_eq!(roundtrip(proxy, target), b"PING :proxy\r\n");
+}
+
+#[test]
+fn proxy_http() {
+    let server = echo_server();
+    let proxy = Proxy::new(ProxyKind::Http, "127.0.0.1".to_string(), http_proxy());
+
+    let target = Address::new("127.0.0.1".to_string(), server, false);
+    assert_eq!(roundtrip(proxy, target), b"PING :proxy\r\n");
+}
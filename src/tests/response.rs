@@ -0,0 +1,63 @@
+use irc::{Capabilities, ClientEvent, CommandType, UserCommand};
+use tests::mock::MockServer;
+
+#[test]
+fn response_labeled() {
+    let mut server = MockServer::new();
+    let client = server.client(Capabilities::new().request("batch").request("labeled-response"));
+    server.expect("CAP LS :302");
+    server.send(":irc.test CAP * LS :batch labeled-response");
+    server.expect("CAP REQ :batch labeled-response");
+    server.send(":irc.test CAP * ACK :batch labeled-response");
+    server.expect("CAP :END");
+    let client = match server.event(client) {
+        (ClientEvent::Capabilities(_), client) => client,
+        _ => panic!("Expected the enabled capabilities"),
+    };
+
+    let whois = client.request(UserCommand::WhoIs("mass".to_string()));
+    let topic = client.request(UserCommand::GetTopic("#mass".to_string()));
+    server.expect("@label=1 WHOIS :mass");
+    server.expect("@label=2 TOPIC :#mass");
+
+    server.send("@label=2 :irc.test 331 me #mass :No topic is set");
+    server.send("@label=1 :irc.test BATCH +w labeled-response");
+    server.send("@batch=w :irc.test 311 me mass ~mass host * :Mass");
+    server.send("@batch=w :irc.test 318 me mass :End of /WHOIS list");
+    server.send(":irc.test BATCH -w");
+    server.send(":other!u@h PRIVMSG #mass :unrelated");
+
+    let replies = server.response(topic);
+    assert_eq!(replies.len(), 1);
+    assert_eq!(replies[0].command, CommandType::Rpl_NoTopic);
+
+    let replies = server.response(whois);
+    let types: Vec<_> = replies.iter().map(|x| x.command).collect();
+    assert_eq!(types, vec![CommandType::Rpl_WhoIsUser, CommandType::Rpl_EndOfWhoIs]);
+
+    match server.event(client).0 {
+        ClientEvent::ChannelMessage(_, _, ref msg, _) => assert_eq!(msg, "unrelated"),
+        _ => panic!("Expected the replies to be kept out of the events"),
+    }
+}
+
+#[test]
+fn response_heuristic() {
+    let mut server = MockServer::new();
+    let client = server.client(Capabilities::new());
+
+    let first = client.request(UserCommand::WhoIs("one".to_string()));
+    let second = client.request(UserCommand::WhoIs("two".to_string()));
+    server.expect("WHOIS :one");
+    server.expect("WHOIS :two");
+
+    server.send(":irc.test 311 me one ~one host * :One");
+    server.send(":irc.test 401 me two :No such nick");
+    server.send(":irc.test 318 me one :End of /WHOIS list");
+    server.send(":irc.test 318 me two :End of /WHOIS list");
+
+    let replies = server.response(second);
+    let types: Vec<_> = replies.iter().map(|x| x.command).collect();
+    assert_eq!(types, vec![CommandType::Err_NoSuchNick, CommandType::Rpl_EndOfWhoIs]);
+    assert_eq!(server.response(first).len(), 2);
+}
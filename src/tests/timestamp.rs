@@ -0,0 +1,28 @@
+use irc::{CommandParser, Timestamp};
+
+#[test]
+fn timestamp_parse() {
+    let time = Timestamp::parse("2011-10-19T16:40:51.620Z").unwrap();
+    assert_eq!(time.millis(), 1319042451620);
+    assert_eq!(time.to_string(), "2011-10-19T16:40:51.620Z");
+
+    assert_eq!(Timestamp::parse("2000-02-29T00:00:00Z").unwrap().to_string(), "2000-02-29T00:00:00.000Z");
+    assert_eq!(Timestamp::parse("1969-12-31T23:59:59.5Z").unwrap().millis(), -500);
+    assert_eq!(Timestamp::from_millis(-500).to_string(), "1969-12-31T23:59:59.500Z");
+
+    assert!(Timestamp::parse("2011-10-19 16:40:51Z").is_none());
+    assert!(Timestamp::parse("2011-13-19T16:40:51Z").is_none());
+    assert!(Timestamp::parse("2011-10-19T16:40:51").is_none());
+    assert!(Timestamp::parse("2011-10-19T16:40:51.Z").is_none());
+}
+
+#[test]
+fn timestamp_tag() {
+    let parser = CommandParser::new();
+    let cmd = parser.parse(&b"@time=2011-10-19T16:40:51.620Z :nick PRIVMSG #mass :hi\r\n".to_vec());
+    assert_eq!(cmd.time(), Timestamp::parse("2011-10-19T16:40:51.620Z"));
+
+    let time = cmd.time().unwrap();
+    assert_eq!(time.format("100%% %Y"), format!("100% {}", time.format("%Y")));
+    assert!(time.same_day(&Timestamp::from_millis(time.millis() + 1)));
+}
@@ -0,0 +1,55 @@
+use irc::{Address, Capabilities, Client, ClientEvent, Transport, UserCommand};
+use tokio_core::reactor::Core;
+use futures::{Sink, Stream};
+
+use std::env;
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::UnixListener;
+use std::thread;
+
+fn first_event(core: &mut Core, client: Client) -> (Option<ClientEvent>, Client) {
+    match core.run(client.into_future()) {
+        Ok(x) => x,
+        Err(_) => panic!("Client failed"),
+    }
+}
+
+#[test]
+fn transport_command() {
+    let mut core = Core::new().unwrap();
+    // The host reaches the command as one word, not as shell code.
+    let address = Address::new("a;b".to_string(), 6667, false);
+    let transport = Transport::Command("[ %h = 'a;b' ] && [ %p = 6667 ] && printf ':irc.test 001 mass :Welcome\\r\\n'; cat > /dev/null".to_string());
+    let client = Client::spawn(address, &transport, Capabilities::new(), &core.handle());
+
+    match first_event(&mut core, client).0 {
+        Some(ClientEvent::Connected) => {},
+        _ => panic!("Expected RPL_WELCOME from the proxy command"),
+    }
+}
+
+#[test]
+fn transport_unix() {
+    let path = env::temp_dir().join("mass-irc-transport-test.sock");
+    let _ = fs::remove_file(&path);
+    let listener = UnixListener::bind(&path).unwrap();
+    thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut line = String::new();
+        BufReader::new(stream.try_clone().unwrap()).read_line(&mut line).unwrap();
+        assert_eq!(line, "NICK :mass\r\n");
+        (&stream).write_all(b":irc.test 001 mass :Welcome\r\n").unwrap();
+    });
+
+    let mut core = Core::new().unwrap();
+    let address = Address::new("bouncer".to_string(), 6667, false);
+    let client = Client::spawn(address, &Transport::Unix(path.clone()), Capabilities::new(), &core.handle());
+    let client = core.run(client.send(UserCommand::Nick("mass".to_string()))).unwrap();
+
+    match first_event(&mut core, client).0 {
+        Some(ClientEvent::Connected) => {},
+        _ => panic!("Expected RPL_WELCOME over the Unix socket"),
+    }
+    let _ = fs::remove_file(&path);
+}
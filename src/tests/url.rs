@@ -0,0 +1,29 @@
+use irc::{Address, IrcUrl};
+
+#[test]
+fn url_full() {
+    let url = IrcUrl::parse("ircs://mass@irc.libera.chat:7000/#rust,%23secret,offtopic?,hunter2").unwrap();
+    assert_eq!(url.address(), Address::new("irc.libera.chat".to_string(), 7000, true));
+    assert_eq!(url.nick, Some("mass".to_string()));
+    assert_eq!(url.channels, vec![("#rust".to_string(), None),
+                                  ("#secret".to_string(), Some("hunter2".to_string())),
+                                  ("#offtopic".to_string(), None)]);
+}
+
+#[test]
+fn url_minimal() {
+    let url = IrcUrl::parse("irc://[::1]").unwrap();
+    assert_eq!(url.address(), Address::new("::1".to_string(), 6667, false));
+    assert_eq!(url.port, None);
+    assert!(url.matches(&Address::new("::1".to_string(), 6697, true)));
+    assert!(!IrcUrl::parse("irc://[::1]:6667").unwrap().matches(&Address::new("::1".to_string(), 6697, true)));
+    assert_eq!(url.nick, None);
+    assert!(url.channels.is_empty());
+
+    let url = IrcUrl::parse("irc://irc.test/chan,needkey").unwrap();
+    assert_eq!(url.channels, vec![("#chan".to_string(), None)]);
+    assert!(IrcUrl::parse("irc://irc.test/mass,isnick").unwrap().channels.is_empty());
+
+    assert!(IrcUrl::parse("http://irc.test/").is_none());
+    assert!(IrcUrl::parse("irc://irc.test:port/").is_none());
+}
@@ -0,0 +1,56 @@
+use irc::{Capabilities, ClientEvent, UserCommand, Whois};
+use tests::mock::MockServer;
+
+#[test]
+fn whois_result() {
+    let mut server = MockServer::new();
+    let client = server.client(Capabilities::new());
+    let client = server.user(client, UserCommand::WhoIs("Mass".to_string()));
+    server.expect("WHOIS :Mass");
+
+    server.send(":irc.test 301 me other :Not part of a WHOIS");
+    server.send(":irc.test 311 me Mass ~mass example.com * :Mass Effect");
+    server.send(":irc.test 319 me Mass :@#mass +#effect");
+    server.send(":irc.test 312 me Mass irc.test :Test server");
+    server.send(":irc.test 301 me Mass :Gone");
+    server.send(":irc.test 330 me Mass mass :is logged in as");
+    server.send(":irc.test 671 me Mass :is using a secure connection");
+    server.send(":irc.test 317 me Mass 95 1483326245 :seconds idle, signon time");
+    server.send(":irc.test 318 me mass :End of /WHOIS list");
+
+    match server.event(client).0 {
+        ClientEvent::WhoisResult(whois) => {
+            assert_eq!(whois.nick, "Mass");
+            assert_eq!(whois.user, Some("~mass".to_string()));
+            assert_eq!(whois.host, Some("example.com".to_string()));
+            assert_eq!(whois.realname, Some("Mass Effect".to_string()));
+            assert_eq!(whois.server, Some("irc.test".to_string()));
+            assert_eq!(whois.channels, vec!["@#mass".to_string(), "+#effect".to_string()]);
+            assert_eq!(whois.account, Some("mass".to_string()));
+            assert_eq!(whois.away, Some("Gone".to_string()));
+            assert_eq!(whois.idle, Some(95));
+            assert_eq!(whois.signon.map(|x| x.to_string()), Some("2017-01-02T03:04:05.000Z".to_string()));
+            assert!(whois.secure);
+            assert!(!whois.operator);
+        },
+        _ => panic!("Expected the collected WHOIS"),
+    }
+}
+
+#[test]
+fn whois_request() {
+    let mut server = MockServer::new();
+    let client = server.client(Capabilities::new());
+    let response = client.request(UserCommand::WhoIs("Mass".to_string()));
+    server.expect("WHOIS :Mass");
+
+    server.send(":irc.test 311 me Mass ~mass example.com * :Mass Effect");
+    server.send(":irc.test 330 me Mass mass :is logged in as");
+    server.send(":irc.test 671 me Mass :is using a secure connection");
+    server.send(":irc.test 318 me Mass :End of /WHOIS list");
+
+    let whois = Whois::from_replies(&server.response(response)).expect("Expected a WHOIS");
+    assert_eq!(whois.nick, "Mass");
+    assert_eq!(whois.account, Some("mass".to_string()));
+    assert!(whois.secure);
+}